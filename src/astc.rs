@@ -1,13 +1,22 @@
 use crate::{
+    Color32,
+    Error,
+    Image,
     Result,
     bitreader::BitReaderLsb,
     bitwriter::{
         BitWriterLsb,
         BitWriterMsbRevBytes,
     },
-    uastc,
+    etc::{self, Selector},
+    mask, uastc,
 };
 
+use std::convert::TryInto;
+use std::sync::OnceLock;
+
+pub const ASTC_BLOCK_SIZE: usize = 16;
+
 pub fn convert_block_from_uastc(bytes: &[u8], output: &mut [u8]) {
     match convert_block_from_uastc_result(bytes, output) {
         Ok(_) => (),
@@ -24,30 +33,13 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
 
     if mode.id == 8 {
         let rgba = uastc::decode_mode8_rgba(reader);
-
-        // 0..=8: void-extent signature
-        // 9: 0 means endpoints are UNORM16, 1 means FP16
-        // 10..=11: reserved, must be 1
-        writer.write_u16(12, 0b1101_1111_1100);
-
-        // 4x 13 bits of void extent coordinates, we don't calculate
-        // them yet so we set them to all 1s to get them ignored
-        writer.write_u32(20, 0x000F_FFFF);
-        writer.write_u32(32, 0xFFFF_FFFF);
-
-        let (r, g, b, a) = (rgba[0] as u16, rgba[1] as u16, rgba[2] as u16, rgba[3] as u16);
-
-        writer.write_u16(16, r << 8 | r);
-        writer.write_u16(16, g << 8 | g);
-        writer.write_u16(16, b << 8 | b);
-        writer.write_u16(16, a << 8 | a);
-
+        write_void_extent_block(writer, rgba);
         return Ok(());
     }
 
     uastc::skip_trans_flags(reader, mode);
 
-    let compsel = uastc::decode_compsel(reader, mode);
+    let compsel = uastc::decode_compsel(reader, mode)?;
     let pat = uastc::decode_pattern_index(reader, mode)?;
 
     let endpoint_count = mode.endpoint_count();
@@ -96,51 +88,7 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
         writer.write_u8(4, cem);
     }
 
-    {   // Write endpoints
-        let bise_range = BISE_RANGES[mode.endpoint_range_index as usize];
-        let bit_count = bise_range.bits as usize;
-
-        if bise_range.quints > 0 {
-            for chunk in quant_endpoints.chunks(3) {
-                let q_lut_id = chunk.iter()
-                    .rev()
-                    .fold(0, |acc, qe| {
-                        acc * 5 + qe.trit_quint
-                    });
-                let q = ASTC_QUINT_ENCODE_LUT[q_lut_id as usize];
-                writer.write_u8(bit_count, chunk.get(0).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(3, q);
-                writer.write_u8(bit_count, chunk.get(1).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(2, q >> 3);
-                writer.write_u8(bit_count, chunk.get(2).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(2, q >> 5);
-            }
-        } else if bise_range.trits > 0 {
-            for chunk in quant_endpoints.chunks(5) {
-                let t_lut_id = chunk.iter()
-                    .rev()
-                    .fold(0, |acc, qe| {
-                        acc * 3 + qe.trit_quint
-                    });
-                let t = ASTC_TRIT_ENCODE_LUT[t_lut_id as usize];
-                writer.write_u8(bit_count, chunk.get(0).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(2, t);
-                writer.write_u8(bit_count, chunk.get(1).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(2, t >> 2);
-                writer.write_u8(bit_count, chunk.get(2).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(1, t >> 4);
-                writer.write_u8(bit_count, chunk.get(3).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(2, t >> 5);
-                writer.write_u8(bit_count, chunk.get(4).map(|qe| qe.bits).unwrap_or(0));
-                writer.write_u8(1, t >> 7);
-            }
-        } else {
-            let bit_count = bise_range.bits as usize;
-            for qe in &quant_endpoints {
-                writer.write_u8(bit_count, qe.bits);
-            }
-        }
-    }
+    write_endpoints(writer, &quant_endpoints, mode.endpoint_range_index);
 
     {   // Write the weights and CCS which is filled from the end
         let writer_rev = &mut BitWriterMsbRevBytes::new(output);
@@ -181,6 +129,711 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
     Ok(())
 }
 
+/// Encodes an opaque ETC1S block, given by its four colors and selectors
+pub fn encode_etc1s_block(colors: &[Color32; 4], selector: &Selector, output: &mut [u8]) {
+    let writer = &mut BitWriterLsb::new(output);
+
+    let (lo, hi) = etc::selector_range(selector);
+    if lo == hi {
+        write_void_extent_block(writer, colors[lo]);
+        return;
+    }
+
+    let levels = &etc1s_endpoint_levels().rgb;
+    let (mut endpoints, mut weights) = etc::fit_selector_endpoints(colors, selector, &WEIGHTS_2, |v| levels.nearest[v as usize], |q| levels.values[q as usize]);
+    invert_blue_contraction(levels, &mut endpoints, &mut weights);
+
+    writer.write_u16(13, ETC1S_RGB_BLOCK_MODE_13);
+    writer.write_u8(4, CEM_LDR_RGB_DIRECT);
+
+    let mut quant_endpoints = [uastc::QuantEndpoint::default(); 6];
+    for (c, pair) in quant_endpoints.chunks_exact_mut(2).enumerate() {
+        pair[0] = levels.quant[endpoints[0][c] as usize];
+        pair[1] = levels.quant[endpoints[1][c] as usize];
+    }
+    write_endpoints(writer, &quant_endpoints, ETC1S_RGB_ENDPOINT_RANGE);
+
+    let writer_rev = &mut BitWriterMsbRevBytes::new(output);
+    for y in 0..4 {
+        for x in 0..4 {
+            writer_rev.write_u8_rev_bits(2, weights[selector.get_selector(x, y)]);
+        }
+    }
+}
+
+/// Encodes an ETC1S block with a block of the alpha slice, alpha goes to the second weight plane.
+///
+/// Alpha is taken from the green channel of the alpha block colors.
+pub fn encode_etc1s_block_with_alpha(colors: &[Color32; 4], selector: &Selector, alpha_colors: &[Color32; 4], alpha_selector: &Selector, output: &mut [u8]) {
+    let writer = &mut BitWriterLsb::new(output);
+
+    let (lo, hi) = etc::selector_range(selector);
+    let (alpha_lo, alpha_hi) = etc::selector_range(alpha_selector);
+    if lo == hi && alpha_lo == alpha_hi {
+        let mut rgba = colors[lo];
+        rgba[3] = alpha_colors[alpha_lo][1];
+        write_void_extent_block(writer, rgba);
+        return;
+    }
+
+    let levels = &etc1s_endpoint_levels().rgba;
+    let quantize = |v: i32| levels.nearest[v as usize];
+    let unquantize = |q: u8| levels.values[q as usize];
+
+    let (mut endpoints, mut weights) = etc::fit_selector_endpoints(colors, selector, &WEIGHTS_2, quantize, unquantize);
+    invert_blue_contraction(levels, &mut endpoints, &mut weights);
+
+    let alphas = alpha_colors.map(|color| Color32::new(color[1], 0, 0, 0));
+    let (alpha_endpoints, alpha_weights) = etc::fit_selector_endpoints(&alphas, alpha_selector, &WEIGHTS_2, quantize, unquantize);
+
+    writer.write_u16(13, ETC1S_RGBA_BLOCK_MODE_13);
+    writer.write_u8(4, CEM_LDR_RGBA_DIRECT);
+
+    let mut quant_endpoints = [uastc::QuantEndpoint::default(); 8];
+    for (c, pair) in quant_endpoints.chunks_exact_mut(2).enumerate() {
+        let endpoints = if c < 3 { [endpoints[0][c], endpoints[1][c]] } else { [alpha_endpoints[0][0], alpha_endpoints[1][0]] };
+        pair[0] = levels.quant[endpoints[0] as usize];
+        pair[1] = levels.quant[endpoints[1] as usize];
+    }
+    write_endpoints(writer, &quant_endpoints, ETC1S_RGBA_ENDPOINT_RANGE);
+
+    // Weights of both planes are interleaved, followed by the component selector of the second plane
+    let writer_rev = &mut BitWriterMsbRevBytes::new(output);
+    for y in 0..4 {
+        for x in 0..4 {
+            writer_rev.write_u8_rev_bits(2, weights[selector.get_selector(x, y)]);
+            writer_rev.write_u8_rev_bits(2, alpha_weights[alpha_selector.get_selector(x, y)]);
+        }
+    }
+    writer_rev.write_u8(2, ALPHA_CHANNEL);
+}
+
+/// The decoder swaps the endpoints and applies blue contraction when the first endpoint is
+/// brighter, swap them beforehand to avoid it
+fn invert_blue_contraction(levels: &EndpointLevels, endpoints: &mut [Color32; 2], weights: &mut [u8; 4]) {
+    let sum = |endpoint: Color32| (0..3).map(|c| levels.values[endpoint[c] as usize] as u32).sum::<u32>();
+    if sum(endpoints[0]) > sum(endpoints[1]) {
+        endpoints.swap(0, 1);
+        for weight in weights.iter_mut() {
+            *weight = 3 - *weight;
+        }
+    }
+}
+
+/// Decodes an ASTC LDR block to RGBA pixels in raster order.
+///
+/// The footprint of the block is `block_width` x `block_height` texels, e.g. 4x4 for
+/// `Astc4x4Rgba` output. With `srgb` the color channels are interpolated like in the sRGB
+/// decode mode, the returned values stay sRGB encoded. Invalid blocks and blocks using HDR
+/// features decode to the error color, magenta.
+///
+/// Returns `Error::UnsupportedDimensions` if the footprint is not one of the 2D ASTC block sizes.
+pub fn decode_block(block: &[u8; 16], block_width: u32, block_height: u32, srgb: bool) -> Result<Vec<[u8; 4]>> {
+    check_footprint(block_width, block_height)?;
+    Ok(decode_block_unchecked(block, block_width, block_height, srgb))
+}
+
+/// Decodes an ASTC LDR image, e.g. the `Astc4x4Rgba` output of transcoding, to RGBA bytes laid out like `Rgba32` output.
+///
+/// The arguments are the same as for [`decode_astc_block`](crate::decode_astc_block).
+pub fn decode_image(image: &Image<u8>, block_width: u32, block_height: u32, srgb: bool) -> Result<Image<u8>> {
+    check_footprint(block_width, block_height)?;
+    Ok(crate::decode_block_image(image, ASTC_BLOCK_SIZE, block_width as usize, block_height as usize, |block| {
+        decode_block_unchecked(block.try_into().unwrap(), block_width, block_height, srgb)
+    }))
+}
+
+fn check_footprint(block_width: u32, block_height: u32) -> Result<()> {
+    if FOOTPRINTS.contains(&(block_width, block_height)) {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedDimensions { width: block_width, height: block_height })
+    }
+}
+
+/// Decodes a block of a footprint already checked by `check_footprint`
+fn decode_block_unchecked(block: &[u8; 16], block_width: u32, block_height: u32, srgb: bool) -> Vec<[u8; 4]> {
+    match decode_block_result(block, block_width as usize, block_height as usize, srgb) {
+        Ok(pixels) => pixels,
+        _ => vec![ERROR_COLOR; (block_width * block_height) as usize],
+    }
+}
+
+fn decode_block_result(block: &[u8; 16], block_width: usize, block_height: usize, srgb: bool) -> Result<Vec<[u8; 4]>> {
+    let bits = u128::from_le_bytes(*block);
+    let read = |pos: usize, count: usize| (bits >> pos) as u32 & mask!(count as u32);
+
+    let texel_count = block_width * block_height;
+
+    if read(0, 9) == 0x1FC {
+        // HDR void-extent blocks are errors in LDR, and the reserved bits 10 and 11 must be 1
+        if read(9, 1) != 0 || read(10, 2) != 0b11 {
+            return Err(Error::InvalidBlock);
+        }
+        let (min_s, max_s, min_t, max_t) = (read(12, 13), read(25, 13), read(38, 13), read(51, 13));
+        let all_ones = [min_s, max_s, min_t, max_t].iter().all(|&c| c == 0x1FFF);
+        if !all_ones && (min_s >= max_s || min_t >= max_t) {
+            return Err(Error::InvalidBlock);
+        }
+        let mut rgba = [0u8; 4];
+        for (c, value) in rgba.iter_mut().enumerate() {
+            *value = (read(64 + 16 * c, 16) >> 8) as u8;
+        }
+        return Ok(vec![rgba; texel_count]);
+    }
+
+    let block_mode = decode_block_mode(read(0, 11))?;
+    let (grid_width, grid_height) = (block_mode.grid_width, block_mode.grid_height);
+    let plane_count = if block_mode.dual_plane { 2 } else { 1 };
+    let weight_count = grid_width * grid_height * plane_count;
+    if grid_width > block_width || grid_height > block_height || weight_count > MAX_WEIGHT_COUNT {
+        return Err(Error::InvalidBlock);
+    }
+    let weight_bits = bise_bit_count(block_mode.weight_range, weight_count);
+    if !(24..=96).contains(&weight_bits) {
+        return Err(Error::InvalidBlock);
+    }
+
+    let partition_count = read(11, 2) as usize + 1;
+    if partition_count == 4 && block_mode.dual_plane {
+        return Err(Error::InvalidBlock);
+    }
+
+    let mut below_weights_pos = 128 - weight_bits;
+    let mut cems = [0u8; 4];
+    let (endpoints_pos, cem_bits) = if partition_count == 1 {
+        cems[0] = read(13, 4) as u8;
+        (17, 4)
+    } else {
+        // The CEM field continues below the weights when the partitions use different CEMs
+        let cem = read(23, 6);
+        if cem & 0b11 == 0 {
+            for partition_cem in cems[..partition_count].iter_mut() {
+                *partition_cem = (cem >> 2) as u8;
+            }
+            (29, 6)
+        } else {
+            let extra_bit_count = 3 * partition_count - 4;
+            below_weights_pos -= extra_bit_count;
+            let cem = cem | read(below_weights_pos, extra_bit_count) << 6;
+            let base_class = (cem & 0b11) - 1;
+            for (i, partition_cem) in cems[..partition_count].iter_mut().enumerate() {
+                let class = base_class + ((cem >> (2 + i)) & 1);
+                let mode = (cem >> (2 + partition_count + 2 * i)) & 0b11;
+                *partition_cem = (class << 2 | mode) as u8;
+            }
+            (29, 6 + extra_bit_count)
+        }
+    };
+    let cems = &cems[..partition_count];
+
+    if cems.iter().any(|&cem| CEM_HDR.contains(&cem)) {
+        return Err(Error::InvalidBlock);
+    }
+
+    let value_count: usize = cems.iter().map(|&cem| ((cem >> 2) as usize + 1) * 2).sum();
+    if value_count > MAX_ENDPOINT_VALUE_COUNT {
+        return Err(Error::InvalidBlock);
+    }
+
+    // Endpoints use the largest range which fits in the remaining bits
+    let ccs_bits = if block_mode.dual_plane { 2 } else { 0 };
+    let endpoint_bits = (128 - weight_bits - ccs_bits).saturating_sub(13 + cem_bits + if partition_count > 1 { 10 } else { 0 });
+    let endpoint_range = (0..BISE_RANGES.len() as u8).rev()
+        .find(|&range| bise_bit_count(range, value_count) <= endpoint_bits)
+        .filter(|&range| range >= MIN_ENDPOINT_RANGE)
+        .ok_or(Error::InvalidBlock)?;
+
+    let mut endpoint_values = [0u8; MAX_ENDPOINT_VALUE_COUNT];
+    {
+        let reader = &mut BitReaderLsb::new(block);
+        reader.remove(endpoints_pos);
+        let mut quant = [uastc::QuantEndpoint::default(); MAX_ENDPOINT_VALUE_COUNT];
+        decode_bise(reader, endpoint_range, &mut quant[..value_count]);
+        for (value, quant) in endpoint_values.iter_mut().zip(quant[..value_count].iter()) {
+            *value = uastc::unquant_endpoint(*quant, endpoint_range);
+        }
+    }
+
+    let mut endpoints = [[[0u8; 4]; 2]; 4];
+    let mut values = &endpoint_values[..value_count];
+    for (&cem, pair) in cems.iter().zip(endpoints.iter_mut()) {
+        let count = ((cem >> 2) as usize + 1) * 2;
+        *pair = decode_endpoint_pair(cem, &values[..count]);
+        values = &values[count..];
+    }
+
+    // Weights are stored from the end of the block with reversed bits
+    let mut weights = [0u8; MAX_WEIGHT_COUNT];
+    {
+        let reversed = bits.reverse_bits().to_le_bytes();
+        let reader = &mut BitReaderLsb::new(&reversed);
+        let mut quant = [uastc::QuantEndpoint::default(); MAX_WEIGHT_COUNT];
+        decode_bise(reader, block_mode.weight_range, &mut quant[..weight_count]);
+        for (weight, quant) in weights.iter_mut().zip(quant[..weight_count].iter()) {
+            *weight = unquant_weight(*quant, block_mode.weight_range);
+        }
+    }
+
+    let ccs = if block_mode.dual_plane { read(below_weights_pos - 2, 2) as usize } else { 4 };
+    let partition_index = read(13, 10);
+    let small_block = texel_count < 31;
+
+    // Fixed point steps between the texels in the weight grid, as in the weight infill procedure
+    let step_x = (1024 + block_width / 2) / (block_width - 1);
+    let step_y = (1024 + block_height / 2) / (block_height - 1);
+
+    let mut pixels = vec![[0u8; 4]; texel_count];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (i % block_width, i / block_width);
+
+        let partition = if partition_count > 1 {
+            select_partition(partition_index, x as u32, y as u32, partition_count as u32, small_block)
+        } else {
+            0
+        };
+        let [e0, e1] = endpoints[partition];
+
+        let grid_x = (step_x * x * (grid_width - 1) + 32) >> 6;
+        let grid_y = (step_y * y * (grid_height - 1) + 32) >> 6;
+        let (fx, fy) = (grid_x & 0xF, grid_y & 0xF);
+        let (gx, gy) = (grid_x >> 4, grid_y >> 4);
+        let w11 = (fx * fy + 8) >> 4;
+        let texel_weights = [
+            (gx, gy, 16 + w11 - fx - fy),
+            (gx + 1, gy, fx - w11),
+            (gx, gy + 1, fy - w11),
+            (gx + 1, gy + 1, w11),
+        ];
+        let infill = |plane: usize| {
+            let sum: usize = texel_weights.iter()
+                .filter(|&&(_, _, factor)| factor > 0)
+                .map(|&(gx, gy, factor)| weights[(gy * grid_width + gx) * plane_count + plane] as usize * factor)
+                .sum();
+            ((sum + 8) >> 4) as u32
+        };
+        let plane_weights = [infill(0), if block_mode.dual_plane { infill(1) } else { 0 }];
+
+        for c in 0..4 {
+            let weight = plane_weights[(c == ccs) as usize];
+            let channel_srgb = srgb && c != ALPHA_CHANNEL as usize;
+            pixel[c] = uastc::astc_interpolate(e0[c] as u32, e1[c] as u32, weight, channel_srgb);
+        }
+    }
+
+    Ok(pixels)
+}
+
+struct BlockMode {
+    grid_width: usize,
+    grid_height: usize,
+    dual_plane: bool,
+    weight_range: u8,
+}
+
+/// Decodes the 11-bit block mode, see the 2D block mode layout table of the spec
+fn decode_block_mode(mode: u32) -> Result<BlockMode> {
+    let bit = |i: u32| (mode >> i) & 1;
+    let field = |i: u32, count: u32| (mode >> i) & mask!(count);
+
+    let (r, grid_width, grid_height, high_precision, dual_plane) = if field(0, 2) != 0 {
+        let (a, b) = (field(5, 2), field(7, 2));
+        let (width, height) = match field(2, 2) {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if bit(8) == 0 => (a + 2, bit(7) + 6),
+            _ => (bit(7) + 2, a + 2),
+        };
+        (field(0, 2) << 1 | bit(4), width, height, bit(9), bit(10))
+    } else {
+        if field(2, 2) == 0 {
+            return Err(Error::InvalidBlock);
+        }
+        let r = field(2, 2) << 1 | bit(4);
+        let a = field(5, 2);
+        match field(7, 2) {
+            0 => (r, 12, a + 2, bit(9), bit(10)),
+            1 => (r, a + 2, 12, bit(9), bit(10)),
+            2 => (r, a + 6, field(9, 2) + 6, 0, 0),
+            _ => match a {
+                0 => (r, 6, 10, bit(9), bit(10)),
+                1 => (r, 10, 6, bit(9), bit(10)),
+                _ => return Err(Error::InvalidBlock),
+            },
+        }
+    };
+
+    if r < 2 {
+        return Err(Error::InvalidBlock);
+    }
+
+    Ok(BlockMode {
+        grid_width: grid_width as usize,
+        grid_height: grid_height as usize,
+        dual_plane: dual_plane != 0,
+        // Ranges 2, 3, 4, 5, 6, 8 with low precision, 10, 12, 16, 20, 24, 32 with high precision
+        weight_range: (r - 2 + 6 * high_precision) as u8,
+    })
+}
+
+/// Number of bits taken by `count` BISE encoded values of the range
+fn bise_bit_count(range_index: u8, count: usize) -> usize {
+    let range = BISE_RANGES[range_index as usize];
+    let trit_quint_bits = if range.trits > 0 {
+        (8 * count).div_ceil(5)
+    } else if range.quints > 0 {
+        (7 * count).div_ceil(3)
+    } else {
+        0
+    };
+    count * range.bits as usize + trit_quint_bits
+}
+
+/// Reads BISE encoded values, the trit and quint bits are interleaved with the bits of the values
+fn decode_bise(reader: &mut BitReaderLsb, range_index: u8, values: &mut [uastc::QuantEndpoint]) {
+    let range = BISE_RANGES[range_index as usize];
+    let bit_count = range.bits as usize;
+
+    // Position and size of the trit or quint bits following each value
+    let (group_size, layout): (usize, &[(u8, usize)]) = if range.trits > 0 {
+        (5, &[(0, 2), (2, 2), (4, 1), (5, 2), (7, 1)])
+    } else if range.quints > 0 {
+        (3, &[(0, 3), (3, 2), (5, 2)])
+    } else {
+        (1, &[(0, 0)])
+    };
+
+    for group in values.chunks_mut(group_size) {
+        let mut packed = 0u8;
+        for (value, &(shift, count)) in group.iter_mut().zip(layout.iter()) {
+            value.bits = reader.read_u8(bit_count);
+            packed |= reader.read_u8(count) << shift;
+        }
+        if range.trits > 0 {
+            for (value, trit) in group.iter_mut().zip(decode_trits(packed).iter()) {
+                value.trit_quint = *trit;
+            }
+        } else if range.quints > 0 {
+            for (value, quint) in group.iter_mut().zip(decode_quints(packed).iter()) {
+                value.trit_quint = *quint;
+            }
+        }
+    }
+}
+
+/// Unquantizes a weight to 0..=64
+fn unquant_weight(quant: uastc::QuantEndpoint, range_index: u8) -> u8 {
+    let range = BISE_RANGES[range_index as usize];
+    let bits = quant.bits as u32;
+    let value = match (range.trits, range.quints, range.bits) {
+        (0, 0, bit_count) => {
+            // Replicate the bits to 6 bits
+            let mut value = 0;
+            let mut shift = 6 - bit_count as i32;
+            while shift > -(bit_count as i32) {
+                value |= if shift >= 0 { bits << shift } else { bits >> -shift };
+                shift -= bit_count as i32;
+            }
+            value
+        }
+        (1, 0, 0) => [0, 32, 63][quant.trit_quint as usize],
+        (0, 1, 0) => [0, 16, 32, 47, 63][quant.trit_quint as usize],
+        (trits, _, bit_count) => {
+            let a = if bits & 1 != 0 { 0x7F } else { 0 };
+            let (b, c) = match (trits, bit_count) {
+                (1, 1) => (0, 50),
+                (1, 2) => (0b100_0101 * (bits >> 1), 23),
+                (1, _) => (0b100_0010 * (bits >> 2 & 1) + 0b010_0001 * (bits >> 1 & 1), 11),
+                (_, 1) => (0, 28),
+                _ => (0b100_0010 * (bits >> 1), 13),
+            };
+            let t = (quant.trit_quint as u32 * c + b) ^ a;
+            (a & 0x20) | t >> 2
+        }
+    };
+    (if value > 32 { value + 1 } else { value }) as u8
+}
+
+/// Decodes the endpoint pair of a partition from its unquantized values
+fn decode_endpoint_pair(cem: u8, v: &[u8]) -> [[u8; 4]; 2] {
+    let mut values = [0i32; 8];
+    for (value, &v) in values.iter_mut().zip(v.iter()) {
+        *value = v as i32;
+    }
+    let v = values;
+
+    // Moves the top bit of the offset to the base and sign extends the remaining 6 bits
+    let bit_transfer_signed = |offset: i32, base: i32| {
+        let base = (base >> 1) | (offset & 0x80);
+        let offset = (offset >> 1) & 0x3F;
+        let offset = if offset & 0x20 != 0 { offset - 0x40 } else { offset };
+        (offset, base)
+    };
+    let blue_contract = |[r, g, b, a]: [i32; 4]| [(r + b) >> 1, (g + b) >> 1, b, a];
+    let clamp = |e: [i32; 4]| e.map(|c| c.clamp(0, 255) as u8);
+
+    let (e0, e1) = match cem {
+        CEM_LDR_LUMINANCE_DIRECT => ([v[0], v[0], v[0], 0xFF], [v[1], v[1], v[1], 0xFF]),
+        CEM_LDR_LUMINANCE_BASE_OFFSET => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(0xFF);
+            ([l0, l0, l0, 0xFF], [l1, l1, l1, 0xFF])
+        }
+        CEM_LDR_LUMINANCE_ALPHA_DIRECT => ([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        CEM_LDR_LUMINANCE_ALPHA_BASE_OFFSET => {
+            let (l_offset, l) = bit_transfer_signed(v[1], v[0]);
+            let (a_offset, a) = bit_transfer_signed(v[3], v[2]);
+            ([l, l, l, a], [l + l_offset, l + l_offset, l + l_offset, a + a_offset])
+        }
+        CEM_LDR_RGB_BASE_SCALE => {
+            let scaled = |c: i32| (c * v[3]) >> 8;
+            ([scaled(v[0]), scaled(v[1]), scaled(v[2]), 0xFF], [v[0], v[1], v[2], 0xFF])
+        }
+        CEM_LDR_RGB_DIRECT | CEM_LDR_RGBA_DIRECT => {
+            let (a0, a1) = if cem == CEM_LDR_RGBA_DIRECT { (v[6], v[7]) } else { (0xFF, 0xFF) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ([v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1])
+            } else {
+                (blue_contract([v[1], v[3], v[5], a1]), blue_contract([v[0], v[2], v[4], a0]))
+            }
+        }
+        CEM_LDR_RGB_BASE_OFFSET | CEM_LDR_RGBA_BASE_OFFSET => {
+            let (r_offset, r) = bit_transfer_signed(v[1], v[0]);
+            let (g_offset, g) = bit_transfer_signed(v[3], v[2]);
+            let (b_offset, b) = bit_transfer_signed(v[5], v[4]);
+            let (a_offset, a) = if cem == CEM_LDR_RGBA_BASE_OFFSET { bit_transfer_signed(v[7], v[6]) } else { (0, 0xFF) };
+            let base = [r, g, b, a];
+            let offset = [r + r_offset, g + g_offset, b + b_offset, a + a_offset];
+            if r_offset + g_offset + b_offset >= 0 {
+                (base, offset)
+            } else {
+                (blue_contract(offset), blue_contract(base))
+            }
+        }
+        CEM_LDR_RGB_BASE_SCALE_TWO_A => {
+            let scaled = |c: i32| (c * v[3]) >> 8;
+            ([scaled(v[0]), scaled(v[1]), scaled(v[2]), v[4]], [v[0], v[1], v[2], v[5]])
+        }
+        _ => unreachable!("HDR endpoint mode {}", cem),
+    };
+
+    [clamp(e0), clamp(e1)]
+}
+
+/// Returns the partition of a texel, a hash of the partition index and the texel coordinates
+fn select_partition(seed: u32, x: u32, y: u32, partition_count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block { (x << 1, y << 1) } else { (x, y) };
+
+    let seed = seed + (partition_count - 1) * 1024;
+    let rnum = hash52(seed);
+
+    let mut seeds = [0u32; 8];
+    for (i, s) in seeds.iter_mut().enumerate() {
+        let s_value = (rnum >> (4 * i)) & 0xF;
+        *s = s_value * s_value;
+    }
+
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (if seed & 2 != 0 { 4 } else { 5 }, if partition_count == 3 { 6 } else { 5 })
+    } else {
+        (if partition_count == 3 { 6 } else { 5 }, if seed & 2 != 0 { 4 } else { 5 })
+    };
+    for (i, s) in seeds.iter_mut().enumerate() {
+        *s >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+
+    // The z coordinate is always 0 for 2D blocks, so seeds 9 to 12 aren't needed
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if partition_count >= 3 { (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3F } else { 0 };
+    let d = if partition_count >= 4 { (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3F } else { 0 };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// 4x4 grid of 2-bit weights in a single plane, like UASTC mode 1
+const ETC1S_RGB_BLOCK_MODE_13: u16 = 0x0042;
+/// 4x4 grid of 2-bit weights in two planes, like UASTC mode 11
+const ETC1S_RGBA_BLOCK_MODE_13: u16 = 0x0442;
+
+/// 256 levels, the endpoints keep all 8 bits
+const ETC1S_RGB_ENDPOINT_RANGE: u8 = 20;
+/// 48 levels, the most which fit in the block next to two weight planes
+const ETC1S_RGBA_ENDPOINT_RANGE: u8 = 13;
+
+const CEM_LDR_LUMINANCE_DIRECT: u8 = 0;
+const CEM_LDR_LUMINANCE_BASE_OFFSET: u8 = 1;
+const CEM_LDR_LUMINANCE_ALPHA_DIRECT: u8 = 4;
+const CEM_LDR_LUMINANCE_ALPHA_BASE_OFFSET: u8 = 5;
+const CEM_LDR_RGB_BASE_SCALE: u8 = 6;
+const CEM_LDR_RGB_DIRECT: u8 = 8;
+const CEM_LDR_RGB_BASE_OFFSET: u8 = 9;
+const CEM_LDR_RGB_BASE_SCALE_TWO_A: u8 = 10;
+const CEM_LDR_RGBA_DIRECT: u8 = 12;
+const CEM_LDR_RGBA_BASE_OFFSET: u8 = 13;
+const CEM_HDR: [u8; 6] = [2, 3, 7, 11, 14, 15];
+
+/// Endpoint ranges below 6 levels are invalid
+const MIN_ENDPOINT_RANGE: u8 = 4;
+const MAX_ENDPOINT_VALUE_COUNT: usize = 18;
+const MAX_WEIGHT_COUNT: usize = 64;
+
+/// Color of invalid blocks, and of blocks which use HDR features
+const ERROR_COLOR: [u8; 4] = [0xFF, 0, 0xFF, 0xFF];
+
+/// 2D block footprints
+static FOOTPRINTS: [(u32, u32); 14] = [
+    (4, 4), (5, 4), (5, 5), (6, 5), (6, 6), (8, 5), (8, 6),
+    (8, 8), (10, 5), (10, 6), (10, 8), (10, 10), (12, 10), (12, 12),
+];
+
+const ALPHA_CHANNEL: u8 = 3;
+
+/// Unquantized values of 2-bit weights
+const WEIGHTS_2: [u8; 4] = [0, 21, 43, 64];
+
+/// Quantization levels of an endpoint range, ordered by their unquantized values
+struct EndpointLevels {
+    values: Vec<u8>,
+    quant: Vec<uastc::QuantEndpoint>,
+    /// Index of the closest level for each value
+    nearest: Vec<u8>,
+}
+
+impl EndpointLevels {
+    fn new(range_index: u8) -> Self {
+        let range = BISE_RANGES[range_index as usize];
+        let trit_quint_count = if range.trits > 0 { 3 } else if range.quints > 0 { 5 } else { 1 };
+
+        let mut levels = Vec::new();
+        for trit_quint in 0..trit_quint_count {
+            for bits in 0..1u16 << range.bits {
+                let quant = uastc::QuantEndpoint { trit_quint, bits: bits as u8 };
+                levels.push((uastc::unquant_endpoint(quant, range_index), quant));
+            }
+        }
+        levels.sort_by_key(|&(value, _)| value);
+
+        let values: Vec<u8> = levels.iter().map(|&(value, _)| value).collect();
+        let nearest = (0..=255u8)
+            .map(|v| {
+                (0..values.len())
+                    .min_by_key(|&i| (values[i] as i32 - v as i32).abs())
+                    .unwrap() as u8
+            })
+            .collect();
+
+        Self {
+            values,
+            quant: levels.iter().map(|&(_, quant)| quant).collect(),
+            nearest,
+        }
+    }
+}
+
+struct Etc1sEndpointLevels {
+    rgb: EndpointLevels,
+    rgba: EndpointLevels,
+}
+
+fn etc1s_endpoint_levels() -> &'static Etc1sEndpointLevels {
+    static LEVELS: OnceLock<Etc1sEndpointLevels> = OnceLock::new();
+
+    LEVELS.get_or_init(|| Etc1sEndpointLevels {
+        rgb: EndpointLevels::new(ETC1S_RGB_ENDPOINT_RANGE),
+        rgba: EndpointLevels::new(ETC1S_RGBA_ENDPOINT_RANGE),
+    })
+}
+
+fn write_void_extent_block(writer: &mut BitWriterLsb, rgba: Color32) {
+    // 0..=8: void-extent signature
+    // 9: 0 means endpoints are UNORM16, 1 means FP16
+    // 10..=11: reserved, must be 1
+    writer.write_u16(12, 0b1101_1111_1100);
+
+    // 4x 13 bits of void extent coordinates, we don't calculate
+    // them yet so we set them to all 1s to get them ignored
+    writer.write_u32(20, 0x000F_FFFF);
+    writer.write_u32(32, 0xFFFF_FFFF);
+
+    let (r, g, b, a) = (rgba[0] as u16, rgba[1] as u16, rgba[2] as u16, rgba[3] as u16);
+
+    writer.write_u16(16, r << 8 | r);
+    writer.write_u16(16, g << 8 | g);
+    writer.write_u16(16, b << 8 | b);
+    writer.write_u16(16, a << 8 | a);
+}
+
+/// Writes BISE encoded endpoints
+fn write_endpoints(writer: &mut BitWriterLsb, quant_endpoints: &[uastc::QuantEndpoint], range_index: u8) {
+    let bise_range = BISE_RANGES[range_index as usize];
+    let bit_count = bise_range.bits as usize;
+
+    if bise_range.quints > 0 {
+        for chunk in quant_endpoints.chunks(3) {
+            let q_lut_id = chunk.iter()
+                .rev()
+                .fold(0, |acc, qe| {
+                    acc * 5 + qe.trit_quint
+                });
+            let q = ASTC_QUINT_ENCODE_LUT[q_lut_id as usize];
+            writer.write_u8(bit_count, chunk.get(0).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(3, q);
+            writer.write_u8(bit_count, chunk.get(1).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(2, q >> 3);
+            writer.write_u8(bit_count, chunk.get(2).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(2, q >> 5);
+        }
+    } else if bise_range.trits > 0 {
+        for chunk in quant_endpoints.chunks(5) {
+            let t_lut_id = chunk.iter()
+                .rev()
+                .fold(0, |acc, qe| {
+                    acc * 3 + qe.trit_quint
+                });
+            let t = ASTC_TRIT_ENCODE_LUT[t_lut_id as usize];
+            writer.write_u8(bit_count, chunk.get(0).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(2, t);
+            writer.write_u8(bit_count, chunk.get(1).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(2, t >> 2);
+            writer.write_u8(bit_count, chunk.get(2).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(1, t >> 4);
+            writer.write_u8(bit_count, chunk.get(3).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(2, t >> 5);
+            writer.write_u8(bit_count, chunk.get(4).map(|qe| qe.bits).unwrap_or(0));
+            writer.write_u8(1, t >> 7);
+        }
+    } else {
+        let bit_count = bise_range.bits as usize;
+        for qe in quant_endpoints {
+            writer.write_u8(bit_count, qe.bits);
+        }
+    }
+}
+
 static PATTERNS_2_ASTC_INDEX_10: [u16; uastc::TOTAL_ASTC_BC7_COMMON_PARTITIONS2] = [
     28, 20, 16, 29, 91, 9, 107, 72,
     149, 204, 50, 114, 496, 17, 78, 39,
@@ -225,33 +878,34 @@ static ASTC_QUINT_ENCODE_LUT: [u8; 125] = [
     0x27, 0x2F, 0x37, 0x3F, 0x1F,
 ];
 
-// fn astc_decode_quint(q: u8) -> u8 {
-//     let (q0, q1, q2);
-//     if (q >> 1) & 0b11 == 0b11 && (q >> 5) & 0b11 == 0 {
-//         q2 = (q & 1) << 2
-//             | ((q >> 4) & !q & 1) << 1
-//             | ((q >> 3) & !q & 1);
-//         q1 = 4;
-//         q0 = 4;
-//     } else {
-//         let c;
-//         if (q >> 1) & 0b11 == 0b11 {
-//             q2 = 4;
-//             c = (q & 0b11000) | (((!q >> 5) & 0b11) << 1) | (q & 1);
-//         } else {
-//             q2 = (q >> 5) & 0b11;
-//             c = q & 0b11111;
-//         }
-//         if c & 0b111 == 0b101 {
-//             q1 = 4;
-//             q0 = (c >> 3) & 0b11;
-//         } else {
-//             q1 = (c >> 3) & 0b11;
-//             q0 = c & 0b111;
-//         }
-//     }
-//     (q2 * 5 + q1) * 5 + q0
-// }
+/// Decodes 3 quints packed in 7 bits
+fn decode_quints(q: u8) -> [u8; 3] {
+    let (q0, q1, q2);
+    if (q >> 1) & 0b11 == 0b11 && (q >> 5) & 0b11 == 0 {
+        q2 = (q & 1) << 2
+            | ((q >> 4) & !q & 1) << 1
+            | ((q >> 3) & !q & 1);
+        q1 = 4;
+        q0 = 4;
+    } else {
+        let c;
+        if (q >> 1) & 0b11 == 0b11 {
+            q2 = 4;
+            c = (q & 0b11000) | (((!q >> 5) & 0b11) << 1) | (q & 1);
+        } else {
+            q2 = (q >> 5) & 0b11;
+            c = q & 0b11111;
+        }
+        if c & 0b111 == 0b101 {
+            q1 = 4;
+            q0 = (c >> 3) & 0b11;
+        } else {
+            q1 = (c >> 3) & 0b11;
+            q0 = c & 0b111;
+        }
+    }
+    [q0, q1, q2]
+}
 
 static ASTC_TRIT_ENCODE_LUT: [u8; 243] = [
     0x00, 0x01, 0x02, 0x04, 0x05, 0x06, 0x08, 0x09, 0x0A,
@@ -283,38 +937,39 @@ static ASTC_TRIT_ENCODE_LUT: [u8; 243] = [
     0x1F, 0x3F, 0x5F, 0x9F, 0xBF, 0xDF, 0x7C, 0x7D, 0x7E,
 ];
 
-// fn astc_decode_trit(t: u8) -> u8 {
-//     let (t0, t1, t2, t3, t4);
-//     let c;
-//     if (t >> 2) & 0b111 == 0b111 {
-//         c = ((t >> 5) & 0b111) << 2 | (t & 0b11);
-//         t4 = 2;
-//         t3 = 2;
-//     } else {
-//         c = t & 0b11111;
-//         if (t >> 5) & 0b11 == 0b11 {
-//             t4 = 2;
-//             t3 = (t >> 7) & 1;
-//         } else {
-//             t4 = (t >> 7) & 1;
-//             t3 = (t >> 5) & 0b11;
-//         }
-//     }
-//     if c & 0b11 == 0b11 {
-//         t2 = 2;
-//         t1 = (c >> 4) & 1;
-//         t0 = ((c >> 2) & 0b10) | ((c >> 2) & (!c >> 3) & 1);
-//     } else if (c >> 2) & 0b11 == 0b11 {
-//         t2 = 2;
-//         t1 = 2;
-//         t0 = c & 0b11;
-//     } else {
-//         t2 = (c >> 4) & 1;
-//         t1 = (c >> 2) & 0b11;
-//         t0 = (c & 0b10) | (c & (!c >> 1) & 1);
-//     }
-//     (((t4*3 + t3)*3 + t2)*3 + t1)*3 + t0
-// }
+/// Decodes 5 trits packed in 8 bits
+fn decode_trits(t: u8) -> [u8; 5] {
+    let (t0, t1, t2, t3, t4);
+    let c;
+    if (t >> 2) & 0b111 == 0b111 {
+        c = ((t >> 5) & 0b111) << 2 | (t & 0b11);
+        t4 = 2;
+        t3 = 2;
+    } else {
+        c = t & 0b11111;
+        if (t >> 5) & 0b11 == 0b11 {
+            t4 = 2;
+            t3 = (t >> 7) & 1;
+        } else {
+            t4 = (t >> 7) & 1;
+            t3 = (t >> 5) & 0b11;
+        }
+    }
+    if c & 0b11 == 0b11 {
+        t2 = 2;
+        t1 = (c >> 4) & 1;
+        t0 = ((c >> 2) & 0b10) | ((c >> 2) & (!c >> 3) & 1);
+    } else if (c >> 2) & 0b11 == 0b11 {
+        t2 = 2;
+        t1 = 2;
+        t0 = c & 0b11;
+    } else {
+        t2 = (c >> 4) & 1;
+        t1 = (c >> 2) & 0b11;
+        t0 = (c & 0b10) | (c & (!c >> 1) & 1);
+    }
+    [t0, t1, t2, t3, t4]
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct BiseCounts {
@@ -376,3 +1031,197 @@ static UASTC_TO_ASTC_BLOCK_MODE_13: [u16; 20] = [
 
          0, // 19
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_trits_quints() {
+        for (id, &packed) in ASTC_TRIT_ENCODE_LUT.iter().enumerate() {
+            let expected = [id % 3, id / 3 % 3, id / 9 % 3, id / 27 % 3, id / 81];
+            assert_eq!(decode_trits(packed).map(|t| t as usize), expected);
+        }
+        for (id, &packed) in ASTC_QUINT_ENCODE_LUT.iter().enumerate() {
+            let expected = [id % 5, id / 5 % 5, id / 25];
+            assert_eq!(decode_quints(packed).map(|q| q as usize), expected);
+        }
+    }
+
+    #[test]
+    fn test_unquant_weights() {
+        let levels = |range_index: u8| {
+            let range = BISE_RANGES[range_index as usize];
+            let trit_quint_count = if range.trits > 0 { 3 } else if range.quints > 0 { 5 } else { 1 };
+            let mut values: Vec<u8> = (0..trit_quint_count)
+                .flat_map(|trit_quint| (0..1u8 << range.bits).map(move |bits| uastc::QuantEndpoint { trit_quint, bits }))
+                .map(|quant| unquant_weight(quant, range_index))
+                .collect();
+            values.sort_unstable();
+            values
+        };
+        assert_eq!(levels(1), [0, 32, 64]);
+        assert_eq!(levels(3), [0, 16, 32, 48, 64]);
+        assert_eq!(levels(4), [0, 12, 25, 39, 52, 64]);
+        assert_eq!(levels(6), [0, 7, 14, 21, 28, 36, 43, 50, 57, 64]);
+        assert_eq!(levels(7), [0, 5, 11, 17, 23, 28, 36, 41, 47, 53, 59, 64]);
+        assert_eq!(levels(8), [0, 4, 8, 12, 17, 21, 25, 29, 35, 39, 43, 47, 52, 56, 60, 64]);
+        assert_eq!(levels(9), [0, 3, 6, 9, 13, 16, 19, 23, 26, 29, 35, 38, 41, 45, 48, 51, 55, 58, 61, 64]);
+        assert_eq!(levels(10), [0, 2, 5, 8, 11, 13, 16, 19, 22, 24, 27, 30, 34, 37, 40, 42, 45, 48, 51, 53, 56, 59, 62, 64]);
+    }
+
+    /// 4x4 block with 2-bit weights and 8-bit endpoint values, the last texel has
+    /// the highest weight and all others the lowest one
+    fn endpoint_test_block(cem: u8, values: &[u8]) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        let writer = &mut BitWriterLsb::new(&mut block);
+        writer.write_u16(13, ETC1S_RGB_BLOCK_MODE_13);
+        writer.write_u8(4, cem);
+        for &value in values {
+            writer.write_u8(8, value);
+        }
+        let writer_rev = &mut BitWriterMsbRevBytes::new(&mut block);
+        for i in 0..16 {
+            writer_rev.write_u8_rev_bits(2, if i == 15 { 3 } else { 0 });
+        }
+        block
+    }
+
+    #[test]
+    fn test_decode_endpoint_modes() {
+        let cases = [
+            (0, &[10, 200][..], [[10, 10, 10, 255], [200, 200, 200, 255]]),
+            (1, &[0x80, 0xC5][..], [[224, 224, 224, 255], [229, 229, 229, 255]]),
+            (4, &[10, 200, 30, 40][..], [[10, 10, 10, 30], [200, 200, 200, 40]]),
+            (5, &[100, 0x0A, 50, 0xFE][..], [[50, 50, 50, 153], [55, 55, 55, 152]]),
+            (6, &[200, 100, 50, 128][..], [[100, 50, 25, 255], [200, 100, 50, 255]]),
+            (8, &[100, 50, 100, 50, 100, 50][..], [[50, 50, 50, 255], [100, 100, 100, 255]]),
+            (9, &[100, 0x7E, 100, 0x7E, 200, 0x7E][..], [[74, 74, 99, 255], [75, 75, 100, 255]]),
+            (10, &[200, 100, 50, 128, 10, 250][..], [[100, 50, 25, 10], [200, 100, 50, 250]]),
+            (12, &[100, 50, 100, 50, 100, 50, 10, 20][..], [[50, 50, 50, 20], [100, 100, 100, 10]]),
+            (13, &[100, 0x10, 100, 0x10, 100, 0x10, 100, 0x90][..], [[50, 50, 50, 178], [58, 58, 58, 186]]),
+        ];
+        for (cem, values, [e0, e1]) in cases.iter() {
+            let pixels = decode_block(&endpoint_test_block(*cem, values), 4, 4, false).unwrap();
+            assert_eq!(pixels[0], *e0, "CEM {}", cem);
+            assert_eq!(pixels[15], *e1, "CEM {}", cem);
+        }
+
+        // HDR endpoints
+        let pixels = decode_block(&endpoint_test_block(2, &[10, 200]), 4, 4, false).unwrap();
+        assert_eq!(pixels, [ERROR_COLOR; 16]);
+    }
+
+    #[test]
+    fn test_decode_partitions_with_different_cems() {
+        let mut block = [0u8; 16];
+        let writer = &mut BitWriterLsb::new(&mut block);
+        writer.write_u16(11, ETC1S_RGB_BLOCK_MODE_13);
+        writer.write_u8(2, 1);
+        writer.write_u16(10, 28);
+        // Luminance in partition 0, luminance with alpha one class higher in partition 1,
+        // the two high bits of the CEM field below the weights stay 0
+        writer.write_u8(6, 0b00_10_01);
+        for &value in &[10, 20, 30, 40, 50, 60] {
+            writer.write_u8(8, value);
+        }
+
+        let pixels = decode_block(&block, 4, 4, false).unwrap();
+        for (i, pixel) in pixels.iter().enumerate() {
+            let expected = match select_partition(28, i as u32 % 4, i as u32 / 4, 2, true) {
+                0 => [10, 10, 10, 255],
+                _ => [30, 30, 30, 50],
+            };
+            assert_eq!(*pixel, expected, "texel {}", i);
+        }
+        assert!(pixels.contains(&[10, 10, 10, 255]) && pixels.contains(&[30, 30, 30, 50]));
+    }
+
+    #[test]
+    fn test_decode_weight_infill() {
+        // 4x4 weight grid in a 6x6 block, the weights increase from left to right
+        let mut block = [0u8; 16];
+        let writer = &mut BitWriterLsb::new(&mut block);
+        writer.write_u16(13, ETC1S_RGB_BLOCK_MODE_13);
+        writer.write_u8(4, CEM_LDR_LUMINANCE_DIRECT);
+        writer.write_u8(8, 0);
+        writer.write_u8(8, 255);
+        let writer_rev = &mut BitWriterMsbRevBytes::new(&mut block);
+        for i in 0..16 {
+            writer_rev.write_u8_rev_bits(2, i % 4);
+        }
+
+        let infill_weights = [0, 13, 25, 39, 51, 64];
+        let pixels = decode_block(&block, 6, 6, false).unwrap();
+        for (i, pixel) in pixels.iter().enumerate() {
+            let value = uastc::astc_interpolate(0, 255, infill_weights[i % 6], false);
+            assert_eq!(*pixel, [value, value, value, 255], "texel {}", i);
+        }
+    }
+
+    #[test]
+    fn test_decode_void_extent() {
+        let mut block = [0u8; 16];
+        write_void_extent_block(&mut BitWriterLsb::new(&mut block), Color32::new(10, 20, 30, 40));
+        for &(width, height) in FOOTPRINTS.iter() {
+            let pixels = decode_block(&block, width, height, true).unwrap();
+            assert_eq!(pixels, vec![[10, 20, 30, 40]; (width * height) as usize]);
+        }
+
+        // HDR void-extent
+        let mut hdr_block = block;
+        hdr_block[1] |= 0x02;
+        assert_eq!(decode_block(&hdr_block, 4, 4, false).unwrap(), [ERROR_COLOR; 16]);
+
+        // Reserved bits 10 and 11 cleared
+        for &reserved_mask in &[0x04, 0x08] {
+            let mut reserved_block = block;
+            reserved_block[1] &= !reserved_mask;
+            assert_eq!(decode_block(&reserved_block, 4, 4, false).unwrap(), [ERROR_COLOR; 16]);
+        }
+
+        // Empty extent
+        let mut empty_block = block;
+        empty_block[2] &= 0x1F;
+        assert_eq!(decode_block(&empty_block, 4, 4, false).unwrap(), [ERROR_COLOR; 16]);
+    }
+
+    #[test]
+    fn test_decode_srgb() {
+        let block = endpoint_test_block(CEM_LDR_RGBA_DIRECT, &[0, 255, 0, 255, 0, 255, 0, 255]);
+        let linear = decode_block(&block, 4, 4, false).unwrap();
+        let srgb = decode_block(&block, 4, 4, true).unwrap();
+        assert_eq!(linear[15], [255, 255, 255, 255]);
+        // The sRGB expansion to 16 bits fills the low bits with 0x80
+        assert_eq!(srgb[0], [0, 0, 0, 0]);
+        assert_eq!(srgb[15], [255, 255, 255, 255]);
+
+        // Weight 21 of 64 between 0 and 200. Linear decoding repeats the value in the low byte,
+        // (0 * 43 + 51400 * 21 + 32) >> 6 = 16866 is 65 in the high byte. sRGB decoding fills
+        // the low byte with 0x80, (128 * 43 + 51328 * 21 + 32) >> 6 = 16928 is 66. Alpha is
+        // always linear.
+        let mut block = [0u8; 16];
+        let writer = &mut BitWriterLsb::new(&mut block);
+        writer.write_u16(13, ETC1S_RGB_BLOCK_MODE_13);
+        writer.write_u8(4, CEM_LDR_RGBA_DIRECT);
+        for _ in 0..4 {
+            writer.write_u8(8, 0);
+            writer.write_u8(8, 200);
+        }
+        let writer_rev = &mut BitWriterMsbRevBytes::new(&mut block);
+        for _ in 0..16 {
+            writer_rev.write_u8_rev_bits(2, 1);
+        }
+        assert_eq!(decode_block(&block, 4, 4, false).unwrap(), [[65, 65, 65, 65]; 16]);
+        assert_eq!(decode_block(&block, 4, 4, true).unwrap(), [[66, 66, 66, 65]; 16]);
+    }
+
+    #[test]
+    fn test_decode_unsupported_footprint() {
+        let block = endpoint_test_block(CEM_LDR_RGBA_DIRECT, &[0, 255, 0, 255, 0, 255, 0, 255]);
+        assert!(matches!(decode_block(&block, 4, 3, false), Err(Error::UnsupportedDimensions { width: 4, height: 3 })));
+
+        let image = Image { w: 4, h: 4, stride: 16, y_flipped: false, data: block.to_vec() };
+        assert!(matches!(decode_image(&image, 7, 7, false), Err(Error::UnsupportedDimensions { width: 7, height: 7 })));
+    }
+}
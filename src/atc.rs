@@ -0,0 +1,49 @@
+use crate::Image;
+
+pub const ATC_BLOCK_SIZE: usize = 8;
+
+/// Converts the BC1 color blocks of an image to ATC, in place.
+///
+/// The color block is the last 8 bytes of each block, so BC3 images turn into ATC RGBA
+/// with interpolated alpha, which uses the same alpha block.
+pub fn convert_image_from_bc1(mut image: Image<u8>, block_size: usize) -> Image<u8> {
+    for block in image.data.chunks_exact_mut(block_size) {
+        convert_block_from_bc1(&mut block[block_size - ATC_BLOCK_SIZE..]);
+    }
+    image
+}
+
+/// Converts a BC1 block in the 4 color mode, as written by the BC1 encoder, to an ATC block.
+///
+/// ATC interpolates between color 0 (RGB555) and color 1 (RGB565) in index order, so the
+/// endpoints are swapped where that keeps the green bit which color 0 lacks.
+fn convert_block_from_bc1(block: &mut [u8]) {
+    let bc1_color0 = u16::from_le_bytes([block[0], block[1]]);
+    let bc1_color1 = u16::from_le_bytes([block[2], block[3]]);
+    let bc1_indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let green_lsb = |color: u16| (color >> 5) & 1;
+    let swap = green_lsb(bc1_color1) != 0 && green_lsb(bc1_color0) == 0;
+    let (low, high, index_map) = if swap {
+        (bc1_color0, bc1_color1, BC1_TO_ATC_INDICES_SWAPPED)
+    } else {
+        (bc1_color1, bc1_color0, BC1_TO_ATC_INDICES)
+    };
+
+    let mut indices = 0u32;
+    for i in 0..16 {
+        let index = (bc1_indices >> (2 * i)) & 3;
+        indices |= (index_map[index as usize] as u32) << (2 * i);
+    }
+
+    // RGB565 to RGB555 with the mode bit cleared
+    let color0 = (low & 0xFFC0) >> 1 | (low & 0x1F);
+
+    block[0..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&high.to_le_bytes());
+    block[4..8].copy_from_slice(&indices.to_le_bytes());
+}
+
+/// BC1 indices 0 and 1 are the endpoints, 2 and 3 their interpolations, ATC goes from color 0 to color 1
+const BC1_TO_ATC_INDICES: [u8; 4] = [3, 0, 2, 1];
+const BC1_TO_ATC_INDICES_SWAPPED: [u8; 4] = [0, 3, 1, 2];
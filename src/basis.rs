@@ -13,34 +13,27 @@ use std::convert::TryFrom;
 pub const SIG: u16 = 0x4273;
 
 pub fn check_file_sig(bytes: &[u8]) -> bool {
-    let sig = LE::read_u16(&bytes);
-    sig == SIG
+    bytes.len() >= 2 && LE::read_u16(bytes) == SIG
 }
 
 pub fn read_header(bytes: &[u8]) -> Result<Header> {
     if !check_file_sig(&bytes) {
-        return Err("Sig mismatch, not a Basis Universal file".into());
+        return Err(Error::BadSignature);
     }
 
     if !Header::check_size(&bytes) {
-        return Err(format!(
-            "Expected at least {} byte header, got {} bytes",
-            Header::FILE_SIZE, bytes.len()).into()
-        );
+        return Err(Error::TruncatedData);
     }
 
     let header = Header::from_file_bytes(&bytes);
 
     if header.header_size as usize != Header::FILE_SIZE {
-        return Err(format!(
-            "File specified unexpected header size, expected {}, got {}",
-            Header::FILE_SIZE, header.header_size).into()
-        );
+        return Err(Error::InvalidHeader("unexpected header size"));
     }
 
     let header_crc16 = crc16(&bytes[8..Header::FILE_SIZE], 0);
     if header_crc16 != header.header_crc16 {
-        return Err("Header CRC16 failed".into());
+        return Err(Error::HeaderCrcMismatch);
     }
 
     Ok(header)
@@ -52,24 +45,26 @@ pub fn check_file_checksum(bytes: &[u8], header: &Header) -> bool {
 }
 
 pub fn read_slice_descs(bytes: &[u8], header: &Header) -> Result<Vec<SliceDesc>> {
-    let start = header.slice_desc_file_ofs as usize;
-    let count = header.total_slices as usize;
-    let mut res = Vec::with_capacity(count);
-    for i in 0..count {
-        let slice_start = start + i * SliceDesc::FILE_SIZE;
-        if !SliceDesc::check_size(&bytes[slice_start..]) {
-            let message = format!(
-                "Expected {} byte slice desc at pos {}, only {} bytes remain",
-                SliceDesc::FILE_SIZE, slice_start, bytes.len()-slice_start
-            );
-            return Err(message.into());
-        }
-        let slice_desc = SliceDesc::from_file_bytes(&bytes[slice_start..]);
-        res.push(slice_desc);
-    }
+    let count = header.total_slices;
+    let slice_desc_bytes = file_range(bytes, header.slice_desc_file_ofs, count * SliceDesc::FILE_SIZE as u32)?;
+    let res = slice_desc_bytes
+        .chunks_exact(SliceDesc::FILE_SIZE)
+        .enumerate()
+        .map(|(index, bytes)| SliceDesc {
+            index: index as u32,
+            ..SliceDesc::from_file_bytes(bytes)
+        })
+        .collect();
     Ok(res)
 }
 
+/// Returns `len` bytes starting at `start`, or an error if the file is too short
+pub fn file_range(bytes: &[u8], start: u32, len: u32) -> Result<&[u8]> {
+    let start = start as usize;
+    let end = start.checked_add(len as usize).ok_or(Error::TruncatedData)?;
+    bytes.get(start..end).ok_or(Error::TruncatedData)
+}
+
 fn crc16(r: &[u8], mut crc: u16) -> u16 {
     crc = !crc;
     for &b in r {
@@ -81,14 +76,35 @@ fn crc16(r: &[u8], mut crc: u16) -> u16 {
 }
 
 // basis_file_header::m_tex_type
+/// How the images of a file fit together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TextureType {
+    /// Independent 2D images
     Type2D = 0,
+    /// Layers of a 2D texture array
     Type2DArray = 1,
+    /// Faces of one or more cubemaps, 6 images per cubemap
     CubemapArray = 2,
+    /// Frames of a video
     VideoFrames = 3,
+    /// Depth slices of a 3D texture
     Volume = 4,
 }
 
+impl TryFrom<u8> for TextureType {
+    type Error = Error;
+    fn try_from(v: u8) -> Result<Self> {
+        match v {
+            0 => Ok(TextureType::Type2D),
+            1 => Ok(TextureType::Type2DArray),
+            2 => Ok(TextureType::CubemapArray),
+            3 => Ok(TextureType::VideoFrames),
+            4 => Ok(TextureType::Volume),
+            _ => Err(Error::InvalidHeader("unknown texture type")),
+        }
+    }
+}
+
 // basis_slice_desc::flags
 pub enum SliceDescFlags {
     HasAlpha = 1,
@@ -108,7 +124,7 @@ impl TryFrom<u8> for TexFormat {
         match v {
             0 => Ok(TexFormat::ETC1S),
             1 => Ok(TexFormat::UASTC4x4),
-            _ => Err("Unknown texture format".into()),
+            _ => Err(Error::InvalidHeader("unknown texture format")),
         }
     }
 }
@@ -177,6 +193,10 @@ impl Header {
         TexFormat::try_from(self.tex_format)
     }
 
+    pub fn texture_type(&self) -> Result<TextureType> {
+        TextureType::try_from(self.tex_type)
+    }
+
     pub fn from_file_bytes(buf: &[u8]) -> Self {
         assert!(Self::check_size(&buf));
         let mut r = ByteReaderLE::new(buf);
@@ -237,6 +257,9 @@ pub struct SliceDesc {
     pub file_size: u32,
 
     pub slice_data_crc16: u16,
+
+    // Not part of the file, used for error reporting
+    pub index: u32,
 }
 
 impl SliceDesc {
@@ -250,6 +273,23 @@ impl SliceDesc {
         (self.flags & SliceDescFlags::HasAlpha as u8) != 0
     }
 
+    pub fn is_iframe(&self) -> bool {
+        (self.flags & SliceDescFlags::FrameIsIFrame as u8) != 0
+    }
+
+    /// Returns the compressed data of the slice
+    pub fn data<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8]> {
+        file_range(bytes, self.file_ofs, self.file_size)
+    }
+
+    /// Checks the compressed data of the slice against its CRC16
+    pub fn check_crc(&self, bytes: &[u8]) -> Result<()> {
+        if crc16(self.data(bytes)?, 0) != self.slice_data_crc16 {
+            return Err(Error::SliceCrcMismatch { slice: self.index });
+        }
+        Ok(())
+    }
+
     pub fn from_file_bytes(buf: &[u8]) -> Self {
         assert!(Self::check_size(&buf));
         let mut r = ByteReaderLE::new(buf);
@@ -264,6 +304,7 @@ impl SliceDesc {
             file_ofs: r.read_u32(),
             file_size: r.read_u32(),
             slice_data_crc16: r.read_u16(),
+            index: 0,
         };
         assert_eq!(r.pos(), Self::FILE_SIZE);
         res
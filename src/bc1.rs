@@ -0,0 +1,420 @@
+use crate::{
+    Color32,
+    Result,
+    bitreader::BitReaderLsb,
+    etc::{self, Selector},
+    uastc,
+};
+
+use std::sync::OnceLock;
+
+pub const BC1_BLOCK_SIZE: usize = 8;
+
+/// Encodes an ETC1S block, given by its 5-bit base color, intensity table and selectors
+pub fn encode_etc1s_block(color5: Color32, inten: u8, selector: &Selector, output: &mut [u8]) {
+    let mut selectors = [0u8; 16];
+    for (i, sel) in selectors.iter_mut().enumerate() {
+        *sel = selector.get_selector(i % 4, i / 4) as u8;
+    }
+    let lo = *selectors.iter().min().unwrap() as usize;
+    let hi = *selectors.iter().max().unwrap() as usize;
+
+    if lo == hi {
+        let colors = etc::apply_mod_to_base_color(etc::color_5_to_8(color5), inten);
+        encode_solid_block(colors[lo], output);
+        return;
+    }
+
+    let tables = etc1s_tables();
+    let range = lo * 4 + hi;
+    let inten = inten as usize;
+
+    // Try all mappings of the selectors to BC1 weights, the best one depends on clamping of the colors
+    let fits = |c: usize| {
+        let channel_fits = if c == 1 { &tables.fits6 } else { &tables.fits5 };
+        &channel_fits[inten][color5[c] as usize][range]
+    };
+    let (r, g, b) = (fits(0), fits(1), fits(2));
+    let best = (0..tables.mappings[range].len())
+        .min_by_key(|&m| r[m].err + g[m].err + b[m].err)
+        .unwrap();
+
+    let endpoints = [
+        [r[best].l, g[best].l, b[best].l],
+        [r[best].h, g[best].h, b[best].h],
+    ];
+
+    let weights = &tables.mappings[range][best];
+    let mut indices = [0u8; 16];
+    for (index, &sel) in indices.iter_mut().zip(selectors.iter()) {
+        *index = weights[sel as usize];
+    }
+
+    write_block(endpoints, indices, output);
+}
+
+/// Encodes a block of a single color
+pub fn encode_solid_block(color: Color32, output: &mut [u8]) {
+    let tables = solid_tables();
+    let mut endpoints = [[0u8; 3]; 2];
+    for c in 0..3 {
+        let channel_endpoints = if c == 1 { &tables.endpoints6 } else { &tables.endpoints5 };
+        let (l, h) = channel_endpoints[color[c] as usize];
+        endpoints[0][c] = l;
+        endpoints[1][c] = h;
+    }
+    write_block(endpoints, [SOLID_WEIGHT; 16], output);
+}
+
+pub fn convert_block_from_uastc(bytes: &[u8], output: &mut [u8]) {
+    match convert_block_from_uastc_result(bytes, output) {
+        Ok(_) => (),
+        _ => output.fill(0),
+    }
+}
+
+fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()> {
+    let reader = &mut BitReaderLsb::new(bytes);
+
+    let mode = uastc::decode_mode(reader)?;
+
+    if mode.id == 8 {
+        encode_solid_block(uastc::decode_mode8_rgba(reader), output);
+        return Ok(());
+    }
+
+    let trans_flags = uastc::decode_trans_flags(reader, mode);
+    let compsel = uastc::decode_compsel(reader, mode)?;
+    let pat = uastc::decode_pattern_index(reader, mode)?;
+
+    // The hints only apply to blocks where all color channels share one set of weights
+    let color_weights = mode.subset_count == 1 && (mode.plane_count == 1 || compsel == 3);
+    if !color_weights || !(trans_flags.bc1h0 || trans_flags.bc1h1) {
+        encode_block(&uastc::decode_block_to_rgba(bytes), output);
+        return Ok(());
+    }
+
+    let endpoint_count = mode.endpoint_count();
+    let quant_endpoints = uastc::decode_endpoints(reader, mode.endpoint_range_index, endpoint_count);
+    let mut unquant_endpoints = [0; 18];
+    for (quant, unquant) in quant_endpoints.iter().zip(unquant_endpoints.iter_mut()).take(endpoint_count) {
+        *unquant = uastc::unquant_endpoint(*quant, mode.endpoint_range_index);
+    }
+    let [e0, e1] = uastc::assemble_endpoint_pairs(mode, &unquant_endpoints)[0];
+
+    let plane_count = mode.plane_count as usize;
+    let mut astc_weights = [0u8; 32];
+    uastc::decode_weights(reader, mode, pat, |i, w| {
+        astc_weights[i] = w;
+    });
+    uastc::unquant_weights(&mut astc_weights[..16 * plane_count], mode.weight_bits);
+
+    // Round the ASTC weights (0..=64) to thirds
+    let mut weights = [0u8; 16];
+    for (weight, &astc_weight) in weights.iter_mut().zip(astc_weights.iter().step_by(plane_count)) {
+        *weight = ((astc_weight as u32 * 3 + 32) / 64) as u8;
+    }
+
+    if trans_flags.bc1h0 {
+        // The encoder checked that the UASTC endpoints and weights work as they are
+        let quantize = |e: Color32| [quantize(e[0], 5), quantize(e[1], 6), quantize(e[2], 5)];
+        write_block([quantize(e0), quantize(e1)], weights, output);
+    } else {
+        // The weights make good selectors, only the endpoints have to be refit
+        encode_block_with_weights(&uastc::decode_block_to_rgba(bytes), weights, output);
+    }
+
+    Ok(())
+}
+
+/// Encodes a block of pixels, alpha is ignored
+pub fn encode_block(pixels: &[Color32; 16], output: &mut [u8]) {
+    let first = pixels[0];
+    if pixels.iter().all(|p| (0..3).all(|c| p[c] == first[c])) {
+        encode_solid_block(first, output);
+        return;
+    }
+    encode_block_with_weights(pixels, principal_axis_weights(pixels), output);
+}
+
+/// Assigns weights by projecting the pixels to the principal axis of their colors
+fn principal_axis_weights(pixels: &[Color32; 16]) -> [u8; 16] {
+    let mut mean = [0f32; 3];
+    for pixel in pixels {
+        for c in 0..3 {
+            mean[c] += pixel[c] as f32 / 16.0;
+        }
+    }
+
+    let mut covariance = [[0f32; 3]; 3];
+    for pixel in pixels {
+        let d = [0, 1, 2].map(|c| pixel[c] as f32 - mean[c]);
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j];
+            }
+        }
+    }
+
+    // Power iteration, starting from the luminance direction
+    let mut axis = [0.3f32, 0.6, 0.1];
+    for _ in 0..8 {
+        let next = [0, 1, 2].map(|i| (0..3).map(|j| covariance[i][j] * axis[j]).sum::<f32>());
+        let length = next.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if length < f32::EPSILON {
+            break;
+        }
+        axis = next.map(|x| x / length);
+    }
+
+    let projections = pixels.map(|p| (0..3).map(|c| p[c] as f32 * axis[c]).sum::<f32>());
+    let min = projections.iter().copied().fold(f32::MAX, f32::min);
+    let max = projections.iter().copied().fold(f32::MIN, f32::max);
+    if max <= min {
+        return [0; 16];
+    }
+    projections.map(|p| ((p - min) / (max - min) * 3.0).round() as u8)
+}
+
+/// Fits endpoints to the weights, then alternates choosing the closest weights and refitting
+fn encode_block_with_weights(pixels: &[Color32; 16], mut weights: [u8; 16], output: &mut [u8]) {
+    let mut best: Option<([[u8; 3]; 2], [u8; 16], u32)> = None;
+    for _ in 0..2 {
+        let mut endpoints = [[0u8; 3]; 2];
+        for c in 0..3 {
+            let bits = if c == 1 { 6 } else { 5 };
+            let values = pixels.map(|p| p[c] as i32);
+            let fit = fit_endpoints(&values, &weights, bits);
+            endpoints[0][c] = fit.l;
+            endpoints[1][c] = fit.h;
+        }
+
+        let (closest, err) = closest_weights(pixels, endpoints);
+        // `is_none_or` would need Rust 1.82
+        #[allow(clippy::unnecessary_map_or)]
+        let improved = best.map_or(true, |(_, _, best_err)| err < best_err);
+        if improved {
+            best = Some((endpoints, closest, err));
+        }
+        weights = closest;
+    }
+
+    let (endpoints, weights, _) = best.unwrap();
+    write_block(endpoints, weights, output);
+}
+
+/// Chooses the weight of each pixel which gives the closest color, returns them with the total squared error
+fn closest_weights(pixels: &[Color32; 16], endpoints: [[u8; 3]; 2]) -> ([u8; 16], u32) {
+    let palette = [0, 1, 2, 3].map(|weight| {
+        [0, 1, 2].map(|c| {
+            let bits = if c == 1 { 6 } else { 5 };
+            interpolate(extend(endpoints[0][c], bits), extend(endpoints[1][c], bits), weight)
+        })
+    });
+
+    let mut weights = [0u8; 16];
+    let mut total_err = 0;
+    for (weight, pixel) in weights.iter_mut().zip(pixels) {
+        let (best_weight, err) = palette.iter()
+            .map(|color| (0..3).map(|c| {
+                let d = color[c] - pixel[c] as i32;
+                (d * d) as u32
+            }).sum::<u32>())
+            .enumerate()
+            .min_by_key(|&(_, err)| err)
+            .unwrap();
+        *weight = best_weight as u8;
+        total_err += err;
+    }
+    (weights, total_err)
+}
+
+/// Writes a block from its low and high 5:6:5 endpoints and per-pixel weights.
+///
+/// A weight is the fraction of the high endpoint in thirds.
+fn write_block(endpoints: [[u8; 3]; 2], weights: [u8; 16], output: &mut [u8]) {
+    let pack = |e: [u8; 3]| (e[0] as u16) << 11 | (e[1] as u16) << 5 | e[2] as u16;
+    let mut color0 = pack(endpoints[1]);
+    let mut color1 = pack(endpoints[0]);
+
+    // Color 0 has to be greater than color 1, otherwise the block uses the 3 color mode
+    let mut flip = 0;
+    if color0 < color1 {
+        std::mem::swap(&mut color0, &mut color1);
+        flip = 1;
+    }
+
+    let mut indices: u32 = 0;
+    if color0 != color1 {
+        for (i, &weight) in weights.iter().enumerate() {
+            indices |= ((WEIGHT_TO_INDEX[weight as usize] ^ flip) as u32) << (2 * i);
+        }
+    }
+
+    output[0..2].copy_from_slice(&color0.to_le_bytes());
+    output[2..4].copy_from_slice(&color1.to_le_bytes());
+    output[4..8].copy_from_slice(&indices.to_le_bytes());
+}
+
+/// Color 0 is the high endpoint, color 2 is 2/3 of the way to it
+const WEIGHT_TO_INDEX: [u8; 4] = [1, 3, 2, 0];
+
+const SOLID_WEIGHT: u8 = 2;
+
+fn extend(q: u8, bits: u32) -> i32 {
+    let q = q as i32;
+    (q << (8 - bits)) | (q >> (2 * bits - 8))
+}
+
+fn quantize(value: u8, bits: u32) -> u8 {
+    let max = (1u32 << bits) - 1;
+    ((value as u32 * max + 127) / 255) as u8
+}
+
+fn interpolate(l: i32, h: i32, weight: u8) -> i32 {
+    let weight = weight as i32;
+    (l * (3 - weight) + h * weight) / 3
+}
+
+struct SolidTables {
+    endpoints5: Vec<(u8, u8)>,
+    endpoints6: Vec<(u8, u8)>,
+}
+
+fn solid_tables() -> &'static SolidTables {
+    static TABLES: OnceLock<SolidTables> = OnceLock::new();
+
+    fn best_endpoints(value: i32, bits: u32) -> (u8, u8) {
+        let max = (1u8 << bits) - 1;
+        let mut best = (0, 0);
+        let mut best_err = i32::MAX;
+        for l in 0..=max {
+            for h in 0..=max {
+                let err = (interpolate(extend(l, bits), extend(h, bits), SOLID_WEIGHT) - value).abs();
+                if err < best_err {
+                    best = (l, h);
+                    best_err = err;
+                }
+            }
+        }
+        best
+    }
+
+    TABLES.get_or_init(|| SolidTables {
+        endpoints5: (0..256).map(|v| best_endpoints(v, 5)).collect(),
+        endpoints6: (0..256).map(|v| best_endpoints(v, 6)).collect(),
+    })
+}
+
+#[derive(Clone, Copy, Default)]
+struct EndpointFit {
+    l: u8,
+    h: u8,
+    err: u32,
+}
+
+/// Fits for each selector mapping of a selector range
+type MappingFits = Vec<EndpointFit>;
+
+/// Precomputed conversions of ETC1S endpoints, indexed by the intensity table,
+/// the 5-bit base color and the range of used selectors as `lo * 4 + hi`
+struct Etc1sTables {
+    fits5: Vec<Vec<Vec<MappingFits>>>,
+    fits6: Vec<Vec<Vec<MappingFits>>>,
+    /// Candidate BC1 weights of the selectors, the lowest used selector always has
+    /// weight 0 and the highest one weight 3
+    mappings: Vec<Vec<[u8; 4]>>,
+}
+
+fn etc1s_tables() -> &'static Etc1sTables {
+    static TABLES: OnceLock<Etc1sTables> = OnceLock::new();
+
+    TABLES.get_or_init(|| {
+        let mut mappings = vec![Vec::new(); 16];
+        for lo in 0..4 {
+            for hi in lo + 1..4 {
+                let mut mapping = [0u8; 4];
+                mapping[hi] = 3;
+                add_mappings(&mut mappings[lo * 4 + hi], &mut mapping, lo + 1, hi, 0);
+            }
+        }
+
+        let table = |bits: u32| -> Vec<Vec<Vec<MappingFits>>> {
+            (0..8).map(|inten| {
+                (0..32u8).map(|base5| {
+                    let base = extend(base5, 5) as i16;
+                    let values = etc::ETC1_MODIFIERS[inten].map(|m| (base + m).clamp(0, 255) as i32);
+                    (0..16).map(|range| {
+                        let (lo, hi) = (range / 4, range % 4);
+                        mappings[range].iter()
+                            .map(|mapping| fit_endpoints(&values[lo..=hi], &mapping[lo..=hi], bits))
+                            .collect()
+                    }).collect()
+                }).collect()
+            }).collect()
+        };
+
+        Etc1sTables {
+            fits5: table(5),
+            fits6: table(6),
+            mappings,
+        }
+    })
+}
+
+/// Adds all non-decreasing weights of selectors `sel..hi`
+fn add_mappings(mappings: &mut Vec<[u8; 4]>, mapping: &mut [u8; 4], sel: usize, hi: usize, min_weight: u8) {
+    if sel == hi {
+        mappings.push(*mapping);
+        return;
+    }
+    for weight in min_weight..=3 {
+        mapping[sel] = weight;
+        add_mappings(mappings, mapping, sel + 1, hi, weight);
+    }
+}
+
+/// Finds the quantized endpoints which best reproduce the values with the given weights
+fn fit_endpoints(values: &[i32], weights: &[u8], bits: u32) -> EndpointFit {
+    // Least squares fit of unquantized endpoints
+    let (mut aa, mut ab, mut bb, mut av, mut bv) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (&value, &weight) in values.iter().zip(weights) {
+        let b = weight as f32 / 3.0;
+        let a = 1.0 - b;
+        let v = value as f32;
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        av += a * v;
+        bv += b * v;
+    }
+    let det = aa * bb - ab * ab;
+    let (l, h) = if det.abs() > f32::EPSILON {
+        ((av * bb - bv * ab) / det, (bv * aa - av * ab) / det)
+    } else {
+        let mean = values.iter().sum::<i32>() as f32 / values.len() as f32;
+        (mean, mean)
+    };
+
+    // Search the neighborhood of the quantized fit
+    let max = (1i32 << bits) - 1;
+    let quantize = |x: f32| ((x / 255.0 * max as f32).round() as i32).clamp(0, max);
+    let (l, h) = (quantize(l), quantize(h));
+
+    let mut best = EndpointFit { err: u32::MAX, ..EndpointFit::default() };
+    for ql in (l - 2).max(0)..=(l + 2).min(max) {
+        for qh in (h - 2).max(0)..=(h + 2).min(max) {
+            let (el, eh) = (extend(ql as u8, bits), extend(qh as u8, bits));
+            let err = values.iter().zip(weights)
+                .map(|(&value, &weight)| {
+                    let d = interpolate(el, eh, weight) - value;
+                    (d * d) as u32
+                })
+                .sum();
+            if err < best.err {
+                best = EndpointFit { l: ql as u8, h: qh as u8, err };
+            }
+        }
+    }
+    best
+}
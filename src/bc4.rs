@@ -0,0 +1,43 @@
+use crate::{
+    Color32,
+    etc::{self, Selector},
+};
+
+pub const BC4_BLOCK_SIZE: usize = 8;
+
+/// Encodes the green channel of an ETC1S block, given by its 5-bit base color, intensity table and selectors
+pub fn encode_etc1s_block(color5: Color32, inten: u8, selector: &Selector, output: &mut [u8]) {
+    let colors = etc::apply_mod_to_base_color(etc::color_5_to_8(color5), inten);
+    let mut values = [0u8; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = colors[selector.get_selector(i % 4, i / 4)][1];
+    }
+    encode_block(&values, output);
+}
+
+/// Encodes a block of single channel values in raster order
+pub fn encode_block(values: &[u8; 16], output: &mut [u8]) {
+    let min = *values.iter().min().unwrap();
+    let max = *values.iter().max().unwrap();
+
+    output[0] = max;
+    output[1] = min;
+
+    let mut indices: u64 = 0;
+    if max != min {
+        // With the first endpoint greater, the block interpolates 6 values between the endpoints
+        let mut palette = [max, min, 0, 0, 0, 0, 0, 0];
+        for (i, entry) in palette.iter_mut().enumerate().skip(2) {
+            *entry = (((8 - i) * max as usize + (i - 1) * min as usize) / 7) as u8;
+        }
+
+        for (i, &value) in values.iter().enumerate() {
+            let index = (0..8)
+                .min_by_key(|&index| (palette[index] as i32 - value as i32).abs())
+                .unwrap();
+            indices |= (index as u64) << (3 * i);
+        }
+    }
+
+    output[2..8].copy_from_slice(&indices.to_le_bytes()[0..6]);
+}
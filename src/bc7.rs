@@ -1,13 +1,18 @@
 use crate::{
     Color32,
+    Image,
     Result,
     bitreader::BitReaderLsb,
     bitwriter::BitWriterLsb,
+    etc::{self, Selector},
     mask, uastc
 };
 
+use std::convert::TryInto;
 use std::sync::Once;
 
+pub const BC7_BLOCK_SIZE: usize = 16;
+
 pub fn convert_block_from_uastc(bytes: &[u8], output: &mut [u8]) {
     match convert_block_from_uastc_result(bytes, output) {
         Ok(_) => (),
@@ -24,38 +29,7 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
 
     if mode.id == 8 {
         let rgba = uastc::decode_mode8_rgba(reader);
-
-        let (mode, endpoint, p_bits, weights) = convert_mode_8_to_bc7_mode_endpoint_p_bits_weights(rgba);
-
-        let bc7_mode = BC7_MODES[mode as usize];
-
-        let weights = &weights[0..bc7_mode.plane_count as usize];
-
-        writer.write_u8(mode as usize + 1, 1 << mode);
-
-        if mode == 5 {
-            writer.write_u8(2, 0);
-        }
-
-        for channel in 0..4 {
-            let bit_count = if channel != ALPHA_CHANNEL { bc7_mode.color_bits } else { bc7_mode.alpha_bits } as usize;
-            writer.write_u8(bit_count, endpoint[0][channel]);
-            writer.write_u8(bit_count, endpoint[1][channel]);
-        }
-
-        if mode == 6 {
-            writer.write_u8(2, (p_bits[1] << 1) | p_bits[0]);
-        }
-
-        {   // Write weights
-            let bit_count = bc7_mode.weight_bits as usize;
-            for &weight in weights.iter() {
-                writer.write_u8(bit_count - 1, weight);
-                for _ in 0..15 {
-                    writer.write_u8(bit_count as usize, weight);
-                }
-            }
-        }
+        write_solid_block(writer, rgba);
         return Ok(())
     }
 
@@ -64,7 +38,7 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
 
     uastc::skip_trans_flags(reader, mode);
 
-    let compsel = uastc::decode_compsel(reader, mode);
+    let compsel = uastc::decode_compsel(reader, mode)?;
     let uastc_pat = uastc::decode_pattern_index(reader, mode)?;
 
     let bc7_plane_count = bc7_mode.plane_count as usize;
@@ -108,8 +82,6 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
 
     let mut bc7_anchors: &[u8] = &[0];
 
-    const ALPHA_CHANNEL: usize = 3;
-
     // Write partition bits
     if bc7_subset_count > 1 {
         let (bc7_pat, pattern, anchors, perm): (_, _, &[u8], &[u8]) = match (mode.id, mode.subset_count) {
@@ -277,6 +249,271 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8]) -> Result<()
     Ok(())
 }
 
+fn write_solid_block(writer: &mut BitWriterLsb, rgba: Color32) {
+    let (mode, endpoint, p_bits, weights) = convert_mode_8_to_bc7_mode_endpoint_p_bits_weights(rgba);
+
+    let bc7_mode = BC7_MODES[mode as usize];
+
+    let weights = &weights[0..bc7_mode.plane_count as usize];
+
+    writer.write_u8(mode as usize + 1, 1 << mode);
+
+    if mode == 5 {
+        writer.write_u8(2, 0);
+    }
+
+    for (channel, (&lo, &hi)) in endpoint[0].0.iter().zip(endpoint[1].0.iter()).enumerate() {
+        let bit_count = if channel != ALPHA_CHANNEL { bc7_mode.color_bits } else { bc7_mode.alpha_bits } as usize;
+        writer.write_u8(bit_count, lo);
+        writer.write_u8(bit_count, hi);
+    }
+
+    if mode == 6 {
+        writer.write_u8(2, (p_bits[1] << 1) | p_bits[0]);
+    }
+
+    {   // Write weights
+        let bit_count = bc7_mode.weight_bits as usize;
+        for &weight in weights.iter() {
+            writer.write_u8(bit_count - 1, weight);
+            for _ in 0..15 {
+                writer.write_u8(bit_count, weight);
+            }
+        }
+    }
+}
+
+/// Encodes an opaque ETC1S block as BC7 mode 6, given by its four colors and selectors
+pub fn encode_etc1s_block(colors: &[Color32; 4], selector: &Selector, output: &mut [u8]) {
+    let writer = &mut BitWriterLsb::new(output);
+
+    let (lo, hi) = etc::selector_range(selector);
+    if lo == hi {
+        write_solid_block(writer, colors[lo]);
+        return;
+    }
+
+    let bc7_mode = BC7_MODES[6];
+
+    // The p-bits are 1 to keep alpha at 255
+    let (mut endpoints, mut weights) = etc::fit_selector_endpoints(colors, selector, &WEIGHTS_4, |v| (v / 2) as u8, |q| q << 1 | 1);
+    for endpoint in endpoints.iter_mut() {
+        endpoint[ALPHA_CHANNEL] = 127;
+    }
+
+    // The first weight has an implicit 0 MSB
+    let weight_mask = mask!(bc7_mode.weight_bits);
+    if weights[selector.get_selector(0, 0)] & (1 << (bc7_mode.weight_bits - 1)) != 0 {
+        endpoints.swap(0, 1);
+        for weight in weights.iter_mut() {
+            *weight = !*weight & weight_mask;
+        }
+    }
+
+    writer.write_u8(bc7_mode.id as usize + 1, 1 << bc7_mode.id);
+    for (&lo, &hi) in endpoints[0].0.iter().zip(endpoints[1].0.iter()) {
+        writer.write_u8(bc7_mode.color_bits as usize, lo);
+        writer.write_u8(bc7_mode.color_bits as usize, hi);
+    }
+    writer.write_u8(2, 0b11);
+    write_selector_weights(writer, selector, &weights, bc7_mode.weight_bits);
+}
+
+/// Encodes an ETC1S block with a block of the alpha slice as BC7 mode 5.
+///
+/// Alpha is taken from the green channel of the alpha block colors.
+pub fn encode_etc1s_block_with_alpha(colors: &[Color32; 4], selector: &Selector, alpha_colors: &[Color32; 4], alpha_selector: &Selector, output: &mut [u8]) {
+    let writer = &mut BitWriterLsb::new(output);
+
+    let bc7_mode = BC7_MODES[5];
+    let weight_mask = mask!(bc7_mode.weight_bits);
+    let weight_msb_mask = 1 << (bc7_mode.weight_bits - 1);
+
+    // Color endpoints and weights
+    let (lo, hi) = etc::selector_range(selector);
+    let (mut endpoints, mut weights) = if lo == hi {
+        let (mode_5_optimal_endpoints, _) = get_mode_8_bc7_tables();
+        let mut endpoints = [Color32::default(); 2];
+        for c in 0..3 {
+            endpoints[0][c] = mode_5_optimal_endpoints[colors[lo][c] as usize].lo;
+            endpoints[1][c] = mode_5_optimal_endpoints[colors[lo][c] as usize].hi;
+        }
+        (endpoints, [BC7ENC_MODE_5_OPTIMAL_INDEX; 4])
+    } else {
+        etc::fit_selector_endpoints(colors, selector, &WEIGHTS_2, |v| ((v * 127 + 127) / 255) as u8, |q| q << 1 | q >> 6)
+    };
+    if weights[selector.get_selector(0, 0)] & weight_msb_mask != 0 {
+        endpoints.swap(0, 1);
+        for weight in weights.iter_mut() {
+            *weight = !*weight & weight_mask;
+        }
+    }
+
+    // Alpha endpoints and weights, alpha keeps its 8 bits
+    let alphas = alpha_colors.map(|color| Color32::new(color[1], 0, 0, 0));
+    let (lo, hi) = etc::selector_range(alpha_selector);
+    let (mut alpha_endpoints, mut alpha_weights) = if lo == hi {
+        ([alphas[lo][0]; 2], [0; 4])
+    } else {
+        let (endpoints, weights) = etc::fit_selector_endpoints(&alphas, alpha_selector, &WEIGHTS_2, |v| v as u8, |q| q);
+        ([endpoints[0][0], endpoints[1][0]], weights)
+    };
+    if alpha_weights[alpha_selector.get_selector(0, 0)] & weight_msb_mask != 0 {
+        alpha_endpoints.swap(0, 1);
+        for weight in alpha_weights.iter_mut() {
+            *weight = !*weight & weight_mask;
+        }
+    }
+
+    writer.write_u8(bc7_mode.id as usize + 1, 1 << bc7_mode.id);
+    // No rotation
+    writer.write_u8(2, 0);
+    for (&lo, &hi) in endpoints[0].0[..3].iter().zip(endpoints[1].0[..3].iter()) {
+        writer.write_u8(bc7_mode.color_bits as usize, lo);
+        writer.write_u8(bc7_mode.color_bits as usize, hi);
+    }
+    writer.write_u8(bc7_mode.alpha_bits as usize, alpha_endpoints[0]);
+    writer.write_u8(bc7_mode.alpha_bits as usize, alpha_endpoints[1]);
+    write_selector_weights(writer, selector, &weights, bc7_mode.weight_bits);
+    write_selector_weights(writer, alpha_selector, &alpha_weights, bc7_mode.weight_bits);
+}
+
+fn write_selector_weights(writer: &mut BitWriterLsb, selector: &Selector, weights: &[u8; 4], weight_bits: u8) {
+    for y in 0..4 {
+        for x in 0..4 {
+            // The first weight has an implicit 0 MSB
+            let bit_count = if x == 0 && y == 0 { weight_bits - 1 } else { weight_bits };
+            writer.write_u8(bit_count as usize, weights[selector.get_selector(x, y)]);
+        }
+    }
+}
+
+/// Decodes a BC7 block to RGBA pixels in raster order.
+///
+/// Blocks with the reserved mode (first byte zero) decode to transparent black, as required by the spec.
+pub fn decode_block(block: &[u8; 16]) -> [[u8; 4]; 16] {
+    let mut pixels = [[0u8; 4]; 16];
+    if block[0] == 0 {
+        return pixels;
+    }
+
+    let reader = &mut BitReaderLsb::new(block);
+
+    let bc7_mode = BC7_MODES[block[0].trailing_zeros() as usize];
+    reader.remove(bc7_mode.id as usize + 1);
+
+    let pattern = reader.read_u8(bc7_mode.pat_bits as usize) as usize;
+    let (rotation, index_selection) = match bc7_mode.id {
+        4 => (reader.read_u8(2), reader.read_bool()),
+        5 => (reader.read_u8(2), false),
+        _ => (0, false),
+    };
+
+    let (partition, anchors): (&[u8; 16], &[u8]) = match bc7_mode.subset_count {
+        1 => (&[0; 16], &[0]),
+        2 => (&ALL_PATTERNS_2_BC7[pattern], &PATTERNS_2_BC7_ANCHORS[pattern]),
+        _ => (&ALL_PATTERNS_3_BC7[pattern], &PATTERNS_3_BC7_ANCHORS[pattern]),
+    };
+
+    let endpoint_count = 2 * bc7_mode.subset_count as usize;
+    let mut endpoints = [[0u8; 4]; 6];
+    for channel in 0..4 {
+        let bit_count = if channel != ALPHA_CHANNEL { bc7_mode.color_bits } else { bc7_mode.alpha_bits } as usize;
+        for endpoint in endpoints[..endpoint_count].iter_mut() {
+            endpoint[channel] = reader.read_u8(bit_count);
+        }
+    }
+
+    // Unique p-bits belong to endpoints, shared p-bits to subsets
+    let p_bit_count = if bc7_mode.p_bits != 0 { endpoint_count } else { bc7_mode.sp_bits as usize * endpoint_count / 2 };
+    let mut p_bits = [0u8; 6];
+    for p_bit in p_bits[..p_bit_count].iter_mut() {
+        *p_bit = reader.read_u8(1);
+    }
+
+    for (i, endpoint) in endpoints[..endpoint_count].iter_mut().enumerate() {
+        let p_bit = if bc7_mode.p_bits != 0 { p_bits[i] } else { p_bits[i / 2] };
+        for (channel, value) in endpoint.iter_mut().enumerate() {
+            let bit_count = if channel != ALPHA_CHANNEL { bc7_mode.color_bits } else { bc7_mode.alpha_bits };
+            if bit_count == 0 {
+                *value = 0xFF;
+                continue;
+            }
+            let (quantized, bit_count) = if p_bit_count != 0 {
+                ((*value << 1 | p_bit) as u32, bit_count + 1)
+            } else {
+                (*value as u32, bit_count)
+            };
+            *value = (quantized << (8 - bit_count) | quantized >> (2 * bit_count - 8)) as u8;
+        }
+    }
+
+    // Anchor weights have an implicit 0 MSB
+    let mut read_weights = |bit_count: u8| {
+        let mut weights = [0u8; 16];
+        for (i, weight) in weights.iter_mut().enumerate() {
+            let is_anchor = anchors.contains(&(i as u8));
+            *weight = reader.read_u8(bit_count as usize - is_anchor as usize);
+        }
+        weights
+    };
+
+    let weight_bits = bc7_mode.weight_bits;
+    let weights = read_weights(weight_bits);
+    let (color_weights, color_weight_bits, alpha_weights, alpha_weight_bits) = if bc7_mode.plane_count == 2 {
+        // Mode 4 has 3-bit weights in the second plane, the index selection bit swaps the planes
+        let second_weight_bits = if bc7_mode.id == 4 { 3 } else { weight_bits };
+        let second_weights = read_weights(second_weight_bits);
+        if index_selection {
+            (second_weights, second_weight_bits, weights, weight_bits)
+        } else {
+            (weights, weight_bits, second_weights, second_weight_bits)
+        }
+    } else {
+        (weights, weight_bits, weights, weight_bits)
+    };
+
+    let color_weight_table = weight_table(color_weight_bits);
+    let alpha_weight_table = weight_table(alpha_weight_bits);
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let subset = partition[i] as usize;
+        let (e0, e1) = (endpoints[2 * subset], endpoints[2 * subset + 1]);
+        for channel in 0..4 {
+            let weight = if channel != ALPHA_CHANNEL {
+                color_weight_table[color_weights[i] as usize]
+            } else {
+                alpha_weight_table[alpha_weights[i] as usize]
+            } as u32;
+            pixel[channel] = ((e0[channel] as u32 * (64 - weight) + e1[channel] as u32 * weight + 32) >> 6) as u8;
+        }
+        if rotation != 0 {
+            pixel.swap(rotation as usize - 1, ALPHA_CHANNEL);
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a BC7 image, e.g. the `Bc7Rgba` output of transcoding, to RGBA bytes laid out like `Rgba32` output
+pub fn decode_image(image: &Image<u8>) -> Image<u8> {
+    crate::decode_block_image(image, BC7_BLOCK_SIZE, 4, 4, |block| decode_block(block.try_into().unwrap()))
+}
+
+fn weight_table(weight_bits: u8) -> &'static [u8] {
+    match weight_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+const ALPHA_CHANNEL: usize = 3;
+
+const WEIGHTS_2: [u8; 4] = [ 0, 21, 43, 64 ];
+const WEIGHTS_3: [u8; 8] = [ 0, 9, 18, 27, 37, 46, 55, 64 ];
+const WEIGHTS_4: [u8; 16] = [ 0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64 ];
+
 fn convert_mode_8_to_bc7_mode_endpoint_p_bits_weights(solid_color: Color32) -> (u8, [Color32; 2], [u8; 2], [u8; 2]) {
 
     let (mode_5_optimal_endpoints, mode_6_optimal_endpoints) = get_mode_8_bc7_tables();
@@ -658,6 +895,76 @@ static PATTERNS_3_BC7_ANCHORS: [[u8; 3]; TOTAL_BC7_PATTERNS] = [
     [0,  5, 15], [0, 10, 15], [0,  8, 15], [0, 13, 15], [0, 15,  3], [0, 12, 15], [0,  3, 15], [0,  3,  8],
 ];
 
+static ALL_PATTERNS_2_BC7: [[u8; 16]; TOTAL_BC7_PATTERNS] = [
+    [ 0,0,1,1,0,0,1,1,0,0,1,1,0,0,1,1 ], [ 0,0,0,1,0,0,0,1,0,0,0,1,0,0,0,1 ],
+    [ 0,1,1,1,0,1,1,1,0,1,1,1,0,1,1,1 ], [ 0,0,0,1,0,0,1,1,0,0,1,1,0,1,1,1 ],
+    [ 0,0,0,0,0,0,0,1,0,0,0,1,0,0,1,1 ], [ 0,0,1,1,0,1,1,1,0,1,1,1,1,1,1,1 ],
+    [ 0,0,0,1,0,0,1,1,0,1,1,1,1,1,1,1 ], [ 0,0,0,0,0,0,0,1,0,0,1,1,0,1,1,1 ],
+    [ 0,0,0,0,0,0,0,0,0,0,0,1,0,0,1,1 ], [ 0,0,1,1,0,1,1,1,1,1,1,1,1,1,1,1 ],
+    [ 0,0,0,0,0,0,0,1,0,1,1,1,1,1,1,1 ], [ 0,0,0,0,0,0,0,0,0,0,0,1,0,1,1,1 ],
+    [ 0,0,0,1,0,1,1,1,1,1,1,1,1,1,1,1 ], [ 0,0,0,0,0,0,0,0,1,1,1,1,1,1,1,1 ],
+    [ 0,0,0,0,1,1,1,1,1,1,1,1,1,1,1,1 ], [ 0,0,0,0,0,0,0,0,0,0,0,0,1,1,1,1 ],
+    [ 0,0,0,0,1,0,0,0,1,1,1,0,1,1,1,1 ], [ 0,1,1,1,0,0,0,1,0,0,0,0,0,0,0,0 ],
+    [ 0,0,0,0,0,0,0,0,1,0,0,0,1,1,1,0 ], [ 0,1,1,1,0,0,1,1,0,0,0,1,0,0,0,0 ],
+    [ 0,0,1,1,0,0,0,1,0,0,0,0,0,0,0,0 ], [ 0,0,0,0,1,0,0,0,1,1,0,0,1,1,1,0 ],
+    [ 0,0,0,0,0,0,0,0,1,0,0,0,1,1,0,0 ], [ 0,1,1,1,0,0,1,1,0,0,1,1,0,0,0,1 ],
+    [ 0,0,1,1,0,0,0,1,0,0,0,1,0,0,0,0 ], [ 0,0,0,0,1,0,0,0,1,0,0,0,1,1,0,0 ],
+    [ 0,1,1,0,0,1,1,0,0,1,1,0,0,1,1,0 ], [ 0,0,1,1,0,1,1,0,0,1,1,0,1,1,0,0 ],
+    [ 0,0,0,1,0,1,1,1,1,1,1,0,1,0,0,0 ], [ 0,0,0,0,1,1,1,1,1,1,1,1,0,0,0,0 ],
+    [ 0,1,1,1,0,0,0,1,1,0,0,0,1,1,1,0 ], [ 0,0,1,1,1,0,0,1,1,0,0,1,1,1,0,0 ],
+    [ 0,1,0,1,0,1,0,1,0,1,0,1,0,1,0,1 ], [ 0,0,0,0,1,1,1,1,0,0,0,0,1,1,1,1 ],
+    [ 0,1,0,1,1,0,1,0,0,1,0,1,1,0,1,0 ], [ 0,0,1,1,0,0,1,1,1,1,0,0,1,1,0,0 ],
+    [ 0,0,1,1,1,1,0,0,0,0,1,1,1,1,0,0 ], [ 0,1,0,1,0,1,0,1,1,0,1,0,1,0,1,0 ],
+    [ 0,1,1,0,1,0,0,1,0,1,1,0,1,0,0,1 ], [ 0,1,0,1,1,0,1,0,1,0,1,0,0,1,0,1 ],
+    [ 0,1,1,1,0,0,1,1,1,1,0,0,1,1,1,0 ], [ 0,0,0,1,0,0,1,1,1,1,0,0,1,0,0,0 ],
+    [ 0,0,1,1,0,0,1,0,0,1,0,0,1,1,0,0 ], [ 0,0,1,1,1,0,1,1,1,1,0,1,1,1,0,0 ],
+    [ 0,1,1,0,1,0,0,1,1,0,0,1,0,1,1,0 ], [ 0,0,1,1,1,1,0,0,1,1,0,0,0,0,1,1 ],
+    [ 0,1,1,0,0,1,1,0,1,0,0,1,1,0,0,1 ], [ 0,0,0,0,0,1,1,0,0,1,1,0,0,0,0,0 ],
+    [ 0,1,0,0,1,1,1,0,0,1,0,0,0,0,0,0 ], [ 0,0,1,0,0,1,1,1,0,0,1,0,0,0,0,0 ],
+    [ 0,0,0,0,0,0,1,0,0,1,1,1,0,0,1,0 ], [ 0,0,0,0,0,1,0,0,1,1,1,0,0,1,0,0 ],
+    [ 0,1,1,0,1,1,0,0,1,0,0,1,0,0,1,1 ], [ 0,0,1,1,0,1,1,0,1,1,0,0,1,0,0,1 ],
+    [ 0,1,1,0,0,0,1,1,1,0,0,1,1,1,0,0 ], [ 0,0,1,1,1,0,0,1,1,1,0,0,0,1,1,0 ],
+    [ 0,1,1,0,1,1,0,0,1,1,0,0,1,0,0,1 ], [ 0,1,1,0,0,0,1,1,0,0,1,1,1,0,0,1 ],
+    [ 0,1,1,1,1,1,1,0,1,0,0,0,0,0,0,1 ], [ 0,0,0,1,1,0,0,0,1,1,1,0,0,1,1,1 ],
+    [ 0,0,0,0,1,1,1,1,0,0,1,1,0,0,1,1 ], [ 0,0,1,1,0,0,1,1,1,1,1,1,0,0,0,0 ],
+    [ 0,0,1,0,0,0,1,0,1,1,1,0,1,1,1,0 ], [ 0,1,0,0,0,1,0,0,0,1,1,1,0,1,1,1 ],
+];
+
+static ALL_PATTERNS_3_BC7: [[u8; 16]; TOTAL_BC7_PATTERNS] = [
+    [ 0,0,1,1,0,0,1,1,0,2,2,1,2,2,2,2 ], [ 0,0,0,1,0,0,1,1,2,2,1,1,2,2,2,1 ],
+    [ 0,0,0,0,2,0,0,1,2,2,1,1,2,2,1,1 ], [ 0,2,2,2,0,0,2,2,0,0,1,1,0,1,1,1 ],
+    [ 0,0,0,0,0,0,0,0,1,1,2,2,1,1,2,2 ], [ 0,0,1,1,0,0,1,1,0,0,2,2,0,0,2,2 ],
+    [ 0,0,2,2,0,0,2,2,1,1,1,1,1,1,1,1 ], [ 0,0,1,1,0,0,1,1,2,2,1,1,2,2,1,1 ],
+    [ 0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2 ], [ 0,0,0,0,1,1,1,1,1,1,1,1,2,2,2,2 ],
+    [ 0,0,0,0,1,1,1,1,2,2,2,2,2,2,2,2 ], [ 0,0,1,2,0,0,1,2,0,0,1,2,0,0,1,2 ],
+    [ 0,1,1,2,0,1,1,2,0,1,1,2,0,1,1,2 ], [ 0,1,2,2,0,1,2,2,0,1,2,2,0,1,2,2 ],
+    [ 0,0,1,1,0,1,1,2,1,1,2,2,1,2,2,2 ], [ 0,0,1,1,2,0,0,1,2,2,0,0,2,2,2,0 ],
+    [ 0,0,0,1,0,0,1,1,0,1,1,2,1,1,2,2 ], [ 0,1,1,1,0,0,1,1,2,0,0,1,2,2,0,0 ],
+    [ 0,0,0,0,1,1,2,2,1,1,2,2,1,1,2,2 ], [ 0,0,2,2,0,0,2,2,0,0,2,2,1,1,1,1 ],
+    [ 0,1,1,1,0,1,1,1,0,2,2,2,0,2,2,2 ], [ 0,0,0,1,0,0,0,1,2,2,2,1,2,2,2,1 ],
+    [ 0,0,0,0,0,0,1,1,0,1,2,2,0,1,2,2 ], [ 0,0,0,0,1,1,0,0,2,2,1,0,2,2,1,0 ],
+    [ 0,1,2,2,0,1,2,2,0,0,1,1,0,0,0,0 ], [ 0,0,1,2,0,0,1,2,1,1,2,2,2,2,2,2 ],
+    [ 0,1,1,0,1,2,2,1,1,2,2,1,0,1,1,0 ], [ 0,0,0,0,0,1,1,0,1,2,2,1,1,2,2,1 ],
+    [ 0,0,2,2,1,1,0,2,1,1,0,2,0,0,2,2 ], [ 0,1,1,0,0,1,1,0,2,0,0,2,2,2,2,2 ],
+    [ 0,0,1,1,0,1,2,2,0,1,2,2,0,0,1,1 ], [ 0,0,0,0,2,0,0,0,2,2,1,1,2,2,2,1 ],
+    [ 0,0,0,0,0,0,0,2,1,1,2,2,1,2,2,2 ], [ 0,2,2,2,0,0,2,2,0,0,1,2,0,0,1,1 ],
+    [ 0,0,1,1,0,0,1,2,0,0,2,2,0,2,2,2 ], [ 0,1,2,0,0,1,2,0,0,1,2,0,0,1,2,0 ],
+    [ 0,0,0,0,1,1,1,1,2,2,2,2,0,0,0,0 ], [ 0,1,2,0,1,2,0,1,2,0,1,2,0,1,2,0 ],
+    [ 0,1,2,0,2,0,1,2,1,2,0,1,0,1,2,0 ], [ 0,0,1,1,2,2,0,0,1,1,2,2,0,0,1,1 ],
+    [ 0,0,1,1,1,1,2,2,2,2,0,0,0,0,1,1 ], [ 0,1,0,1,0,1,0,1,2,2,2,2,2,2,2,2 ],
+    [ 0,0,0,0,0,0,0,0,2,1,2,1,2,1,2,1 ], [ 0,0,2,2,1,1,2,2,0,0,2,2,1,1,2,2 ],
+    [ 0,0,2,2,0,0,1,1,0,0,2,2,0,0,1,1 ], [ 0,2,2,0,1,2,2,1,0,2,2,0,1,2,2,1 ],
+    [ 0,1,0,1,2,2,2,2,2,2,2,2,0,1,0,1 ], [ 0,0,0,0,2,1,2,1,2,1,2,1,2,1,2,1 ],
+    [ 0,1,0,1,0,1,0,1,0,1,0,1,2,2,2,2 ], [ 0,2,2,2,0,1,1,1,0,2,2,2,0,1,1,1 ],
+    [ 0,0,0,2,1,1,1,2,0,0,0,2,1,1,1,2 ], [ 0,0,0,0,2,1,1,2,2,1,1,2,2,1,1,2 ],
+    [ 0,2,2,2,0,1,1,1,0,1,1,1,0,2,2,2 ], [ 0,0,0,2,1,1,1,2,1,1,1,2,0,0,0,2 ],
+    [ 0,1,1,0,0,1,1,0,0,1,1,0,2,2,2,2 ], [ 0,0,0,0,0,0,0,0,2,1,1,2,2,1,1,2 ],
+    [ 0,1,1,0,0,1,1,0,2,2,2,2,2,2,2,2 ], [ 0,0,2,2,0,0,1,1,0,0,1,1,0,0,2,2 ],
+    [ 0,0,2,2,1,1,2,2,1,1,2,2,0,0,2,2 ], [ 0,0,0,0,0,0,0,0,0,0,0,0,2,1,1,2 ],
+    [ 0,0,0,2,0,0,0,1,0,0,0,2,0,0,0,1 ], [ 0,2,2,2,1,2,2,2,0,2,2,2,1,2,2,2 ],
+    [ 0,1,0,1,2,2,2,2,2,2,2,2,2,2,2,2 ], [ 0,1,1,1,2,0,1,1,2,2,0,1,2,2,2,0 ],
+];
+
 #[derive(Clone, Copy, Default)]
 struct OptimalEndpoint {
     lo: u8,
@@ -687,8 +994,8 @@ fn get_mode_8_bc7_tables() -> (&'static [OptimalEndpoint; 256], &'static [[Optim
 
     fn calculate_mode_8_bc7_tables() {
 
-        let weights2: [u8; 4] = [ 0, 21, 43, 64 ];
-        let weights4: [u8; 16] = [ 0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64 ];
+        let weights2 = WEIGHTS_2;
+        let weights4 = WEIGHTS_4;
 
         // TODO: Precompute?
         // BC7 777.1
@@ -758,3 +1065,166 @@ fn get_mode_8_bc7_tables() -> (&'static [OptimalEndpoint; 256], &'static [[Optim
         (&BC7_MODE_5_OPTIMAL_ENDPOINTS, &BC7_MODE_6_OPTIMAL_ENDPOINTS)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        for (pattern, anchors) in ALL_PATTERNS_2_BC7.iter().zip(PATTERNS_2_BC7_ANCHORS.iter()) {
+            for (subset, &anchor) in anchors.iter().enumerate() {
+                assert_eq!(pattern[anchor as usize], subset as u8);
+            }
+        }
+        for (pattern, anchors) in ALL_PATTERNS_3_BC7.iter().zip(PATTERNS_3_BC7_ANCHORS.iter()) {
+            for (subset, &anchor) in anchors.iter().enumerate() {
+                assert_eq!(pattern[anchor as usize], subset as u8);
+            }
+        }
+
+        // The patterns shared with ASTC are copies of the full tables
+        for (pattern, &(index, _)) in PATTERNS_2_BC7.iter().zip(PATTERNS_2_BC7_INDEX_INV.iter()) {
+            assert_eq!(pattern, &ALL_PATTERNS_2_BC7[index as usize]);
+        }
+        for (pattern, &(index, _)) in PATTERNS_3_BC7.iter().zip(PATTERNS_3_BC7_INDEX_PERM.iter()) {
+            assert_eq!(pattern, &ALL_PATTERNS_3_BC7[index as usize]);
+        }
+        for (pattern, &(index, _)) in PATTERNS_2_3_BC7.iter().zip(PATTERNS_2_3_BC7_INDEX_PERM.iter()) {
+            assert_eq!(pattern, &ALL_PATTERNS_3_BC7[index as usize]);
+        }
+    }
+
+    #[test]
+    fn test_decode_block_mode_4() {
+        let mut block = [0u8; 16];
+        let writer = &mut BitWriterLsb::new(&mut block);
+        writer.write_u8(5, 1 << 4);
+        // Rotation swaps red and alpha
+        writer.write_u8(2, 1);
+        // Index selection, color uses the 3-bit weights
+        writer.write_bool(true);
+        for &(e0, e1) in &[(0, 31), (16, 16), (31, 0)] {
+            writer.write_u8(5, e0);
+            writer.write_u8(5, e1);
+        }
+        writer.write_u8(6, 0);
+        writer.write_u8(6, 63);
+        for i in 0..16 {
+            writer.write_u8(if i == 0 { 1 } else { 2 }, i % 4);
+        }
+        for i in 0..16 {
+            writer.write_u8(if i == 0 { 2 } else { 3 }, i / 2);
+        }
+
+        let interpolate = |e0: u32, e1: u32, w: u8| ((e0 * (64 - w as u32) + e1 * w as u32 + 32) >> 6) as u8;
+        for (i, pixel) in decode_block(&block).iter().enumerate() {
+            let color_weight = WEIGHTS_3[i / 2];
+            let alpha_weight = WEIGHTS_2[i % 4];
+            let expected = [
+                interpolate(0, 255, alpha_weight),
+                132,
+                interpolate(255, 0, color_weight),
+                interpolate(0, 255, color_weight),
+            ];
+            assert_eq!(pixel, &expected, "pixel {}", i);
+        }
+    }
+
+    /// Writes a block of one of the modes 0 to 3, which have RGB endpoints and a single weight plane
+    fn write_rgb_block(mode: u8, pattern: u8, endpoints: &[[u8; 3]], p_bits: &[u8], weights: &[u8; 16], anchors: &[usize]) -> [u8; 16] {
+        let bc7_mode = BC7_MODES[mode as usize];
+        let mut block = [0u8; 16];
+        let writer = &mut BitWriterLsb::new(&mut block);
+        writer.write_u8(mode as usize + 1, 1 << mode);
+        writer.write_u8(bc7_mode.pat_bits as usize, pattern);
+        for channel in 0..3 {
+            for endpoint in endpoints {
+                writer.write_u8(bc7_mode.color_bits as usize, endpoint[channel]);
+            }
+        }
+        for &p_bit in p_bits {
+            writer.write_u8(1, p_bit);
+        }
+        for (i, &weight) in weights.iter().enumerate() {
+            writer.write_u8(bc7_mode.weight_bits as usize - anchors.contains(&i) as usize, weight);
+        }
+        block
+    }
+
+    /// Interpolates the expanded endpoints of each pixel's subset, alpha is always 255
+    fn expected_rgb_pixels(endpoints: &[[u32; 3]], partition: &[usize; 16], weights: &[u8; 16], weight_table: &[u8]) -> [[u8; 4]; 16] {
+        let interpolate = |e0: u32, e1: u32, w: u8| ((e0 * (64 - w as u32) + e1 * w as u32 + 32) >> 6) as u8;
+        let mut pixels = [[255u8; 4]; 16];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let (e0, e1) = (endpoints[2 * partition[i]], endpoints[2 * partition[i] + 1]);
+            let w = weight_table[weights[i] as usize];
+            for c in 0..3 {
+                pixel[c] = interpolate(e0[c], e1[c], w);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_decode_block_mode_0() {
+        // Partition 0 of 3 subsets, the anchors are pixels 0, 3 and 15
+        let partition = [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2];
+        let weights = [0, 1, 2, 3, 4, 5, 6, 7, 7, 6, 5, 4, 3, 2, 1, 0];
+        let block = write_rgb_block(
+            0, 0,
+            &[[15, 0, 0], [0, 15, 0], [0, 0, 15], [15, 15, 15], [8, 4, 2], [1, 9, 12]],
+            &[1, 0, 1, 0, 0, 1],
+            &weights,
+            &[0, 3, 15],
+        );
+
+        // 4 bits and a p-bit per endpoint, expanded from 5 bits: 0b11111 -> 255, 0b00001 -> 8,
+        // 0b11110 -> 247, 0b10000 -> 132 and so on
+        let endpoints = [[255, 8, 8], [0, 247, 0], [8, 8, 255], [247, 247, 247], [132, 66, 33], [24, 156, 206]];
+        assert_eq!(decode_block(&block), expected_rgb_pixels(&endpoints, &partition, &weights, &WEIGHTS_3));
+    }
+
+    #[test]
+    fn test_decode_block_mode_1() {
+        // Partition 2 of 2 subsets, the anchors are pixels 0 and 15
+        let partition = [0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1];
+        let weights = [3, 7, 6, 5, 4, 3, 2, 1, 0, 1, 2, 3, 4, 5, 6, 2];
+        let block = write_rgb_block(
+            1, 2,
+            &[[63, 0, 32], [0, 63, 10], [5, 40, 63], [60, 20, 0]],
+            &[1, 0],
+            &weights,
+            &[0, 15],
+        );
+
+        // 6 bits and a p-bit shared by the endpoints of a subset, expanded from 7 bits
+        let endpoints = [[255, 2, 131], [2, 255, 42], [20, 161, 253], [241, 80, 0]];
+        assert_eq!(decode_block(&block), expected_rgb_pixels(&endpoints, &partition, &weights, &WEIGHTS_3));
+    }
+
+    #[test]
+    fn test_decode_block_mode_2() {
+        // Partition 1 of 3 subsets, the anchors are pixels 0, 3 and 8
+        let partition = [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1];
+        let weights = [0, 1, 2, 1, 3, 2, 1, 0, 1, 2, 3, 0, 1, 2, 3, 3];
+        let block = write_rgb_block(
+            2, 1,
+            &[[31, 0, 0], [0, 0, 31], [0, 31, 0], [31, 31, 31], [10, 20, 30], [25, 5, 15]],
+            &[],
+            &weights,
+            &[0, 3, 8],
+        );
+
+        // 5 bits per endpoint without p-bits
+        let endpoints = [[255, 0, 0], [0, 0, 255], [0, 255, 0], [255, 255, 255], [82, 165, 247], [206, 41, 123]];
+        assert_eq!(decode_block(&block), expected_rgb_pixels(&endpoints, &partition, &weights, &WEIGHTS_2));
+    }
+
+    #[test]
+    fn test_decode_block_reserved_mode() {
+        let mut block = [0xFFu8; 16];
+        block[0] = 0;
+        assert_eq!(decode_block(&block), [[0; 4]; 16]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::io;
+
+use crate::{
+    TextureType,
+    TranscoderTextureFormat,
+};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading the file failed
+    Io(io::Error),
+    /// Signature mismatch, not a Basis Universal file
+    BadSignature,
+    /// The file header is malformed
+    InvalidHeader(&'static str),
+    /// CRC16 of the header does not match
+    HeaderCrcMismatch,
+    /// CRC16 of the data following the header does not match
+    DataCrcMismatch,
+    /// CRC16 of the slice does not match
+    SliceCrcMismatch { slice: u32 },
+    /// The data ends before all the structures it describes
+    TruncatedData,
+    /// The file can't be transcoded to the requested format
+    UnsupportedTextureFormat(TranscoderTextureFormat),
+    /// The file uses global or hybrid selector codebooks
+    UnsupportedSelectorCodebook,
+    /// Huffman code lengths don't describe a valid table
+    InvalidHuffmanTable,
+    /// The bit stream contains a code which is not in the Huffman table
+    InvalidHuffmanCode,
+    /// The slice descriptions don't match each other or the header
+    InvalidSlice(&'static str),
+    /// The slice data violates the specification, see the Illegal Encodings chapter
+    IllegalEncoding { slice: u32, block_x: u32, block_y: u32, reason: &'static str },
+    /// The block uses an invalid mode, pattern or component selector, or an out of range solid color
+    InvalidBlock,
+    /// The file does not contain the requested image or level
+    ImageNotFound { image: u32, level: u32 },
+    /// The operation is not supported for this type of texture
+    UnsupportedTextureType(TextureType),
+    /// The format can't store a texture of these dimensions (padded to whole blocks),
+    /// PVRTC1 requires square powers of two. Also returned when opening a file with a slice
+    /// larger than the maximum block count, or decoding ASTC with a block footprint that
+    /// isn't one of the 2D ASTC footprints.
+    UnsupportedDimensions { width: u32, height: u32 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::BadSignature => write!(f, "Sig mismatch, not a Basis Universal file"),
+            Error::InvalidHeader(reason) => write!(f, "Invalid header: {}", reason),
+            Error::HeaderCrcMismatch => write!(f, "Header CRC16 failed"),
+            Error::DataCrcMismatch => write!(f, "Data CRC16 failed"),
+            Error::SliceCrcMismatch { slice } => write!(f, "Slice {} CRC16 failed", slice),
+            Error::TruncatedData => write!(f, "Data is truncated"),
+            Error::UnsupportedTextureFormat(format) => write!(f, "Transcoding to {:?} is not supported for this file", format),
+            Error::UnsupportedSelectorCodebook => write!(f, "Global and hybrid selector codebooks are not supported"),
+            Error::InvalidHuffmanTable => write!(f, "Invalid Huffman table"),
+            Error::InvalidHuffmanCode => write!(f, "No matching code found in the Huffman table"),
+            Error::InvalidSlice(reason) => write!(f, "Invalid slice: {}", reason),
+            Error::IllegalEncoding { slice, block_x, block_y, reason } => {
+                write!(f, "Illegal encoding in slice {} at block ({}, {}): {}", slice, block_x, block_y, reason)
+            }
+            Error::InvalidBlock => write!(f, "Invalid block"),
+            Error::ImageNotFound { image, level } => write!(f, "Level {} of image {} not found", level, image),
+            Error::UnsupportedTextureType(tex_type) => write!(f, "Operation is not supported for {:?} textures", tex_type),
+            Error::UnsupportedDimensions { width, height } => write!(f, "Dimensions {}x{} are not supported", width, height),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
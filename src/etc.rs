@@ -1,15 +1,21 @@
 use crate::{
     Color32,
+    Image,
     Result,
     bitreader::BitReaderLsb,
     bitwriter::BitWriterLsb,
     mask, uastc,
 };
 
+use std::convert::TryInto;
+
+pub const ETC1_BLOCK_SIZE: usize = 8;
+pub const EAC_BLOCK_SIZE: usize = 8;
+
 pub fn convert_block_from_uastc(bytes: &[u8], output: &mut [u8], alpha: bool) {
     match convert_block_from_uastc_result(bytes, output, alpha) {
         Ok(_) => (),
-        _ => output.copy_from_slice(&[0; 8]), // TODO: purple or black?
+        _ => output.fill(0), // TODO: purple or black?
     }
 }
 
@@ -28,7 +34,7 @@ fn convert_block_from_uastc_result(bytes: &[u8], output: &mut [u8], alpha: bool)
             uastc::skip_mode8_rgba(reader);
         }
 
-        let trans_flags = uastc::decode_mode8_etc1_flags(reader);
+        let trans_flags = uastc::decode_mode8_etc1_flags(reader)?;
 
         if !trans_flags.etc1d {
             writer.write_u8(8, trans_flags.etc1r << 4 | trans_flags.etc1r);
@@ -278,35 +284,325 @@ fn write_etc2_alpha_block(writer: &mut BitWriterLsb, etc2tm: u8, rgba: &[Color32
                 *val = (center + (modifier as i32 * multiplier as i32)).max(0).min(255) as u8;
             }
 
-            let mut selectors = 0u64;
-            for (i, c) in rgba.iter().enumerate() {
-                let a = c[3];
-                let best_selector = values.iter().enumerate()
-                    .min_by_key(|(_, &val)| {
-                        (val as i32 - a as i32).abs()
-                    })
-                    .map(|(i, _)| i)
-                    .unwrap() as u64;
+            let alphas = rgba.map(|c| c[3]);
+            write_etc2_alpha_indices(writer, center as u8, etc2tm, &values, &alphas);
+        }
+    }
+}
 
-                // Transpose to match ETC2 pixel order
-                let x = i / 4;
-                let y = i % 4;
-                let id = y * 4 + x;
+/// Writes an alpha block which picks the closest of the `values` for each alpha, given in raster order
+fn write_etc2_alpha_indices(writer: &mut BitWriterLsb, center: u8, etc2tm: u8, values: &[u8; 8], alphas: &[u8; 16]) {
+    let mut selectors = 0u64;
+    for (i, &a) in alphas.iter().enumerate() {
+        let best_selector = values.iter().enumerate()
+            .min_by_key(|(_, &val)| {
+                (val as i32 - a as i32).abs()
+            })
+            .map(|(i, _)| i)
+            .unwrap() as u64;
+
+        // Transpose to match ETC2 pixel order
+        let x = i / 4;
+        let y = i % 4;
+        let id = y * 4 + x;
+
+        selectors |= best_selector << (45 - id * 3);
+    }
 
-                selectors |= best_selector << (45 - id * 3);
-            }
+    writer.write_u8(8, center);
+    writer.write_u8(8, etc2tm);
+
+    // Weight indices, 16x 3 bits
+    for &byte in selectors.to_be_bytes().iter().skip(2) {
+        writer.write_u8(8, byte);
+    }
+}
+
+/// Encodes an EAC alpha block of a single value
+pub(crate) fn encode_solid_alpha_block(value: u8, output: &mut [u8]) {
+    write_solid_etc2_alpha_block(&mut BitWriterLsb::new(output), value);
+}
+
+/// Encodes the green channel of an ETC1S block, given by its four colors and selectors, as an EAC block
+pub(crate) fn encode_etc1s_alpha_block(colors: &[Color32; 4], selector: &Selector, output: &mut [u8]) {
+    let mut values = [0u8; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = colors[selector.get_selector(i % 4, i / 4)][1];
+    }
+    encode_eac_block(&values, output);
+}
+
+/// Encodes values in raster order as an EAC block.
+///
+/// The block has the layout of an ETC2 alpha block, which is the same as the layout of an R11 block.
+pub(crate) fn encode_eac_block(values: &[u8; 16], output: &mut [u8]) {
+    let writer = &mut BitWriterLsb::new(output);
 
-            writer.write_u8(8, center as u8);
-            // Multiplier, doesn't matter, but has to be non-zero, so choosing 1
-            // Modifier table index: choosing 13 (only one with 0 in it)
-            writer.write_u8(8, etc2tm);
+    let min_value = *values.iter().min().unwrap() as i32;
+    let max_value = *values.iter().max().unwrap() as i32;
+    if min_value == max_value {
+        write_solid_etc2_alpha_block(writer, min_value as u8);
+        return;
+    }
 
-            // Weight indices, 16x 3 bits, value 4 (0b100)
-            for &byte in selectors.to_be_bytes().iter().skip(2) {
-                writer.write_u8(8, byte);
+    let mut best_err = u32::MAX;
+    let mut best = (0, 0, [0u8; 8]);
+    for (table_index, mod_table) in ETC2_ALPHA_MODIFIERS.iter().enumerate() {
+        let mod_min = mod_table[ETC2_ALPHA_MODIFIERS_MIN_INDEX] as i32;
+        let mod_max = mod_table[ETC2_ALPHA_MODIFIERS_MAX_INDEX] as i32;
+        let multiplier = (max_value - min_value) / (mod_max - mod_min);
+
+        for multiplier in (multiplier.max(1)..=multiplier + 1).filter(|&m| m <= 15) {
+            // Center the range of the modifiers on the range of the values
+            let center = (min_value + max_value - (mod_min + mod_max) * multiplier) / 2;
+            for center in (center - 2).max(0)..=(center + 2).min(255) {
+                let mut table_values = [0u8; 8];
+                for (val, &modifier) in table_values.iter_mut().zip(mod_table.iter()) {
+                    *val = (center + modifier as i32 * multiplier).clamp(0, 255) as u8;
+                }
+                let err: u32 = values.iter()
+                    .map(|&value| {
+                        let d = table_values.iter().map(|&val| (val as i32 - value as i32).unsigned_abs()).min().unwrap();
+                        d * d
+                    })
+                    .sum();
+                if err < best_err {
+                    best_err = err;
+                    best = (center as u8, (multiplier as u8) << 4 | table_index as u8, table_values);
+                }
             }
         }
     }
+
+    let (center, etc2tm, table_values) = best;
+    write_etc2_alpha_indices(writer, center, etc2tm, &table_values, values);
+}
+
+/// Decodes an ETC2 RGB block to RGBA pixels in raster order.
+///
+/// ETC1 blocks are ETC2 blocks without the T, H and planar modes, which are stored as differential
+/// blocks whose second base color overflows. Such blocks are invalid ETC1, they decode the way ETC2 hardware shows them.
+pub fn decode_rgb_block(block: &[u8; 8]) -> [[u8; 4]; 16] {
+    if block[3] & 0b10 == 0 {
+        // Individual mode, two 4-bit base colors
+        let base_color = |shift: u8| Color32::new(block[0] >> shift & 0xF, block[1] >> shift & 0xF, block[2] >> shift & 0xF, 255);
+        return decode_etc1_subblocks(block, [color_4_to_8(base_color(4)), color_4_to_8(base_color(0))]);
+    }
+
+    // Differential mode, a 5-bit base color and a 3-bit signed difference to the second one
+    let base = [block[0] >> 3, block[1] >> 3, block[2] >> 3];
+    let mut second = [0i8; 3];
+    for (c, second) in second.iter_mut().enumerate() {
+        *second = base[c] as i8 + ((block[c] as i8) << 5 >> 5);
+    }
+    let overflows = |c: usize| !(0..32).contains(&second[c]);
+
+    if overflows(0) {
+        decode_t_mode(block)
+    } else if overflows(1) {
+        decode_h_mode(block)
+    } else if overflows(2) {
+        decode_planar_mode(block)
+    } else {
+        decode_etc1_subblocks(block, [
+            color_5_to_8(Color32::new(base[0], base[1], base[2], 255)),
+            color_5_to_8(Color32::new(second[0] as u8, second[1] as u8, second[2] as u8, 255)),
+        ])
+    }
+}
+
+/// Decodes an ETC1 or ETC2 RGB image, e.g. the `Etc1Rgb` output of transcoding, to RGBA bytes laid out like `Rgba32` output
+pub fn decode_rgb_image(image: &Image<u8>) -> Image<u8> {
+    crate::decode_block_image(image, ETC1_BLOCK_SIZE, 4, 4, |block| decode_rgb_block(block.try_into().unwrap()))
+}
+
+/// Decodes an ETC2 RGBA block, an EAC alpha block followed by an ETC2 RGB block, to RGBA pixels in raster order
+pub fn decode_rgba_block(block: &[u8; 16]) -> [[u8; 4]; 16] {
+    let mut pixels = decode_rgb_block(block[EAC_BLOCK_SIZE..].try_into().unwrap());
+    let alpha = decode_alpha_block(block[..EAC_BLOCK_SIZE].try_into().unwrap());
+    for (pixel, alpha) in pixels.iter_mut().zip(alpha) {
+        pixel[3] = alpha;
+    }
+    pixels
+}
+
+/// Decodes an ETC2 RGBA image, e.g. the `Etc2Rgba` output of transcoding, to RGBA bytes laid out like `Rgba32` output
+pub fn decode_rgba_image(image: &Image<u8>) -> Image<u8> {
+    crate::decode_block_image(image, 2 * ETC1_BLOCK_SIZE, 4, 4, |block| decode_rgba_block(block.try_into().unwrap()))
+}
+
+/// Decodes an EAC alpha block, the alpha part of an ETC2 RGBA block, to values in raster order
+pub fn decode_alpha_block(block: &[u8; 8]) -> [u8; 16] {
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = ETC2_ALPHA_MODIFIERS[(block[1] & 0xF) as usize];
+    eac_indices(block).map(|index| (base + modifiers[index] as i32 * multiplier).clamp(0, 255) as u8)
+}
+
+/// Decodes an unsigned EAC R11 block to 11-bit values in raster order.
+///
+/// A multiplier of zero doesn't flatten the block like in alpha blocks, the modifiers are then used unscaled.
+pub fn decode_r11_block(block: &[u8; 8]) -> [u16; 16] {
+    let base = block[0] as i32 * 8 + 4;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = ETC2_ALPHA_MODIFIERS[(block[1] & 0xF) as usize];
+    eac_indices(block).map(|index| {
+        let modifier = modifiers[index] as i32;
+        let modifier = if multiplier == 0 { modifier } else { modifier * multiplier * 8 };
+        (base + modifier).clamp(0, 2047) as u16
+    })
+}
+
+/// Decodes an unsigned EAC RG11 block, the R11 block of the red channel followed by the one of green,
+/// to 11-bit value pairs in raster order
+pub fn decode_rg11_block(block: &[u8; 16]) -> [[u16; 2]; 16] {
+    let red = decode_r11_block(block[..EAC_BLOCK_SIZE].try_into().unwrap());
+    let green = decode_r11_block(block[EAC_BLOCK_SIZE..].try_into().unwrap());
+    let mut values = [[0u16; 2]; 16];
+    for (value, (&red, &green)) in values.iter_mut().zip(red.iter().zip(green.iter())) {
+        *value = [red, green];
+    }
+    values
+}
+
+/// Decodes an unsigned EAC R11 image, e.g. the `EacR11` output of transcoding, to RGBA bytes laid out like `Rgba32` output.
+///
+/// The values are rounded to 8 bits and end up in the red channel, green and blue are zero and alpha is opaque, as sampled by GPUs.
+pub fn decode_r11_image(image: &Image<u8>) -> Image<u8> {
+    crate::decode_block_image(image, EAC_BLOCK_SIZE, 4, 4, |block| {
+        decode_r11_block(block.try_into().unwrap()).map(|red| [unorm11_to_u8(red), 0, 0, 255])
+    })
+}
+
+/// Decodes an unsigned EAC RG11 image, e.g. the `EacRg11` output of transcoding, to RGBA bytes laid out like `Rgba32` output.
+///
+/// The values are rounded to 8 bits and end up in the red and green channels, blue is zero and alpha is opaque, as sampled by GPUs.
+pub fn decode_rg11_image(image: &Image<u8>) -> Image<u8> {
+    crate::decode_block_image(image, 2 * EAC_BLOCK_SIZE, 4, 4, |block| {
+        decode_rg11_block(block.try_into().unwrap()).map(|[red, green]| [unorm11_to_u8(red), unorm11_to_u8(green), 0, 255])
+    })
+}
+
+fn unorm11_to_u8(value: u16) -> u8 {
+    ((value as u32 * 255 + 1023) / 2047) as u8
+}
+
+/// Decodes the two subblocks of an individual or differential block, given their base colors
+fn decode_etc1_subblocks(block: &[u8; 8], base_colors: [Color32; 2]) -> [[u8; 4]; 16] {
+    let flip = block[3] & 1 != 0;
+    let subblock_colors = [
+        apply_mod_to_base_color(base_colors[0], block[3] >> 5),
+        apply_mod_to_base_color(base_colors[1], block[3] >> 2 & 0b111),
+    ];
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, (pixel, index)) in pixels.iter_mut().zip(pixel_indices(block)).enumerate() {
+        let (x, y) = (i % 4, i / 4);
+        let subblock = if flip { y / 2 } else { x / 2 };
+        *pixel = subblock_colors[subblock][ETC1_TO_SELECTOR_ID[index] as usize].0;
+    }
+    pixels
+}
+
+/// T mode, the first base color and three colors around the second one
+fn decode_t_mode(block: &[u8; 8]) -> [[u8; 4]; 16] {
+    let c0 = color_4_to_8(Color32::new(block[0] >> 1 & 0b1100 | block[0] & 0b11, block[1] >> 4, block[1] & 0xF, 255));
+    let c1 = color_4_to_8(Color32::new(block[2] >> 4, block[2] & 0xF, block[3] >> 4, 255));
+    let distance = ETC2_DISTANCES[(block[3] >> 1 & 0b110 | block[3] & 1) as usize];
+    decode_paint_colors(block, [c0, add_to_color(c1, distance), c1, add_to_color(c1, -distance)])
+}
+
+/// H mode, two colors around each of the base colors
+fn decode_h_mode(block: &[u8; 8]) -> [[u8; 4]; 16] {
+    let c0 = color_4_to_8(Color32::new(
+        block[0] >> 3 & 0xF,
+        (block[0] & 0b111) << 1 | block[1] >> 4 & 1,
+        block[1] & 0b1000 | (block[1] & 0b11) << 1 | block[2] >> 7,
+        255,
+    ));
+    let c1 = color_4_to_8(Color32::new(block[2] >> 3 & 0xF, (block[2] & 0b111) << 1 | block[3] >> 7, block[3] >> 3 & 0xF, 255));
+    // The lowest bit of the distance index is given by the order of the base colors
+    let order = (c0[0], c0[1], c0[2]) >= (c1[0], c1[1], c1[2]);
+    let distance = ETC2_DISTANCES[(block[3] & 0b100 | (block[3] & 1) << 1 | order as u8) as usize];
+    decode_paint_colors(block, [
+        add_to_color(c0, distance),
+        add_to_color(c0, -distance),
+        add_to_color(c1, distance),
+        add_to_color(c1, -distance),
+    ])
+}
+
+/// Planar mode, the colors are interpolated from the colors at the origin, at x = 4 and at y = 4 without indices
+fn decode_planar_mode(block: &[u8; 8]) -> [[u8; 4]; 16] {
+    let b = block.map(|byte| byte as i32);
+    let extend_6_to_8 = |x: i32| x << 2 | x >> 4;
+    let extend_7_to_8 = |x: i32| x << 1 | x >> 6;
+
+    let origin = [
+        extend_6_to_8(b[0] >> 1 & 0x3F),
+        extend_7_to_8((b[0] & 1) << 6 | b[1] >> 1 & 0x3F),
+        extend_6_to_8((b[1] & 1) << 5 | b[2] & 0b11000 | (b[2] & 0b11) << 1 | b[3] >> 7),
+    ];
+    let horizontal = [
+        extend_6_to_8(b[3] >> 1 & 0b111110 | b[3] & 1),
+        extend_7_to_8(b[4] >> 1),
+        extend_6_to_8((b[4] & 1) << 5 | b[5] >> 3),
+    ];
+    let vertical = [
+        extend_6_to_8((b[5] & 0b111) << 3 | b[6] >> 5),
+        extend_7_to_8((b[6] & 0x1F) << 2 | b[7] >> 6),
+        extend_6_to_8(b[7] & 0x3F),
+    ];
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = ((i % 4) as i32, (i / 4) as i32);
+        for c in 0..3 {
+            let value = x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]) + 4 * origin[c];
+            pixel[c] = ((value + 2) >> 2).clamp(0, 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+    pixels
+}
+
+/// Looks up the paint color of each pixel of a T or H mode block
+fn decode_paint_colors(block: &[u8; 8], paint_colors: [Color32; 4]) -> [[u8; 4]; 16] {
+    pixel_indices(block).map(|index| paint_colors[index].0)
+}
+
+fn add_to_color(color: Color32, value: i16) -> Color32 {
+    let add = |c: usize| (color[c] as i16 + value).clamp(0, 255) as u8;
+    Color32::new(add(0), add(1), add(2), 255)
+}
+
+/// Returns the 2-bit pixel indices of an ETC1 or ETC2 RGB block in raster order
+fn pixel_indices(block: &[u8; 8]) -> [usize; 16] {
+    let msb = u16::from_be_bytes([block[4], block[5]]);
+    let lsb = u16::from_be_bytes([block[6], block[7]]);
+
+    let mut indices = [0; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        // Pixels are stored in columns
+        let id = (i % 4) * 4 + i / 4;
+        *index = ((msb >> id & 1) << 1 | lsb >> id & 1) as usize;
+    }
+    indices
+}
+
+/// Returns the 3-bit modifier indices of an EAC block in raster order
+fn eac_indices(block: &[u8; 8]) -> [usize; 16] {
+    let mut index_bytes = [0u8; 8];
+    index_bytes[2..].copy_from_slice(&block[2..]);
+    let bits = u64::from_be_bytes(index_bytes);
+
+    let mut indices = [0; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        // Pixels are stored in columns
+        let id = (i % 4) * 4 + i / 4;
+        *index = ((bits >> (45 - 3 * id)) & 0b111) as usize;
+    }
+    indices
 }
 
 #[derive(Clone, Copy, Debug,  Default)]
@@ -400,11 +696,108 @@ pub(crate) fn apply_mod_to_base_color(base: Color32, inten: u8) -> [Color32; 4]
     colors
 }
 
+/// Returns the lowest and the highest selector used in the block
+pub(crate) fn selector_range(selector: &Selector) -> (usize, usize) {
+    let mut lo = 3;
+    let mut hi = 0;
+    for y in 0..4 {
+        for x in 0..4 {
+            let sel = selector.get_selector(x, y);
+            lo = lo.min(sel);
+            hi = hi.max(sel);
+        }
+    }
+    (lo, hi)
+}
+
+/// Finds endpoints and weights for the colors of the selectors used in a BC7 or ASTC block,
+/// both interpolate 8-bit endpoints with 6-bit weights.
+///
+/// Starts from the extreme colors and refines the endpoints with least squares fits to the chosen weights.
+pub(crate) fn fit_selector_endpoints(
+    colors: &[Color32; 4], selector: &Selector, weight_values: &[u8],
+    quantize: impl Fn(i32) -> u8, unquantize: impl Fn(u8) -> u8,
+) -> ([Color32; 2], [u8; 4]) {
+    let quantize_color = |color: [i32; 3]| Color32::new(quantize(color[0]), quantize(color[1]), quantize(color[2]), 0);
+    let unquantize_endpoints = |endpoints: [Color32; 2]| endpoints.map(|e| Color32::new(unquantize(e[0]), unquantize(e[1]), unquantize(e[2]), 0));
+    let color_values = |color: Color32| [color[0] as i32, color[1] as i32, color[2] as i32];
+
+    let (lo, hi) = selector_range(selector);
+    let mut counts = [0u32; 4];
+    for y in 0..4 {
+        for x in 0..4 {
+            counts[selector.get_selector(x, y)] += 1;
+        }
+    }
+
+    let mut endpoints = [quantize_color(color_values(colors[lo])), quantize_color(color_values(colors[hi]))];
+    let (mut weights, mut err) = selector_weights(colors, &counts, unquantize_endpoints(endpoints), weight_values);
+
+    for _ in 0..2 {
+        let (mut aa, mut ab, mut bb) = (0.0, 0.0, 0.0);
+        let (mut av, mut bv) = ([0.0f32; 3], [0.0f32; 3]);
+        for sel in lo..=hi {
+            let count = counts[sel] as f32;
+            let b = weight_values[weights[sel] as usize] as f32 / 64.0;
+            let a = 1.0 - b;
+            aa += count * a * a;
+            ab += count * a * b;
+            bb += count * b * b;
+            for c in 0..3 {
+                av[c] += count * a * colors[sel][c] as f32;
+                bv[c] += count * b * colors[sel][c] as f32;
+            }
+        }
+        let det = aa * bb - ab * ab;
+        if det.abs() <= f32::EPSILON {
+            break;
+        }
+
+        let mut fit = [[0i32; 3]; 2];
+        for c in 0..3 {
+            fit[0][c] = (((av[c] * bb - bv[c] * ab) / det).round() as i32).clamp(0, 255);
+            fit[1][c] = (((bv[c] * aa - av[c] * ab) / det).round() as i32).clamp(0, 255);
+        }
+        let fit_endpoints = [quantize_color(fit[0]), quantize_color(fit[1])];
+        let (fit_weights, fit_err) = selector_weights(colors, &counts, unquantize_endpoints(fit_endpoints), weight_values);
+        if fit_err >= err {
+            break;
+        }
+        endpoints = fit_endpoints;
+        weights = fit_weights;
+        err = fit_err;
+    }
+    (endpoints, weights)
+}
+
+/// Picks the weight which best reproduces the color of each used selector, returns the weights and the error of the block
+fn selector_weights(colors: &[Color32; 4], counts: &[u32; 4], endpoints: [Color32; 2], weight_values: &[u8]) -> ([u8; 4], i32) {
+    let mut weights = [0u8; 4];
+    let mut total_err = 0;
+    for sel in (0..4).filter(|&sel| counts[sel] > 0) {
+        let err = |weight: u8| -> i32 {
+            let w = weight_values[weight as usize] as i32;
+            (0..3).map(|c| {
+                let value = (endpoints[0][c] as i32 * (64 - w) + endpoints[1][c] as i32 * w + 32) >> 6;
+                let d = value - colors[sel][c] as i32;
+                d * d
+            }).sum()
+        };
+        weights[sel] = (0..weight_values.len() as u8).min_by_key(|&weight| err(weight)).unwrap();
+        total_err += counts[sel] as i32 * err(weights[sel]);
+    }
+    (weights, total_err)
+}
+
 static SELECTOR_ID_TO_ETC1: [u8; 4] = [
     0b11, 0b10, 0b00, 0b01,
 ];
 
-static ETC1_MODIFIERS: [[i16; 4]; 8] = [
+static ETC1_TO_SELECTOR_ID: [u8; 4] = [
+    2, 3, 1, 0,
+];
+
+pub(crate) static ETC1_MODIFIERS: [[i16; 4]; 8] = [
     [   -8,  -2,  2,   8 ],
     [  -17,  -5,  5,  17 ],
     [  -29,  -9,  9,  29 ],
@@ -415,10 +808,15 @@ static ETC1_MODIFIERS: [[i16; 4]; 8] = [
     [ -183, -47, 47, 183 ],
 ];
 
+/// Distances of the T and H mode colors from the base colors
+static ETC2_DISTANCES: [i16; 8] = [
+    3, 6, 11, 16, 23, 32, 41, 64,
+];
+
 const ETC2_ALPHA_MODIFIERS_MIN_INDEX: usize = 3;
 const ETC2_ALPHA_MODIFIERS_MAX_INDEX: usize = 7;
 
-static ETC2_ALPHA_MODIFIERS: [[i8; 8]; 16] = [
+pub(crate) static ETC2_ALPHA_MODIFIERS: [[i8; 8]; 16] = [
     [ -3, -6,  -9, -15, 2, 5, 8, 14 ],
     [ -3, -7, -10, -13, 2, 6, 9, 12 ],
     [ -2, -5,  -8, -13, 1, 4, 7, 12 ],
@@ -436,3 +834,147 @@ static ETC2_ALPHA_MODIFIERS: [[i8; 8]; 16] = [
     [ -4, -6,  -8,  -9, 3, 5, 7,  8 ],
     [ -3, -5,  -7,  -9, 2, 4, 6,  8 ],
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expected pixels of a block whose pixel indices equal their column
+    fn column_pixels(colors: [[u8; 3]; 4]) -> [[u8; 4]; 16] {
+        let mut pixels = [[0u8; 4]; 16];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let [r, g, b] = colors[i % 4];
+            *pixel = [r, g, b, 255];
+        }
+        pixels
+    }
+
+    // Pixel indices 0, 1, 2 and 3 in the columns
+    const COLUMN_INDICES: [u8; 4] = [0xFF, 0x00, 0xF0, 0xF0];
+
+    fn test_etc1_block(color_bytes: [u8; 3], codewords: [u8; 2], diff: bool, flip: bool, base_colors: [Color32; 2]) {
+        let mut selector = Selector::default();
+        for y in 0..4 {
+            for x in 0..4 {
+                selector.set_selector(x, y, ((x + 2 * y) % 4) as u8);
+            }
+        }
+        let mut block = [0u8; 8];
+        block[..3].copy_from_slice(&color_bytes);
+        block[3] = codewords[0] << 5 | codewords[1] << 2 | (diff as u8) << 1 | flip as u8;
+        block[4..].copy_from_slice(&selector.etc1_bytes);
+
+        let pixels = decode_rgb_block(&block);
+        for (i, pixel) in pixels.iter().enumerate() {
+            let (x, y) = (i % 4, i / 4);
+            let subblock = if flip { y / 2 } else { x / 2 };
+            let colors = apply_mod_to_base_color(base_colors[subblock], codewords[subblock]);
+            assert_eq!(*pixel, colors[selector.get_selector(x, y)].0, "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn test_decode_individual_block() {
+        test_etc1_block([0xA3, 0x5C, 0x18], [2, 6], false, true, [
+            Color32::new(0xAA, 0x55, 0x11, 255),
+            Color32::new(0x33, 0xCC, 0x88, 255),
+        ]);
+    }
+
+    #[test]
+    fn test_decode_differential_block() {
+        // Differences of -2, 3 and 0
+        test_etc1_block([10 << 3 | 0b110, 20 << 3 | 0b011, 0], [1, 7], true, false, [
+            color_5_to_8(Color32::new(10, 20, 0, 255)),
+            color_5_to_8(Color32::new(8, 23, 0, 255)),
+        ]);
+    }
+
+    #[test]
+    fn test_decode_t_mode() {
+        // Red overflows, 30 + 3
+        let mut block = [0xF3, 0x5A, 0x37, 0xCB, 0, 0, 0, 0];
+        block[4..].copy_from_slice(&COLUMN_INDICES);
+        assert_eq!(decode_rgb_block(&block), column_pixels([
+            [0xBB, 0x55, 0xAA],
+            [0x53, 0x97, 0xEC],
+            [0x33, 0x77, 0xCC],
+            [0x13, 0x57, 0xAC],
+        ]));
+    }
+
+    #[test]
+    fn test_decode_h_mode() {
+        // Green overflows, 1 - 3
+        let mut block = [0x4B, 0x0D, 0x16, 0x3E, 0, 0, 0, 0];
+        block[4..].copy_from_slice(&COLUMN_INDICES);
+        assert_eq!(decode_rgb_block(&block), column_pixels([
+            [0xB9, 0x86, 0xCA],
+            [0x79, 0x46, 0x8A],
+            [0x42, 0xEC, 0x97],
+            [0x02, 0xAC, 0x57],
+        ]));
+
+        // Swapping the base colors selects the smaller distance
+        let mut block = [0x16, 0x07, 0xCB, 0x56, 0, 0, 0, 0];
+        block[4..].copy_from_slice(&COLUMN_INDICES);
+        assert_eq!(decode_rgb_block(&block), column_pixels([
+            [0x39, 0xE3, 0x8E],
+            [0x0B, 0xB5, 0x60],
+            [0xB0, 0x7D, 0xC1],
+            [0x82, 0x4F, 0x93],
+        ]));
+    }
+
+    #[test]
+    fn test_decode_planar_mode() {
+        // Blue overflows, 0 - 4
+        let block = [0x41, 0x01, 0x04, 0x62, 0x40, 0x81, 0x1F, 0xFF];
+        let pixels = decode_rgb_block(&block);
+        assert_eq!(pixels[0], [130, 129, 130, 255]);
+        assert_eq!(pixels[3], [179, 80, 81, 255]);
+        assert_eq!(pixels[12], [57, 224, 224, 255]);
+        assert_eq!(pixels[15], [105, 175, 175, 255]);
+    }
+
+    fn eac_block(base: u8, multiplier: u8, table: u8, indices: [u8; 16]) -> [u8; 8] {
+        let mut bits = 0u64;
+        for (i, &index) in indices.iter().enumerate() {
+            let id = (i % 4) * 4 + i / 4;
+            bits |= (index as u64) << (45 - 3 * id);
+        }
+        let mut block = [0u8; 8];
+        block[0] = base;
+        block[1] = multiplier << 4 | table;
+        block[2..].copy_from_slice(&bits.to_be_bytes()[2..]);
+        block
+    }
+
+    #[test]
+    fn test_decode_eac_blocks() {
+        let indices = [0, 1, 2, 3, 4, 5, 6, 7, 7, 6, 5, 4, 3, 2, 1, 0];
+        let modifiers = ETC2_ALPHA_MODIFIERS[13];
+
+        let block = eac_block(0x80, 3, 13, indices);
+        assert_eq!(decode_alpha_block(&block), indices.map(|index| (0x80 + 3 * modifiers[index as usize] as i32) as u8));
+        assert_eq!(decode_r11_block(&block), indices.map(|index| (0x404 + 24 * modifiers[index as usize] as i32) as u16));
+
+        // A zero multiplier gives a solid alpha block, but still applies the modifiers to R11
+        let block = eac_block(0x80, 0, 13, indices);
+        assert_eq!(decode_alpha_block(&block), [0x80; 16]);
+        assert_eq!(decode_r11_block(&block), indices.map(|index| (0x404 + modifiers[index as usize] as i32) as u16));
+
+        // Values are clamped
+        let block = eac_block(0x02, 15, 0, [3; 16]);
+        assert_eq!(decode_alpha_block(&block), [0; 16]);
+        assert_eq!(decode_r11_block(&block), [0; 16]);
+        let block = eac_block(0xFE, 15, 0, [7; 16]);
+        assert_eq!(decode_alpha_block(&block), [255; 16]);
+        assert_eq!(decode_r11_block(&block), [2047; 16]);
+
+        let mut rg11 = [0u8; 16];
+        rg11[..8].copy_from_slice(&eac_block(0, 0, 0, [0; 16]));
+        rg11[8..].copy_from_slice(&eac_block(0xFF, 0, 0, [7; 16]));
+        assert_eq!(decode_rg11_block(&rg11), [[1, 2047]; 16]);
+    }
+}
@@ -4,12 +4,20 @@ use std::ops::{
     IndexMut,
 };
 use crate::{
+    Channel,
     Color32,
+    astc,
+    Error,
+    bc1,
+    bc4,
+    bc7,
     etc::{self, Selector},
     Image,
     mask,
+    pvrtc,
     Result,
     basis::{
+        self,
         Header,
         SliceDesc,
         TextureType,
@@ -59,6 +67,27 @@ pub struct DecodedBlock {
     selector_index: u16,
 }
 
+#[derive(Clone, Copy, Default)]
+pub(crate) struct PrevFrameIndices {
+    endpoint_index: u16,
+    selector_index: u16,
+}
+
+/// Block indices of the last decoded frame of a video, P-frames reuse them.
+///
+/// Files which are not videos don't use it, an empty state can be passed in.
+#[derive(Clone, Default)]
+pub(crate) struct FrameState {
+    rgb: Vec<PrevFrameIndices>,
+    alpha: Vec<PrevFrameIndices>,
+}
+
+impl FrameState {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rgb.is_empty()
+    }
+}
+
 pub struct Decoder {
     endpoint_pred_model: HuffmanDecodingTable,
     delta_endpoint_model: HuffmanDecodingTable,
@@ -77,28 +106,26 @@ impl Decoder {
     pub(crate) fn from_file_bytes(header: &Header, bytes: &[u8]) -> Result<Self> {
         let endpoints = {
             let num_endpoints = header.total_endpoints as usize;
-            let start = header.endpoint_cb_file_ofs as usize;
-            let len = header.endpoint_cb_file_size as usize;
-            decode_endpoints(num_endpoints, &bytes[start..start + len])?
+            let bytes = basis::file_range(bytes, header.endpoint_cb_file_ofs, header.endpoint_cb_file_size)?;
+            decode_endpoints(num_endpoints, bytes)?
         };
 
         let selectors = {
             let num_selectors = header.total_selectors as usize;
-            let start = header.selector_cb_file_ofs as usize;
-            let len = header.selector_cb_file_size as usize;
-            decode_selectors(num_selectors, &bytes[start..start + len])?
+            let bytes = basis::file_range(bytes, header.selector_cb_file_ofs, header.selector_cb_file_size)?;
+            decode_selectors(num_selectors, bytes)?
         };
 
-        let start = header.tables_file_ofs as usize;
-        let len = header.tables_file_size as usize;
-
-        let reader = &mut BitReaderLsb::new(&bytes[start..start + len]);
+        let reader = &mut BitReaderLsb::new(basis::file_range(bytes, header.tables_file_ofs, header.tables_file_size)?);
 
         let endpoint_pred_model = huffman::read_huffman_table(reader)?;
         let delta_endpoint_model = huffman::read_huffman_table(reader)?;
         let selector_model = huffman::read_huffman_table(reader)?;
         let selector_history_buf_rle_model = huffman::read_huffman_table(reader)?;
         let selector_history_buffer_size = reader.read_u32(13);
+        if selector_history_buffer_size > MAX_SELECTOR_HISTORY_BUF_SIZE {
+            return Err(Error::InvalidHeader("selector history buffer is too large"));
+        }
 
         Ok(Self {
             endpoint_pred_model,
@@ -113,23 +140,16 @@ impl Decoder {
         })
     }
 
-    pub(crate) fn decode_to_rgba(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8]) -> Result<Image<Color32>> {
-        if let Some(alpha_desc) = alpha_desc {
-            if !alpha_desc.has_alpha() {
-                return Err("Expected slice with alpha".into());
-            }
-            if alpha_desc.num_blocks_x != rgb_desc.num_blocks_x || alpha_desc.num_blocks_y != rgb_desc.num_blocks_y {
-                return Err("RGB slice and Alpha slice have different dimensions".into());
-            }
-        }
+    pub(crate) fn decode_to_rgba(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<Color32>> {
+        check_alpha_slice(rgb_desc, alpha_desc)?;
 
         let num_blocks_x = rgb_desc.num_blocks_x as u32;
         let num_blocks_y = rgb_desc.num_blocks_y as u32;
         let mut rgba = vec![Color32::default(); (num_blocks_x * num_blocks_y) as usize * 16];
 
-        self.decode_to_rgba_internal(rgb_desc, bytes, &mut rgba, false)?;
+        self.decode_to_rgba_internal(rgb_desc, bytes, &mut rgba, false, &mut frame_state.rgb)?;
         if let Some(alpha_desc) = alpha_desc {
-            self.decode_to_rgba_internal(alpha_desc, bytes, &mut rgba, true)?;
+            self.decode_to_rgba_internal(alpha_desc, bytes, &mut rgba, true, &mut frame_state.alpha)?;
         }
 
         Ok(Image {
@@ -141,7 +161,7 @@ impl Decoder {
         })
     }
 
-    fn decode_to_rgba_internal(&self, slice_desc: &SliceDesc, bytes: &[u8], pixels: &mut [Color32], alpha: bool) -> Result<()> {
+    fn decode_to_rgba_internal(&self, slice_desc: &SliceDesc, bytes: &[u8], pixels: &mut [Color32], alpha: bool, prev_frame_indices: &mut Vec<PrevFrameIndices>) -> Result<()> {
         let block_to_rgba = |block: DecodedBlock| {
             let endpoint: Endpoint = self.endpoints[block.endpoint_index as usize];
             let selector: Selector = self.selectors[block.selector_index as usize];
@@ -167,12 +187,21 @@ impl Decoder {
             }
         };
 
-        self.decode_blocks(slice_desc, bytes, block_to_rgba)?;
+        self.decode_blocks(slice_desc, bytes, prev_frame_indices, block_to_rgba)?;
+
+        Ok(())
+    }
 
+    /// Decodes the block indices of a video frame, without producing any output
+    pub(crate) fn skip_frame(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState) -> Result<()> {
+        self.decode_blocks(rgb_desc, bytes, &mut frame_state.rgb, |_| {})?;
+        if let Some(alpha_desc) = alpha_desc {
+            self.decode_blocks(alpha_desc, bytes, &mut frame_state.alpha, |_| {})?;
+        }
         Ok(())
     }
 
-    pub(crate) fn transcode_to_etc1(&self, slice_desc: &SliceDesc, bytes: &[u8]) -> Result<Image<u8>> {
+    pub(crate) fn transcode_to_etc1(&self, slice_desc: &SliceDesc, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<u8>> {
         let num_blocks_x = slice_desc.num_blocks_x as u32;
         let num_blocks_y = slice_desc.num_blocks_y as u32;
 
@@ -184,25 +213,151 @@ impl Decoder {
 
         let block_to_etc1 = |block: DecodedBlock| {
             let endpoint: Endpoint = self.endpoints[block.endpoint_index as usize];
-            let selector: Selector = self.selectors[block.selector_index as usize];
+            let selector: &Selector = &self.selectors[block.selector_index as usize];
+
+            let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+            let block_start = BLOCK_SIZE * block_id;
+            write_etc1_block(endpoint, selector, &mut blocks[block_start..block_start + BLOCK_SIZE]);
+        };
+
+        self.decode_blocks(slice_desc, bytes, &mut frame_state.rgb, block_to_etc1)?;
+
+        Ok(Image {
+            w: slice_desc.orig_width as u32,
+            h: slice_desc.orig_height as u32,
+            stride: BLOCK_SIZE as u32 * slice_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: blocks,
+        })
+    }
+
+    pub(crate) fn transcode_to_etc2(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<u8>> {
+        check_alpha_slice(rgb_desc, alpha_desc)?;
+
+        let num_blocks_x = rgb_desc.num_blocks_x as u32;
+        let num_blocks_y = rgb_desc.num_blocks_y as u32;
+
+        const ALPHA_BLOCK_SIZE: usize = 8;
+        const BLOCK_SIZE: usize = ALPHA_BLOCK_SIZE + 8;
+
+        let block_count = (num_blocks_x * num_blocks_y) as usize;
+
+        let mut blocks = vec![0u8; BLOCK_SIZE * block_count];
+
+        // EAC alpha block first, ETC1 color block second
+        let mut block_to_etc2 = |block: DecodedBlock, alpha: bool| {
+            let endpoint: Endpoint = self.endpoints[block.endpoint_index as usize];
+            let selector: &Selector = &self.selectors[block.selector_index as usize];
 
             let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
             let block_start = BLOCK_SIZE * block_id;
             let block = &mut blocks[block_start..block_start + BLOCK_SIZE];
+            if alpha {
+                let colors = etc::apply_mod_to_base_color(etc::color_5_to_8(endpoint.color5), endpoint.inten5);
+                etc::encode_etc1s_alpha_block(&colors, selector, &mut block[..ALPHA_BLOCK_SIZE]);
+            } else {
+                write_etc1_block(endpoint, selector, &mut block[ALPHA_BLOCK_SIZE..]);
+            }
+        };
 
-            // color_r: 5 | delta: 3
-            block[0] = endpoint.color5[0] << 3;
-            // color_g: 5 | delta: 3
-            block[1] = endpoint.color5[1] << 3;
-            // color_b: 5 | delta: 3
-            block[2] = endpoint.color5[2] << 3;
-            // codeword: 3 | codeword: 3 | diff: 1 | flip: 1
-            block[3] = endpoint.inten5 << 5 | endpoint.inten5 << 2 | 0b11;
-            // selector bits: 16 x 2 bits
-            block[4..].copy_from_slice(&selector.etc1_bytes);
+        self.decode_blocks(rgb_desc, bytes, &mut frame_state.rgb, |block| block_to_etc2(block, false))?;
+        if let Some(alpha_desc) = alpha_desc {
+            self.decode_blocks(alpha_desc, bytes, &mut frame_state.alpha, |block| block_to_etc2(block, true))?;
+        } else {
+            for block in blocks.chunks_exact_mut(BLOCK_SIZE) {
+                etc::encode_solid_alpha_block(255, &mut block[..ALPHA_BLOCK_SIZE]);
+            }
+        }
+
+        Ok(Image {
+            w: rgb_desc.orig_width as u32,
+            h: rgb_desc.orig_height as u32,
+            stride: BLOCK_SIZE as u32 * rgb_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: blocks,
+        })
+    }
+
+    /// Transcodes the channels to EAC R11 blocks, one block per channel
+    pub(crate) fn transcode_to_eac(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState, channels: &[Channel]) -> Result<Image<u8>> {
+        check_alpha_slice(rgb_desc, alpha_desc)?;
+
+        let num_blocks_x = rgb_desc.num_blocks_x as u32;
+        let num_blocks_y = rgb_desc.num_blocks_y as u32;
+
+        const EAC_BLOCK_SIZE: usize = 8;
+        let block_size = EAC_BLOCK_SIZE * channels.len();
+
+        let block_count = (num_blocks_x * num_blocks_y) as usize;
+
+        let mut blocks = vec![0u8; block_size * block_count];
+
+        // Alpha comes from the green channel of the alpha slice
+        let mut block_to_eac = |block: DecodedBlock, alpha: bool| {
+            let endpoint: Endpoint = self.endpoints[block.endpoint_index as usize];
+            let selector: &Selector = &self.selectors[block.selector_index as usize];
+            let colors = etc::apply_mod_to_base_color(etc::color_5_to_8(endpoint.color5), endpoint.inten5);
+
+            let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+            let block_start = block_size * block_id;
+            let outputs = blocks[block_start..block_start + block_size].chunks_exact_mut(EAC_BLOCK_SIZE);
+            for (&channel, output) in channels.iter().zip(outputs) {
+                // Color slices fill the color channels, alpha slices the alpha channel
+                let c = match (channel, alpha) {
+                    (Channel::Alpha, true) => 1,
+                    (channel, false) if channel != Channel::Alpha => channel.index(),
+                    _ => continue,
+                };
+                let mut values = [0u8; 16];
+                for (i, value) in values.iter_mut().enumerate() {
+                    *value = colors[selector.get_selector(i % 4, i / 4)][c];
+                }
+                etc::encode_eac_block(&values, output);
+            }
         };
 
-        self.decode_blocks(slice_desc, bytes, block_to_etc1)?;
+        self.decode_blocks(rgb_desc, bytes, &mut frame_state.rgb, |block| block_to_eac(block, false))?;
+        if let Some(alpha_desc) = alpha_desc {
+            self.decode_blocks(alpha_desc, bytes, &mut frame_state.alpha, |block| block_to_eac(block, true))?;
+        } else {
+            for block in blocks.chunks_exact_mut(block_size) {
+                for (&channel, output) in channels.iter().zip(block.chunks_exact_mut(EAC_BLOCK_SIZE)) {
+                    if channel == Channel::Alpha {
+                        etc::encode_solid_alpha_block(255, output);
+                    }
+                }
+            }
+        }
+
+        Ok(Image {
+            w: rgb_desc.orig_width as u32,
+            h: rgb_desc.orig_height as u32,
+            stride: block_size as u32 * rgb_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: blocks,
+        })
+    }
+
+    pub(crate) fn transcode_to_bc1(&self, slice_desc: &SliceDesc, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<u8>> {
+        let num_blocks_x = slice_desc.num_blocks_x as u32;
+        let num_blocks_y = slice_desc.num_blocks_y as u32;
+
+        const BLOCK_SIZE: usize = bc1::BC1_BLOCK_SIZE;
+
+        let block_count = (num_blocks_x * num_blocks_y) as usize;
+
+        let mut blocks = vec![0u8; BLOCK_SIZE * block_count];
+
+        let block_to_bc1 = |block: DecodedBlock| {
+            let endpoint: Endpoint = self.endpoints[block.endpoint_index as usize];
+            let selector: &Selector = &self.selectors[block.selector_index as usize];
+
+            let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+            let block_start = BLOCK_SIZE * block_id;
+            bc1::encode_etc1s_block(endpoint.color5, endpoint.inten5, selector, &mut blocks[block_start..block_start + BLOCK_SIZE]);
+        };
+
+        self.decode_blocks(slice_desc, bytes, &mut frame_state.rgb, block_to_bc1)?;
 
         Ok(Image {
             w: slice_desc.orig_width as u32,
@@ -213,18 +368,162 @@ impl Decoder {
         })
     }
 
-    fn decode_blocks<F>(&self, slice_desc: &SliceDesc, bytes: &[u8], mut f: F) -> Result<()>
+    pub(crate) fn transcode_to_bc3(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<u8>> {
+        check_alpha_slice(rgb_desc, alpha_desc)?;
+
+        let num_blocks_x = rgb_desc.num_blocks_x as u32;
+        let num_blocks_y = rgb_desc.num_blocks_y as u32;
+
+        const BLOCK_SIZE: usize = bc4::BC4_BLOCK_SIZE + bc1::BC1_BLOCK_SIZE;
+
+        let block_count = (num_blocks_x * num_blocks_y) as usize;
+
+        let mut blocks = vec![0u8; BLOCK_SIZE * block_count];
+
+        // Alpha block first, color block second
+        let mut block_to_bc3 = |block: DecodedBlock, alpha: bool| {
+            let endpoint: Endpoint = self.endpoints[block.endpoint_index as usize];
+            let selector: &Selector = &self.selectors[block.selector_index as usize];
+
+            let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+            let block_start = BLOCK_SIZE * block_id;
+            let block = &mut blocks[block_start..block_start + BLOCK_SIZE];
+            if alpha {
+                bc4::encode_etc1s_block(endpoint.color5, endpoint.inten5, selector, &mut block[..bc4::BC4_BLOCK_SIZE]);
+            } else {
+                bc1::encode_etc1s_block(endpoint.color5, endpoint.inten5, selector, &mut block[bc4::BC4_BLOCK_SIZE..]);
+            }
+        };
+
+        self.decode_blocks(rgb_desc, bytes, &mut frame_state.rgb, |block| block_to_bc3(block, false))?;
+        if let Some(alpha_desc) = alpha_desc {
+            self.decode_blocks(alpha_desc, bytes, &mut frame_state.alpha, |block| block_to_bc3(block, true))?;
+        } else {
+            for block in blocks.chunks_exact_mut(BLOCK_SIZE) {
+                bc4::encode_block(&[255; 16], &mut block[..bc4::BC4_BLOCK_SIZE]);
+            }
+        }
+
+        Ok(Image {
+            w: rgb_desc.orig_width as u32,
+            h: rgb_desc.orig_height as u32,
+            stride: BLOCK_SIZE as u32 * rgb_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: blocks,
+        })
+    }
+
+    pub(crate) fn transcode_to_pvrtc1(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState, alpha: bool) -> Result<Image<u8>> {
+        let num_blocks_x = rgb_desc.num_blocks_x as u32;
+        let num_blocks_y = rgb_desc.num_blocks_y as u32;
+        pvrtc::check_dimensions(num_blocks_x, num_blocks_y)?;
+
+        let rgba = self.decode_to_rgba(rgb_desc, alpha_desc, bytes, frame_state)?;
+
+        Ok(Image {
+            w: rgba.w,
+            h: rgba.h,
+            stride: pvrtc::PVRTC1_BLOCK_SIZE as u32 * num_blocks_x,
+            y_flipped: self.y_flipped,
+            data: pvrtc::encode_image(&rgba.data, num_blocks_x, num_blocks_y, alpha),
+        })
+    }
+
+    pub(crate) fn transcode_to_bc7(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<u8>> {
+        self.transcode_with_alpha_blocks(rgb_desc, alpha_desc, bytes, frame_state, 16, |colors, selector, alpha, output| {
+            match alpha {
+                Some((alpha_colors, alpha_selector)) => bc7::encode_etc1s_block_with_alpha(colors, selector, alpha_colors, alpha_selector, output),
+                None => bc7::encode_etc1s_block(colors, selector, output),
+            }
+        })
+    }
+
+    pub(crate) fn transcode_to_astc(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState) -> Result<Image<u8>> {
+        self.transcode_with_alpha_blocks(rgb_desc, alpha_desc, bytes, frame_state, 16, |colors, selector, alpha, output| {
+            match alpha {
+                Some((alpha_colors, alpha_selector)) => astc::encode_etc1s_block_with_alpha(colors, selector, alpha_colors, alpha_selector, output),
+                None => astc::encode_etc1s_block(colors, selector, output),
+            }
+        })
+    }
+
+    /// Transcodes to a format which encodes color and alpha of a block together.
+    ///
+    /// The colors and selectors of each block are passed to `encode_block`, together with those
+    /// of the alpha block if the alpha slice is present.
+    fn transcode_with_alpha_blocks<F>(&self, rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>, bytes: &[u8], frame_state: &mut FrameState, block_size: usize, mut encode_block: F) -> Result<Image<u8>>
+        where F: FnMut(&[Color32; 4], &Selector, Option<(&[Color32; 4], &Selector)>, &mut [u8])
+    {
+        check_alpha_slice(rgb_desc, alpha_desc)?;
+
+        let num_blocks_x = rgb_desc.num_blocks_x as u32;
+        let num_blocks_y = rgb_desc.num_blocks_y as u32;
+
+        let block_count = (num_blocks_x * num_blocks_y) as usize;
+
+        let mut blocks = vec![0u8; block_size * block_count];
+
+        let block_colors = |endpoint_index: u16| {
+            let endpoint: Endpoint = self.endpoints[endpoint_index as usize];
+            etc::apply_mod_to_base_color(etc::color_5_to_8(endpoint.color5), endpoint.inten5)
+        };
+
+        if let Some(alpha_desc) = alpha_desc {
+            // Keep the color blocks until the alpha slice is decoded
+            let mut rgb_blocks = vec![(0u16, 0u16); block_count];
+            self.decode_blocks(rgb_desc, bytes, &mut frame_state.rgb, |block| {
+                let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+                rgb_blocks[block_id] = (block.endpoint_index, block.selector_index);
+            })?;
+
+            self.decode_blocks(alpha_desc, bytes, &mut frame_state.alpha, |block| {
+                let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+                let (endpoint_index, selector_index) = rgb_blocks[block_id];
+                let block_start = block_size * block_id;
+                encode_block(
+                    &block_colors(endpoint_index),
+                    &self.selectors[selector_index as usize],
+                    Some((&block_colors(block.endpoint_index), &self.selectors[block.selector_index as usize])),
+                    &mut blocks[block_start..block_start + block_size],
+                );
+            })?;
+        } else {
+            self.decode_blocks(rgb_desc, bytes, &mut frame_state.rgb, |block| {
+                let block_id = (block.block_y * num_blocks_x + block.block_x) as usize;
+                let block_start = block_size * block_id;
+                encode_block(
+                    &block_colors(block.endpoint_index),
+                    &self.selectors[block.selector_index as usize],
+                    None,
+                    &mut blocks[block_start..block_start + block_size],
+                );
+            })?;
+        }
+
+        Ok(Image {
+            w: rgb_desc.orig_width as u32,
+            h: rgb_desc.orig_height as u32,
+            stride: block_size as u32 * rgb_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: blocks,
+        })
+    }
+
+    fn decode_blocks<F>(&self, slice_desc: &SliceDesc, bytes: &[u8], prev_frame_indices: &mut Vec<PrevFrameIndices>, mut f: F) -> Result<()>
         where F: FnMut(DecodedBlock)
     {
 
-        let reader = {
-            let start = slice_desc.file_ofs as usize;
-            let len = slice_desc.file_size as usize;
-            &mut BitReaderLsb::new(&bytes[start..start+len])
+        let reader = &mut BitReaderLsb::new(slice_desc.data(bytes)?);
+
+        let illegal = |block_x: u32, block_y: u32, reason: &'static str| Error::IllegalEncoding {
+            slice: slice_desc.index,
+            block_x,
+            block_y,
+            reason,
         };
 
-        let num_endpoints = self.endpoints.len() as u16;
-        let num_selectors = self.selectors.len() as u16;
+        let num_endpoints = self.endpoints.len() as u32;
+        let num_selectors = self.selectors.len() as u32;
 
         // Endpoint/selector codebooks - decoded previously. See sections 7.0 and 8.0.
 
@@ -243,22 +542,28 @@ impl Decoder {
         ];
 
         // Some constants and state used during block decoding
-        let selector_history_buf_first_symbol_index: u16 = num_selectors;
-        let selector_history_buf_rle_symbol_index: u16 = self.selector_history_buffer_size as u16 + selector_history_buf_first_symbol_index;
+        let selector_history_buf_first_symbol_index: u32 = num_selectors;
+        let selector_history_buf_rle_symbol_index: u32 = self.selector_history_buffer_size + selector_history_buf_first_symbol_index;
         let mut cur_selector_rle_count: u32 = 0;
+        let mut selector_rle_start = (0, 0);
 
         let mut cur_pred_bits: u8 = 0;
         let mut prev_endpoint_pred_sym: u8 = 0;
         let mut endpoint_pred_repeat_count: u32 = 0;
+        let mut endpoint_pred_repeat_start = (0, 0);
         let mut prev_endpoint_index: u16 = 0;
 
-        #[derive(Clone, Copy, Default)]
-        struct PrevFrameIndices {
-            endpoint_index: u16,
-            selector_index: u16,
-        }
         // This array is only used for texture video. It holds the previous frame's endpoint and selector indices (each 16-bits, for 32-bits total).
-        let mut prev_frame_indices = vec![PrevFrameIndices::default(); (num_blocks_x * num_blocks_y) as usize];
+        if self.is_video {
+            let block_count = (num_blocks_x * num_blocks_y) as usize;
+            if slice_desc.is_iframe() {
+                // I-frames don't reference the previous frame, start with a clean history
+                prev_frame_indices.clear();
+                prev_frame_indices.resize(block_count, PrevFrameIndices::default());
+            } else if prev_frame_indices.len() != block_count {
+                return Err(Error::InvalidSlice("P-frame decoded without the previous frame"));
+            }
+        }
 
         // Selector history buffer - See section 10.1.
         // For the selector history buffer's size, see section 9.0.
@@ -287,7 +592,10 @@ impl Decoder {
                             let pred_bits_sym = self.endpoint_pred_model.decode_symbol(reader)?;
                             if pred_bits_sym == ENDPOINT_PRED_REPEAT_LAST_SYMBOL {
                                 // It's a run of symbols, so decode the count using VLC decoding (see section 10.2)
-                                endpoint_pred_repeat_count = decode_vlc(reader, ENDPOINT_PRED_COUNT_VLC_BITS) + ENDPOINT_PRED_MIN_REPEAT_COUNT - 1;
+                                endpoint_pred_repeat_count = decode_vlc(reader, ENDPOINT_PRED_COUNT_VLC_BITS)
+                                    .ok_or_else(|| illegal(block_x, block_y, "variable length code is too long"))?
+                                    .saturating_add(ENDPOINT_PRED_MIN_REPEAT_COUNT - 1);
+                                endpoint_pred_repeat_start = (block_x, block_y);
                                 cur_pred_bits = prev_endpoint_pred_sym;
                             } else {
                                 // It's not a run of symbols
@@ -317,39 +625,44 @@ impl Decoder {
                 let endpoint_index = match pred {
                     0 => {
                         // Reuse the left block's endpoint index
-                        assert!(block_x > 0, "block_x: {}, block_y: {}, cur_pred_bits: {}", block_x, block_y, cur_pred_bits);
+                        if block_x == 0 {
+                            return Err(illegal(block_x, block_y, "block on the left edge predicts endpoint from the left"));
+                        }
                         prev_endpoint_index
                     }
                     1 => {
                         // Reuse the upper block's endpoint index
-                        assert!(block_y > 0, "block_x: {}, block_y: {}, cur_pred_bits: {}", block_x, block_y, cur_pred_bits);
-                        block_endpoint_preds[cur_block_endpoint_pred_array as usize ^ 1][block_x as usize].endpoint_index as u16
+                        if block_y == 0 {
+                            return Err(illegal(block_x, block_y, "block on the top edge predicts endpoint from above"));
+                        }
+                        block_endpoint_preds[cur_block_endpoint_pred_array as usize ^ 1][block_x as usize].endpoint_index
                     }
-                    2 => {
+                    CR_ENDPOINT_PRED_INDEX => {
                         if self.is_video {
                             // If it's texture video, reuse the previous frame's endpoint index, at this block.
-                            assert_eq!(pred, CR_ENDPOINT_PRED_INDEX);
                             prev_frame_indices[(block_x + block_y * num_blocks_x) as usize].endpoint_index
                         } else {
                             // Reuse the upper left block's endpoint index.
-                            assert!(block_x > 0);
-                            assert!(block_y > 0);
+                            if block_x == 0 || block_y == 0 {
+                                return Err(illegal(block_x, block_y, "block on the edge predicts endpoint from the upper left"));
+                            }
                             block_endpoint_preds[cur_block_endpoint_pred_array as usize ^ 1][block_x as usize - 1].endpoint_index
                         }
                     }
                     _ => {
                         // We need to decode and apply a DPCM encoded delta to the previously used endpoint index.
                         // This uses the delta endpoint Huffman table (see section 9.0).
-                        let delta_sym = self.delta_endpoint_model.decode_symbol(reader)?;
+                        let delta_sym = self.delta_endpoint_model.decode_symbol(reader)? as u32;
 
-                        let mut endpoint_index = delta_sym + prev_endpoint_index;
+                        let mut endpoint_index = delta_sym + prev_endpoint_index as u32;
 
                         // Wrap around if the index goes beyond the end of the endpoint codebook
                         if endpoint_index >= num_endpoints {
                             endpoint_index -= num_endpoints;
                         }
 
-                        endpoint_index
+                        // Out of range indices are caught by the check below
+                        endpoint_index.min(u16::MAX as u32) as u16
                     }
                 };
 
@@ -367,26 +680,29 @@ impl Decoder {
                 // block's endpoints were reused from the previous frame.
                 let selector_index = if !self.is_video || pred != CR_ENDPOINT_PRED_INDEX {
                     // Are we in a selector RLE run?
-                    let selector_sym: u16 = if cur_selector_rle_count > 0 {
+                    let selector_sym: u32 = if cur_selector_rle_count > 0 {
                         cur_selector_rle_count -= 1;
                         num_selectors
                     } else {
                         // Decode the selector symbol, using the selector Huffman table (see section 9.0).
-                        let sym = self.selector_model.decode_symbol(reader)?;
+                        let sym = self.selector_model.decode_symbol(reader)? as u32;
 
                         // Is it a run?
-                        if sym == selector_history_buf_rle_symbol_index as u16 {
+                        if sym == selector_history_buf_rle_symbol_index {
                             // Decode the selector run's size, using the selector history buf RLE Huffman table (see section 9.0).
                             let run_sym = self.selector_history_buf_rle_model.decode_symbol(reader)? as u32;
 
                             // Is it a very long run?
                             if run_sym == (SELECTOR_HISTORY_BUF_RLE_COUNT_TOTAL - 1) {
-                                cur_selector_rle_count = SELECTOR_HISTORY_BUF_RLE_COUNT_THRESH + decode_vlc(reader, 7);
+                                cur_selector_rle_count = decode_vlc(reader, 7)
+                                    .ok_or_else(|| illegal(block_x, block_y, "variable length code is too long"))?
+                                    .saturating_add(SELECTOR_HISTORY_BUF_RLE_COUNT_THRESH);
                             } else {
                                 cur_selector_rle_count = SELECTOR_HISTORY_BUF_RLE_COUNT_THRESH + run_sym;
                             }
 
                             cur_selector_rle_count -= 1;
+                            selector_rle_start = (block_x, block_y);
 
                             num_selectors
                         } else {
@@ -396,12 +712,13 @@ impl Decoder {
 
                     // Is it a reference into the selector history buffer?
                     if selector_sym >= num_selectors {
-                        assert!(self.selector_history_buffer_size > 0);
-
                         // Compute the history buffer index
                         let history_buf_index = (selector_sym - num_selectors) as usize;
 
-                        assert!(history_buf_index < selector_history_buf.size());
+                        // This also covers an empty history buffer
+                        if history_buf_index >= selector_history_buf.size() {
+                            return Err(illegal(block_x, block_y, "selector history buffer index out of range"));
+                        }
 
                         // Access the history buffer
                         let index = selector_history_buf[history_buf_index];
@@ -416,9 +733,9 @@ impl Decoder {
                         // It's an index into the selector codebook
                         // Add it to the selector history buffer
                         if self.selector_history_buffer_size > 0 {
-                            selector_history_buf.add(selector_sym);
+                            selector_history_buf.add(selector_sym as u16);
                         }
-                        selector_sym
+                        selector_sym as u16
                     }
                 } else {
                     // If it's texture video, reuse the previous frame's selector index, at this block.
@@ -434,8 +751,12 @@ impl Decoder {
 
                 // The block is fully decoded here. The codebook indices are endpoint_index and selector_index.
                 // Make sure they are valid
-                assert!(endpoint_index < num_endpoints);
-                assert!(selector_index < num_selectors);
+                if endpoint_index as u32 >= num_endpoints {
+                    return Err(illegal(block_x, block_y, "endpoint index out of range"));
+                }
+                if selector_index as u32 >= num_selectors {
+                    return Err(illegal(block_x, block_y, "selector index out of range"));
+                }
 
                 let block = DecodedBlock {
                     block_x, block_y, endpoint_index, selector_index
@@ -445,10 +766,45 @@ impl Decoder {
             }
         }
 
+        // Runs must end within the slice
+        if endpoint_pred_repeat_count > 0 {
+            let (block_x, block_y) = endpoint_pred_repeat_start;
+            return Err(illegal(block_x, block_y, "endpoint prediction run goes past the end of the slice"));
+        }
+        if cur_selector_rle_count > 0 {
+            let (block_x, block_y) = selector_rle_start;
+            return Err(illegal(block_x, block_y, "selector run goes past the end of the slice"));
+        }
+
         Ok(())
     }
 }
 
+fn write_etc1_block(endpoint: Endpoint, selector: &Selector, block: &mut [u8]) {
+    // color_r: 5 | delta: 3
+    block[0] = endpoint.color5[0] << 3;
+    // color_g: 5 | delta: 3
+    block[1] = endpoint.color5[1] << 3;
+    // color_b: 5 | delta: 3
+    block[2] = endpoint.color5[2] << 3;
+    // codeword: 3 | codeword: 3 | diff: 1 | flip: 1
+    block[3] = endpoint.inten5 << 5 | endpoint.inten5 << 2 | 0b11;
+    // selector bits: 16 x 2 bits
+    block[4..].copy_from_slice(&selector.etc1_bytes);
+}
+
+fn check_alpha_slice(rgb_desc: &SliceDesc, alpha_desc: Option<&SliceDesc>) -> Result<()> {
+    if let Some(alpha_desc) = alpha_desc {
+        if !alpha_desc.has_alpha() {
+            return Err(Error::InvalidSlice("expected slice with alpha"));
+        }
+        if alpha_desc.num_blocks_x != rgb_desc.num_blocks_x || alpha_desc.num_blocks_y != rgb_desc.num_blocks_y {
+            return Err(Error::InvalidSlice("RGB slice and alpha slice have different dimensions"));
+        }
+    }
+    Ok(())
+}
+
 fn decode_endpoints(num_endpoints: usize, bytes: &[u8]) -> Result<Vec<Endpoint>> {
     let reader = &mut BitReaderLsb::new(bytes);
 
@@ -515,12 +871,8 @@ fn decode_selectors(num_selectors: usize, bytes: &[u8]) -> Result<Vec<Selector>>
     let hybrid = reader.read_bool();
     let raw = reader.read_bool();
 
-    if global {
-        return Err("Global selector codebooks are not supported".into());
-    }
-
-    if hybrid {
-        return Err("Hybrid selector codebooks are not supported".into());
+    if global || hybrid {
+        return Err(Error::UnsupportedSelectorCodebook);
     }
 
     let mut selectors = vec![Selector::default(); num_selectors];
@@ -570,8 +922,7 @@ fn decode_selectors(num_selectors: usize, bytes: &[u8]) -> Result<Vec<Selector>>
 }
 
 
-fn decode_vlc(reader: &mut BitReaderLsb, chunk_bits: u32) -> u32 {
-    assert!(chunk_bits > 0);
+fn decode_vlc(reader: &mut BitReaderLsb, chunk_bits: u32) -> Option<u32> {
     let chunk_size = 1 << chunk_bits;
     let chunk_mask = mask!(chunk_bits);
 
@@ -588,11 +939,11 @@ fn decode_vlc(reader: &mut BitReaderLsb, chunk_bits: u32) -> u32 {
         }
 
         if ofs >= 32 {
-            panic!();
+            return None;
         }
     }
 
-    v
+    Some(v)
 }
 
 
@@ -0,0 +1,620 @@
+use crate::{
+    Error,
+    atc,
+    Image,
+    LayeredImage,
+    Result,
+    TranscoderTextureFormat,
+    basis::{
+        self,
+        Header,
+        SliceDesc,
+        TexFormat,
+        TextureType,
+    },
+    etc1s::{
+        self,
+        FrameState,
+    },
+    packed,
+    uastc,
+};
+
+/// A parsed .basis file.
+///
+/// Parsing checks the header and the file checksum, reads the slice
+/// descriptions and prepares the decoder (for ETC1S this means decoding the
+/// endpoint and selector codebooks and the Huffman tables). Individual images
+/// and mip levels can then be transcoded on demand.
+pub struct BasisFile<'a> {
+    bytes: &'a [u8],
+    header: Header,
+    texture_type: TextureType,
+    slice_descs: Vec<SliceDesc>,
+    /// Slices of each mip level, indexed by image and then by level
+    images: Vec<Vec<LevelSlices>>,
+    decoder: Decoder,
+    checksum_policy: ChecksumPolicy,
+}
+
+/// Default limit of `BasisFile::with_options` on the number of blocks in a slice,
+/// enough for 16384x16384 pixels
+pub const DEFAULT_MAX_BLOCK_COUNT: u32 = 4096 * 4096;
+
+/// Slices holding a single mip level of an image
+#[derive(Clone, Copy)]
+struct LevelSlices {
+    slice_index: usize,
+    /// ETC1S alpha slice, stored right after the RGB slice
+    alpha_slice_index: Option<usize>,
+}
+
+enum Decoder {
+    Etc1s(etc1s::Decoder),
+    Uastc(uastc::Decoder),
+}
+
+/// Description of a single mip level of an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageLevelInfo {
+    pub image_index: u32,
+    pub level_index: u32,
+
+    /// Dimensions of the level in pixels
+    pub orig_width: u32,
+    pub orig_height: u32,
+
+    /// Dimensions of the level rounded up to whole 4x4 blocks
+    pub width: u32,
+    pub height: u32,
+
+    pub num_blocks_x: u32,
+    pub num_blocks_y: u32,
+
+    /// Index of the first slice of the level, ETC1S alpha slice follows right after it
+    pub first_slice_index: u32,
+
+    /// Cubemap face of the image, see `CubeFace`. Always 0 for other texture types.
+    pub face_index: u32,
+
+    pub has_alpha: bool,
+}
+
+/// Face of a cubemap, images of a cubemap array are stored in this order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX = 0,
+    NegativeX = 1,
+    PositiveY = 2,
+    NegativeY = 3,
+    PositiveZ = 4,
+    NegativeZ = 5,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX, CubeFace::NegativeX,
+        CubeFace::PositiveY, CubeFace::NegativeY,
+        CubeFace::PositiveZ, CubeFace::NegativeZ,
+    ];
+
+    const COUNT: u32 = 6;
+}
+
+/// Which CRC16 checksums of the file are verified.
+///
+/// The header checksum is always verified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    /// Verify the checksum of all data when opening the file and of each slice before decoding it
+    #[default]
+    VerifyAll,
+    /// Only verify each slice before decoding it, so intact images of a damaged file can still
+    /// be decoded. The codebooks of ETC1S files are not covered by any slice checksum.
+    VerifySlices,
+    /// Don't verify any data, for trusted files
+    Skip,
+}
+
+impl<'a> BasisFile<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        Self::with_options(bytes, ChecksumPolicy::default(), DEFAULT_MAX_BLOCK_COUNT)
+    }
+
+    pub fn with_checksum_policy(bytes: &'a [u8], checksum_policy: ChecksumPolicy) -> Result<Self> {
+        Self::with_options(bytes, checksum_policy, DEFAULT_MAX_BLOCK_COUNT)
+    }
+
+    pub fn with_max_block_count(bytes: &'a [u8], max_block_count: u32) -> Result<Self> {
+        Self::with_options(bytes, ChecksumPolicy::default(), max_block_count)
+    }
+
+    /// Opens the file, rejecting it with `Error::UnsupportedDimensions` if any slice has more
+    /// than `max_block_count` blocks.
+    ///
+    /// Transcoding allocates memory proportional to the block count, so this bounds the memory
+    /// a small but malicious file can make the transcoder allocate.
+    pub fn with_options(bytes: &'a [u8], checksum_policy: ChecksumPolicy, max_block_count: u32) -> Result<Self> {
+        let header = basis::read_header(bytes)?;
+
+        if checksum_policy == ChecksumPolicy::VerifyAll && !basis::check_file_checksum(bytes, &header) {
+            return Err(Error::DataCrcMismatch);
+        }
+
+        let texture_type = header.texture_type()?;
+        if texture_type == TextureType::CubemapArray && header.total_images % CubeFace::COUNT != 0 {
+            return Err(Error::InvalidHeader("cubemap array image count is not a multiple of 6"));
+        }
+
+        let slice_descs = basis::read_slice_descs(bytes, &header)?;
+        check_slice_descs(bytes, &header, &slice_descs, max_block_count)?;
+
+        let decoder = match header.texture_format()? {
+            TexFormat::ETC1S => {
+                if header.has_alpha() && (header.total_slices % 2) != 0 {
+                    return Err(Error::InvalidHeader("file has alpha, but slice count is odd"));
+                }
+                Decoder::Etc1s(etc1s::Decoder::from_file_bytes(&header, bytes)?)
+            }
+            TexFormat::UASTC4x4 => {
+                Decoder::Uastc(uastc::Decoder::from_file_bytes(&header, bytes)?)
+            }
+        };
+
+        let has_alpha_slices = matches!(decoder, Decoder::Etc1s(_)) && header.has_alpha();
+        let images = index_levels(&header, &slice_descs, has_alpha_slices)?;
+
+        match texture_type {
+            TextureType::CubemapArray => {
+                check_same_levels(&slice_descs, &images, "cubemap faces have different dimensions")?;
+            }
+            TextureType::Type2DArray | TextureType::Volume => {
+                check_same_levels(&slice_descs, &images, "layers have different dimensions")?;
+            }
+            TextureType::Type2D | TextureType::VideoFrames => {}
+        }
+
+        Ok(Self {
+            bytes,
+            header,
+            texture_type,
+            slice_descs,
+            images,
+            decoder,
+            checksum_policy,
+        })
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        match self.decoder {
+            Decoder::Etc1s(_) => self.header.has_alpha(),
+            Decoder::Uastc(_) => self.slice_descs.iter().any(|s| s.has_alpha()),
+        }
+    }
+
+    pub fn texture_type(&self) -> TextureType {
+        self.texture_type
+    }
+
+    pub fn image_count(&self) -> u32 {
+        self.header.total_images
+    }
+
+    /// Duration of a video frame in microseconds
+    pub fn us_per_frame(&self) -> u32 {
+        self.header.us_per_frame
+    }
+
+    /// Number of faces per cubemap, 6 for cubemap arrays and 1 for other texture types
+    pub fn face_count(&self) -> u32 {
+        match self.texture_type {
+            TextureType::CubemapArray => CubeFace::COUNT,
+            _ => 1,
+        }
+    }
+
+    /// Number of cubemaps in a cubemap array, 0 for other texture types
+    pub fn cubemap_count(&self) -> u32 {
+        match self.texture_type {
+            TextureType::CubemapArray => self.image_count() / CubeFace::COUNT,
+            _ => 0,
+        }
+    }
+
+    /// Number of layers of a layered texture, 1 for 2D textures and videos.
+    ///
+    /// This is the layer count of a 2D array, the depth of a volume and the
+    /// face count of a cubemap array. Each layer is one image of the file.
+    pub fn layer_count(&self) -> u32 {
+        match self.texture_type {
+            TextureType::Type2DArray | TextureType::CubemapArray | TextureType::Volume => self.image_count(),
+            TextureType::Type2D | TextureType::VideoFrames => 1,
+        }
+    }
+
+    /// Returns the cubemap face stored in the image, `None` if this is not a cubemap array
+    pub fn image_face(&self, image: u32) -> Option<CubeFace> {
+        match self.texture_type {
+            TextureType::CubemapArray if image < self.image_count() => {
+                Some(CubeFace::ALL[(image % CubeFace::COUNT) as usize])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn level_count(&self, image: u32) -> u32 {
+        self.images.get(image as usize).map_or(0, |levels| levels.len() as u32)
+    }
+
+    /// Returns the descriptions of all mip levels of an image, largest level first
+    pub fn image_levels(&self, image: u32) -> Vec<ImageLevelInfo> {
+        (0..self.level_count(image))
+            .filter_map(|level| self.image_level_info(image, level))
+            .collect()
+    }
+
+    pub fn image_level_info(&self, image: u32, level: u32) -> Option<ImageLevelInfo> {
+        let slice_index = self.find_level_slices(image, level)?.slice_index;
+        let slice_desc = &self.slice_descs[slice_index];
+
+        let has_alpha = match self.decoder {
+            Decoder::Etc1s(_) => self.header.has_alpha(),
+            Decoder::Uastc(_) => slice_desc.has_alpha(),
+        };
+
+        Some(ImageLevelInfo {
+            image_index: image,
+            level_index: level,
+            orig_width: slice_desc.orig_width as u32,
+            orig_height: slice_desc.orig_height as u32,
+            width: slice_desc.num_blocks_x as u32 * 4,
+            height: slice_desc.num_blocks_y as u32 * 4,
+            num_blocks_x: slice_desc.num_blocks_x as u32,
+            num_blocks_y: slice_desc.num_blocks_y as u32,
+            first_slice_index: slice_index as u32,
+            face_index: image % self.face_count(),
+            has_alpha,
+        })
+    }
+
+    /// Transcodes all mip levels of an image, largest level first.
+    ///
+    /// The dimensions of each level are in the returned images.
+    pub fn transcode_image(&self, image: u32, format: TranscoderTextureFormat) -> Result<Vec<Image<u8>>> {
+        if image >= self.image_count() {
+            return Err(Error::ImageNotFound { image, level: 0 });
+        }
+        (0..self.level_count(image))
+            .map(|level| self.transcode(image, level, format))
+            .collect()
+    }
+
+    /// Transcodes a mip level of all six faces of a cubemap.
+    ///
+    /// Faces are returned in the +X, -X, +Y, -Y, +Z, -Z order, see `CubeFace`.
+    pub fn transcode_cubemap(&self, cubemap: u32, level: u32, format: TranscoderTextureFormat) -> Result<Vec<Image<u8>>> {
+        if self.texture_type != TextureType::CubemapArray {
+            return Err(Error::UnsupportedTextureType(self.texture_type));
+        }
+        // Checked before multiplying, a large cubemap index would overflow
+        if cubemap >= self.cubemap_count() {
+            return Err(Error::ImageNotFound { image: cubemap.saturating_mul(CubeFace::COUNT), level });
+        }
+        let first_image = cubemap * CubeFace::COUNT;
+        (first_image..first_image + CubeFace::COUNT)
+            .map(|image| self.transcode(image, level, format))
+            .collect()
+    }
+
+    /// Transcodes a mip level of all layers into a single buffer, ready for
+    /// uploading as a texture array or a 3D texture.
+    ///
+    /// All layers have the same dimensions, this is checked when the file is parsed.
+    pub fn transcode_layers(&self, level: u32, format: TranscoderTextureFormat) -> Result<LayeredImage<u8>> {
+        match self.texture_type {
+            TextureType::Type2DArray | TextureType::CubemapArray | TextureType::Volume => {}
+            TextureType::Type2D | TextureType::VideoFrames => {
+                return Err(Error::UnsupportedTextureType(self.texture_type));
+            }
+        }
+
+        let first = self.transcode(0, level, format)?;
+        let layers = self.layer_count();
+        let layer_stride = first.data.len() as u32;
+
+        let mut data = first.data;
+        data.reserve(layer_stride as usize * (layers as usize - 1));
+        for layer in 1..layers {
+            let image = self.transcode(layer, level, format)?;
+            data.extend_from_slice(&image.data);
+        }
+
+        Ok(LayeredImage {
+            w: first.w,
+            h: first.h,
+            layers,
+            stride: first.stride,
+            layer_stride,
+            y_flipped: first.y_flipped,
+            data,
+        })
+    }
+
+    pub fn transcode(&self, image: u32, level: u32, format: TranscoderTextureFormat) -> Result<Image<u8>> {
+        let mut frame_state = FrameState::default();
+        if self.has_inter_frames() {
+            // P-frames reuse blocks of the previous frame, decode forward from the nearest I-frame
+            for frame in self.find_iframe(image, level)?..image {
+                self.skip_frame(frame, level, &mut frame_state)?;
+            }
+        }
+        self.transcode_frame(image, level, format, &mut frame_state)
+    }
+
+    /// Checks all slices for encodings the specification declares illegal.
+    ///
+    /// Transcoding is lenient about some invalid data, e.g. invalid UASTC
+    /// blocks turn into magenta or zeroed blocks. This decodes every slice and
+    /// reports the first violation together with its slice and block.
+    pub fn validate(&self) -> Result<()> {
+        match &self.decoder {
+            Decoder::Etc1s(_) => {
+                // Decode images in order, so video P-frames see their previous frame
+                let mut frame_states: Vec<FrameState> = Vec::new();
+                for (image, levels) in self.images.iter().enumerate() {
+                    if frame_states.len() < levels.len() {
+                        frame_states.resize_with(levels.len(), FrameState::default);
+                    }
+                    for (level, frame_state) in frame_states.iter_mut().enumerate().take(levels.len()) {
+                        self.skip_frame(image as u32, level as u32, frame_state)?;
+                    }
+                }
+            }
+            Decoder::Uastc(decoder) => {
+                for slice_desc in &self.slice_descs {
+                    self.check_slice_crc(slice_desc)?;
+                    decoder.validate_slice(slice_desc, self.bytes)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Video frames of ETC1S files can depend on the previous frame
+    pub(crate) fn has_inter_frames(&self) -> bool {
+        self.texture_type == TextureType::VideoFrames && matches!(self.decoder, Decoder::Etc1s(_))
+    }
+
+    /// Returns the nearest I-frame at or before `image`
+    pub(crate) fn find_iframe(&self, image: u32, level: u32) -> Result<u32> {
+        if self.find_level_slices(image, level).is_none() {
+            return Err(Error::ImageNotFound { image, level });
+        }
+        (0..=image).rev()
+            .find(|&frame| {
+                self.find_level_slices(frame, level)
+                    .is_some_and(|slices| self.slice_descs[slices.slice_index].is_iframe())
+            })
+            .ok_or(Error::InvalidSlice("video frame is not preceded by an I-frame"))
+    }
+
+    /// Decodes a video frame only to update the state used by the next frame
+    pub(crate) fn skip_frame(&self, image: u32, level: u32, frame_state: &mut FrameState) -> Result<()> {
+        let level_slices = self.find_level_slices(image, level)
+            .ok_or(Error::ImageNotFound { image, level })?;
+
+        if let Decoder::Etc1s(decoder) = &self.decoder {
+            let slice_desc = &self.slice_descs[level_slices.slice_index];
+            let alpha_desc = level_slices.alpha_slice_index.map(|i| &self.slice_descs[i]);
+            self.check_slice_crc(slice_desc)?;
+            if let Some(alpha_desc) = alpha_desc {
+                self.check_slice_crc(alpha_desc)?;
+            }
+            decoder.skip_frame(slice_desc, alpha_desc, self.bytes, frame_state)?;
+        }
+        Ok(())
+    }
+
+    /// Transcodes a level, for videos `frame_state` has to hold the previous frame
+    pub(crate) fn transcode_frame(&self, image: u32, level: u32, format: TranscoderTextureFormat, frame_state: &mut FrameState) -> Result<Image<u8>> {
+        let level_slices = self.find_level_slices(image, level)
+            .ok_or(Error::ImageNotFound { image, level })?;
+
+        let bytes = self.bytes;
+        let slice_desc = &self.slice_descs[level_slices.slice_index];
+        self.check_slice_crc(slice_desc)?;
+
+        match &self.decoder {
+            Decoder::Etc1s(decoder) => {
+                let alpha_desc = level_slices.alpha_slice_index.map(|i| &self.slice_descs[i]);
+                if let Some(alpha_desc) = alpha_desc {
+                    self.check_slice_crc(alpha_desc)?;
+                }
+                match format {
+                    TranscoderTextureFormat::Etc1Rgb => decoder.transcode_to_etc1(slice_desc, bytes, frame_state),
+                    TranscoderTextureFormat::Etc2Rgba => decoder.transcode_to_etc2(slice_desc, alpha_desc, bytes, frame_state),
+                    TranscoderTextureFormat::Bc1Rgb => decoder.transcode_to_bc1(slice_desc, bytes, frame_state),
+                    TranscoderTextureFormat::Bc3Rgba => decoder.transcode_to_bc3(slice_desc, alpha_desc, bytes, frame_state),
+                    TranscoderTextureFormat::Bc7Rgba => decoder.transcode_to_bc7(slice_desc, alpha_desc, bytes, frame_state),
+                    TranscoderTextureFormat::Astc4x4Rgba => decoder.transcode_to_astc(slice_desc, alpha_desc, bytes, frame_state),
+                    TranscoderTextureFormat::EacR11 { channel } => decoder.transcode_to_eac(slice_desc, alpha_desc, bytes, frame_state, &[channel]),
+                    TranscoderTextureFormat::EacRg11 { channels } => decoder.transcode_to_eac(slice_desc, alpha_desc, bytes, frame_state, &channels),
+                    TranscoderTextureFormat::Pvrtc1Rgb => decoder.transcode_to_pvrtc1(slice_desc, alpha_desc, bytes, frame_state, false),
+                    TranscoderTextureFormat::Pvrtc1Rgba => decoder.transcode_to_pvrtc1(slice_desc, alpha_desc, bytes, frame_state, true),
+                    TranscoderTextureFormat::AtcRgb => {
+                        let image = decoder.transcode_to_bc1(slice_desc, bytes, frame_state)?;
+                        Ok(atc::convert_image_from_bc1(image, 8))
+                    }
+                    TranscoderTextureFormat::AtcRgba => {
+                        let image = decoder.transcode_to_bc3(slice_desc, alpha_desc, bytes, frame_state)?;
+                        Ok(atc::convert_image_from_bc1(image, 16))
+                    }
+                    TranscoderTextureFormat::Rgba32 => {
+                        let image = decoder.decode_to_rgba(slice_desc, alpha_desc, bytes, frame_state)?;
+                        Ok(image.into_rgba_bytes())
+                    }
+                    TranscoderTextureFormat::Rgb565 { dither } => {
+                        let image = decoder.decode_to_rgba(slice_desc, alpha_desc, bytes, frame_state)?;
+                        Ok(packed::pack_image(image, &packed::RGB565, dither))
+                    }
+                    TranscoderTextureFormat::Bgr565 { dither } => {
+                        let image = decoder.decode_to_rgba(slice_desc, alpha_desc, bytes, frame_state)?;
+                        Ok(packed::pack_image(image, &packed::BGR565, dither))
+                    }
+                    TranscoderTextureFormat::Rgba4444 { dither } => {
+                        let image = decoder.decode_to_rgba(slice_desc, alpha_desc, bytes, frame_state)?;
+                        Ok(packed::pack_image(image, &packed::RGBA4444, dither))
+                    }
+                    _ => Err(Error::UnsupportedTextureFormat(format)),
+                }
+            }
+            Decoder::Uastc(decoder) => {
+                match format {
+                    TranscoderTextureFormat::Etc1Rgb => decoder.transcode_to_etc1(slice_desc, bytes),
+                    TranscoderTextureFormat::Etc2Rgba => decoder.transcode_to_etc2(slice_desc, bytes),
+                    TranscoderTextureFormat::Bc1Rgb => decoder.transcode_to_bc1(slice_desc, bytes),
+                    TranscoderTextureFormat::Bc3Rgba => decoder.transcode_to_bc3(slice_desc, bytes),
+                    TranscoderTextureFormat::Bc7Rgba => decoder.transcode_to_bc7(slice_desc, bytes),
+                    TranscoderTextureFormat::Astc4x4Rgba => decoder.transcode_to_astc(slice_desc, bytes),
+                    TranscoderTextureFormat::Rgba32 => {
+                        let image = decoder.decode_to_rgba(slice_desc, bytes)?;
+                        Ok(image.into_rgba_bytes())
+                    }
+                    TranscoderTextureFormat::Rgb565 { dither } => {
+                        let image = decoder.decode_to_rgba(slice_desc, bytes)?;
+                        Ok(packed::pack_image(image, &packed::RGB565, dither))
+                    }
+                    TranscoderTextureFormat::Bgr565 { dither } => {
+                        let image = decoder.decode_to_rgba(slice_desc, bytes)?;
+                        Ok(packed::pack_image(image, &packed::BGR565, dither))
+                    }
+                    TranscoderTextureFormat::Rgba4444 { dither } => {
+                        let image = decoder.decode_to_rgba(slice_desc, bytes)?;
+                        Ok(packed::pack_image(image, &packed::RGBA4444, dither))
+                    }
+                    TranscoderTextureFormat::Uastc4x4 => decoder.read_to_uastc(slice_desc, bytes),
+                    TranscoderTextureFormat::EacR11 { channel } => decoder.transcode_to_eac(slice_desc, bytes, &[channel]),
+                    TranscoderTextureFormat::EacRg11 { channels } => decoder.transcode_to_eac(slice_desc, bytes, &channels),
+                    TranscoderTextureFormat::Bc4R { channel } => decoder.transcode_to_bc4(slice_desc, bytes, &[channel]),
+                    TranscoderTextureFormat::Bc5Rg { channels } => decoder.transcode_to_bc4(slice_desc, bytes, &channels),
+                    TranscoderTextureFormat::Pvrtc1Rgb => decoder.transcode_to_pvrtc1(slice_desc, bytes, false),
+                    TranscoderTextureFormat::Pvrtc1Rgba => decoder.transcode_to_pvrtc1(slice_desc, bytes, true),
+                    TranscoderTextureFormat::AtcRgb => {
+                        let image = decoder.transcode_to_bc1(slice_desc, bytes)?;
+                        Ok(atc::convert_image_from_bc1(image, 8))
+                    }
+                    TranscoderTextureFormat::AtcRgba => {
+                        let image = decoder.transcode_to_bc3(slice_desc, bytes)?;
+                        Ok(atc::convert_image_from_bc1(image, 16))
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_slice_crc(&self, slice_desc: &SliceDesc) -> Result<()> {
+        match self.checksum_policy {
+            ChecksumPolicy::VerifyAll | ChecksumPolicy::VerifySlices => slice_desc.check_crc(self.bytes),
+            ChecksumPolicy::Skip => Ok(()),
+        }
+    }
+
+    fn find_level_slices(&self, image: u32, level: u32) -> Option<LevelSlices> {
+        self.images.get(image as usize)?.get(level as usize).copied()
+    }
+}
+
+/// Checks that the slice descriptions are consistent and point into the file
+fn check_slice_descs(bytes: &[u8], header: &Header, slice_descs: &[SliceDesc], max_block_count: u32) -> Result<()> {
+    const UASTC_BLOCK_SIZE: usize = 16;
+
+    for slice_desc in slice_descs {
+        let data = slice_desc.data(bytes)?;
+        if slice_desc.num_blocks_x as u32 != (slice_desc.orig_width as u32).div_ceil(4)
+            || slice_desc.num_blocks_y as u32 != (slice_desc.orig_height as u32).div_ceil(4) {
+            return Err(Error::InvalidSlice("block count does not match dimensions"));
+        }
+        let block_count = slice_desc.num_blocks_x as usize * slice_desc.num_blocks_y as usize;
+        if block_count > max_block_count as usize {
+            return Err(Error::UnsupportedDimensions {
+                width: 4 * slice_desc.num_blocks_x as u32,
+                height: 4 * slice_desc.num_blocks_y as u32,
+            });
+        }
+        if header.texture_format()? == TexFormat::UASTC4x4 && data.len() < block_count * UASTC_BLOCK_SIZE {
+            return Err(Error::TruncatedData);
+        }
+    }
+    Ok(())
+}
+
+/// Groups the slices into images and their mip levels.
+///
+/// Every image has to have a contiguous range of levels starting at 0. ETC1S
+/// files with alpha store the alpha slice of each level right after the RGB
+/// slice, so the pairs are checked to describe the same level.
+fn index_levels(header: &Header, slice_descs: &[SliceDesc], has_alpha_slices: bool) -> Result<Vec<Vec<LevelSlices>>> {
+    // Every image needs at least one slice, this also bounds the allocation below
+    if header.total_images > header.total_slices {
+        return Err(Error::InvalidHeader("more images than slices"));
+    }
+
+    let step = if has_alpha_slices { 2 } else { 1 };
+    let mut images: Vec<Vec<Option<LevelSlices>>> = vec![Vec::new(); header.total_images as usize];
+
+    for (slice_index, slice_desc) in slice_descs.iter().enumerate().step_by(step) {
+        let alpha_slice_index = if has_alpha_slices {
+            let alpha_desc = &slice_descs[slice_index + 1];
+            if !alpha_desc.has_alpha() {
+                return Err(Error::InvalidSlice("expected slice with alpha"));
+            }
+            if alpha_desc.image_index != slice_desc.image_index || alpha_desc.level_index != slice_desc.level_index {
+                return Err(Error::InvalidSlice("alpha slice belongs to a different level than RGB slice"));
+            }
+            if alpha_desc.orig_width != slice_desc.orig_width || alpha_desc.orig_height != slice_desc.orig_height
+                || alpha_desc.num_blocks_x != slice_desc.num_blocks_x || alpha_desc.num_blocks_y != slice_desc.num_blocks_y {
+                return Err(Error::InvalidSlice("RGB slice and alpha slice have different dimensions"));
+            }
+            Some(slice_index + 1)
+        } else {
+            None
+        };
+
+        let levels = images.get_mut(slice_desc.image_index as usize)
+            .ok_or(Error::InvalidSlice("image index out of range"))?;
+        let level = slice_desc.level_index as usize;
+        if levels.len() <= level {
+            levels.resize(level + 1, None);
+        }
+        if levels[level].is_some() {
+            return Err(Error::InvalidSlice("multiple slices for the same level"));
+        }
+        levels[level] = Some(LevelSlices { slice_index, alpha_slice_index });
+    }
+
+    images.into_iter()
+        .map(|levels| {
+            if levels.is_empty() {
+                return Err(Error::InvalidSlice("image has no slices"));
+            }
+            levels.into_iter().collect::<Option<Vec<_>>>()
+                .ok_or(Error::InvalidSlice("image is missing a mip level"))
+        })
+        .collect()
+}
+
+/// Checks that all images have the same number of levels with the same dimensions
+fn check_same_levels(slice_descs: &[SliceDesc], images: &[Vec<LevelSlices>], error: &'static str) -> Result<()> {
+    let level_size = |level: &LevelSlices| {
+        let slice_desc = &slice_descs[level.slice_index];
+        (slice_desc.orig_width, slice_desc.orig_height, slice_desc.num_blocks_x, slice_desc.num_blocks_y)
+    };
+    if let Some((first, rest)) = images.split_first() {
+        for levels in rest {
+            if levels.len() != first.len() || !levels.iter().zip(first).all(|(a, b)| level_size(a) == level_size(b)) {
+                return Err(Error::InvalidSlice(error));
+            }
+        }
+    }
+    Ok(())
+}
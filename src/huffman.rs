@@ -2,7 +2,10 @@
 
 use crate::bitreader::BitReaderLsb;
 
-use crate::Result;
+use crate::{
+    Error,
+    Result,
+};
 
 // Max supported Huffman code size is 16-bits
 const MaxSupportedCodeSize: usize = 16;
@@ -42,12 +45,13 @@ const BigRepeatCode: usize = 20;
 
 pub fn read_huffman_table(reader: &mut BitReaderLsb) -> Result<HuffmanDecodingTable> {
 
-    // TODO: sanity & overflow checks
-
     let total_used_syms = reader.read_u32(MaxSymsLog2) as usize;  // [1, MaxSyms]
 
     let codelength_table = {
         let num_codelength_codes = reader.read_u32(5) as usize; // [1, TotalCodelengthCodes]
+        if num_codelength_codes > TotalCodelengthCodes {
+            return Err(Error::InvalidHuffmanTable);
+        }
 
         let indices = [
             SmallZeroRunCode, BigZeroRunCode,
@@ -83,10 +87,11 @@ pub fn read_huffman_table(reader: &mut BitReaderLsb) -> Result<HuffmanDecodingTa
                 }
             }
             SmallRepeatCode => {
+                // SmallRepeatCode can't be the first code and the previous symbol's code length can't be 0
                 let prev_sym_code_size = symbol_code_sizes.last().copied()
-                    .ok_or_else(|| "Encountered SmallRepeatCode as the first code")?;
+                    .ok_or(Error::InvalidHuffmanTable)?;
                 if prev_sym_code_size == 0 {
-                    return Err("Encountered SmallRepeatCode, but the previous symbol's code length was 0".into());
+                    return Err(Error::InvalidHuffmanTable);
                 }
                 let count = SmallRepeatSizeMin + reader.read_u32(SmallRepeatExtraBits) as usize;
                 for _ in 0..count {
@@ -94,10 +99,11 @@ pub fn read_huffman_table(reader: &mut BitReaderLsb) -> Result<HuffmanDecodingTa
                 }
             }
             BigRepeatCode => {
+                // BigRepeatCode can't be the first code and the previous symbol's code length can't be 0
                 let prev_sym_code_size = symbol_code_sizes.last().copied()
-                    .ok_or_else(|| "Encountered BigRepeatCode as the first code")?;
+                    .ok_or(Error::InvalidHuffmanTable)?;
                 if prev_sym_code_size == 0 {
-                    return Err("Encountered BigRepeatCode, but the previous symbol's code length was 0".into());
+                    return Err(Error::InvalidHuffmanTable);
                 }
                 let count = BigRepeatSizeMin + reader.read_u32(BigRepeatExtraBits) as usize;
                 for _ in 0..count {
@@ -108,6 +114,11 @@ pub fn read_huffman_table(reader: &mut BitReaderLsb) -> Result<HuffmanDecodingTa
         }
     }
 
+    // Runs can't go past the last symbol
+    if symbol_code_sizes.len() > total_used_syms {
+        return Err(Error::InvalidHuffmanTable);
+    }
+
     HuffmanDecodingTable::from_sizes(&symbol_code_sizes)
 }
 
@@ -125,15 +136,24 @@ pub struct HuffmanDecodingTable {
 
 impl HuffmanDecodingTable {
     pub fn from_sizes(code_sizes: &[u8]) -> Result<Self> {
-        // TODO: sanity checks
-
         let mut syms_using_codesize = [0u32; MaxSupportedCodeSize+1];
         let mut max_code_size = 0;
         for &count in code_sizes {
+            if count as usize > MaxSupportedCodeSize {
+                return Err(Error::InvalidHuffmanTable);
+            }
             syms_using_codesize[count as usize] += 1;
             max_code_size = max_code_size.max(count as usize);
         }
 
+        // Reject oversubscribed codes, i.e. codes violating the Kraft inequality
+        let kraft_sum: u32 = (1..=MaxSupportedCodeSize)
+            .map(|size| syms_using_codesize[size] << (MaxSupportedCodeSize - size))
+            .sum();
+        if kraft_sum > 1 << MaxSupportedCodeSize {
+            return Err(Error::InvalidHuffmanTable);
+        }
+
         let mut total = 0;
         let mut next_code = [0u32; MaxSupportedCodeSize+1];
         syms_using_codesize[0] = 0;
@@ -163,10 +183,6 @@ impl HuffmanDecodingTable {
             }
         }
 
-        if next_code.iter().any(|&c| c > u16::MAX as u32 + 1) {
-            return Err("Code lengths are invalid, codes don't fit into 16 bits".into());
-        }
-
         Ok(Self {
             lookup,
             max_code_size,
@@ -180,7 +196,7 @@ impl HuffmanDecodingTable {
             reader.remove(entry.code_size as usize);
             Ok(entry.symbol)
         } else {
-            Err(format!("No matching code found in the decoding table, bits: {:016b}", bits).into())
+            Err(Error::InvalidHuffmanCode)
         }
     }
 }
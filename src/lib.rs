@@ -10,205 +10,132 @@ mod bytereader;
 mod etc1s;
 mod uastc;
 mod basis;
+mod bc1;
+mod bc4;
 mod bc7;
 mod astc;
+mod atc;
+mod pvrtc;
+mod packed;
 mod etc;
-
-use basis::{
-    TexFormat,
+mod file;
+mod error;
+mod video;
+
+pub use file::{
+    BasisFile,
+    ChecksumPolicy,
+    CubeFace,
+    ImageLevelInfo,
+    DEFAULT_MAX_BLOCK_COUNT,
 };
-
-type Error = Box<dyn std::error::Error>;
-type Result<T> = std::result::Result<T, Error>;
-
-pub fn read_to_rgba<P: AsRef<Path>>(path: P) -> Result<Vec<Image<u8>>> {
-    let buf = std::fs::read(path)?;
-
-    let header = basis::read_header(&buf)?;
-
-    if !basis::check_file_checksum(&buf, &header) {
-        return Err("Data CRC16 failed".into());
-    }
-
-    let slice_descs = basis::read_slice_descs(&buf, &header)?;
-
-    if header.texture_format()? == TexFormat::ETC1S {
-        if header.has_alpha() && (header.total_slices % 2) != 0 {
-            return Err("File has alpha, but slice count is odd".into());
-        }
-
-        let decoder = etc1s::Decoder::from_file_bytes(&header, &buf)?;
-
-        if header.has_alpha() {
-            let mut images = Vec::with_capacity(header.total_slices as usize / 2);
-            for slice_desc in slice_descs.chunks_exact(2) {
-                let image = decoder.decode_to_rgba(&slice_desc[0], Some(&slice_desc[1]), &buf)?;
-                images.push(image.into_rgba_bytes());
-            }
-            Ok(images)
-        } else {
-            let mut images = Vec::with_capacity(header.total_slices as usize);
-            for slice_desc in &slice_descs {
-                let image = decoder.decode_to_rgba(slice_desc, None, &buf)?;
-                images.push(image.into_rgba_bytes());
-            }
-            Ok(images)
-        }
-    } else if header.texture_format()? == TexFormat::UASTC4x4 {
-        let decoder = uastc::Decoder::from_file_bytes(&header, &buf)?;
-
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-            for slice_desc in &slice_descs {
-                let image = decoder.decode_to_rgba(slice_desc, &buf)?;
-                images.push(image.into_rgba_bytes());
-            }
-            Ok(images)
-    } else {
-        unimplemented!();
-    }
-}
-
-pub fn read_to_etc1<P: AsRef<Path>>(path: P) -> Result<Vec<Image<u8>>> {
-    let buf = std::fs::read(path)?;
-
-    let header = basis::read_header(&buf)?;
-
-    if !basis::check_file_checksum(&buf, &header) {
-        return Err("Data CRC16 failed".into());
-    }
-
-    let slice_descs = basis::read_slice_descs(&buf, &header)?;
-
-    let format = header.texture_format()?;
-    if format == TexFormat::ETC1S {
-        if header.has_alpha() && (header.total_slices % 2) != 0 {
-            return Err("File has alpha, but slice count is odd".into());
-        }
-
-        let decoder = etc1s::Decoder::from_file_bytes(&header, &buf)?;
-
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-        for slice_desc in &slice_descs {
-            let image = decoder.transcode_to_etc1(slice_desc, &buf)?;
-            images.push(image);
-        }
-        Ok(images)
-    } else if format == TexFormat::UASTC4x4 {
-        let decoder = uastc::Decoder::from_file_bytes(&header, &buf)?;
-
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-        for slice_desc in &slice_descs {
-            let image = decoder.transcode_to_etc1(slice_desc, &buf)?;
-            images.push(image);
-        }
-        Ok(images)
-    } else {
-        unimplemented!();
-    }
+pub use astc::{
+    decode_block as decode_astc_block,
+    decode_image as decode_astc_image,
+};
+pub use basis::TextureType;
+pub use bc7::{
+    decode_block as decode_bc7_block,
+    decode_image as decode_bc7_image,
+};
+pub use etc::{
+    decode_alpha_block as decode_eac_alpha_block,
+    decode_r11_block as decode_eac_r11_block,
+    decode_r11_image as decode_eac_r11_image,
+    decode_rg11_block as decode_eac_rg11_block,
+    decode_rg11_image as decode_eac_rg11_image,
+    decode_rgb_block as decode_etc2_rgb_block,
+    decode_rgb_image as decode_etc2_rgb_image,
+    decode_rgba_block as decode_etc2_rgba_block,
+    decode_rgba_image as decode_etc2_rgba_image,
+};
+pub use error::Error;
+pub use video::VideoDecoder;
+
+/// Target format of transcoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TranscoderTextureFormat {
+    /// ETC1 RGB, 8 bytes per block
+    Etc1Rgb,
+    /// ETC2 RGBA, EAC alpha block followed by ETC1 color block, 16 bytes per block
+    Etc2Rgba,
+    /// BC1 RGB, 8 bytes per block
+    Bc1Rgb,
+    /// BC3 RGBA, BC4 alpha block followed by BC1 color block, 16 bytes per block
+    Bc3Rgba,
+    /// BC7 RGBA, 16 bytes per block
+    Bc7Rgba,
+    /// ASTC 4x4 LDR RGBA, 16 bytes per block
+    Astc4x4Rgba,
+    /// Uncompressed RGBA, 4 bytes per pixel
+    Rgba32,
+    /// Raw UASTC 4x4 blocks copied from the file, 16 bytes per block
+    Uastc4x4,
+    /// EAC R11, a single channel of the texture, 8 bytes per block
+    EacR11 { channel: Channel },
+    /// EAC RG11, two channels of the texture with an R11 block for each, 16 bytes per block
+    EacRg11 { channels: [Channel; 2] },
+    /// BC4, a single channel of the texture, 8 bytes per block
+    Bc4R { channel: Channel },
+    /// BC5, two channels of the texture with a BC4 block for each, 16 bytes per block
+    Bc5Rg { channels: [Channel; 2] },
+    /// PVRTC1 4bpp RGB, 8 bytes per block in Morton order, requires square power of two dimensions of at least 8x8
+    Pvrtc1Rgb,
+    /// PVRTC1 4bpp RGBA, 8 bytes per block in Morton order, requires square power of two dimensions of at least 8x8
+    Pvrtc1Rgba,
+    /// RGB565, 2 bytes per pixel with red in the highest bits, little endian
+    Rgb565 { dither: bool },
+    /// BGR565, 2 bytes per pixel with blue in the highest bits, little endian
+    Bgr565 { dither: bool },
+    /// RGBA4444, 2 bytes per pixel with red in the highest bits, little endian
+    Rgba4444 { dither: bool },
+    /// ATC RGB, 8 bytes per block
+    AtcRgb,
+    /// ATC RGBA with interpolated alpha, BC4 alpha block followed by ATC color block, 16 bytes per block
+    AtcRgba,
 }
 
-pub fn read_to_etc2<P: AsRef<Path>>(path: P) -> Result<Vec<Image<u8>>> {
-    let buf = std::fs::read(path)?;
-
-    let header = basis::read_header(&buf)?;
-
-    if !basis::check_file_checksum(&buf, &header) {
-        return Err("Data CRC16 failed".into());
-    }
-
-    let slice_descs = basis::read_slice_descs(&buf, &header)?;
-
-    let format = header.texture_format()?;
-    if format == TexFormat::UASTC4x4 {
-        let decoder = uastc::Decoder::from_file_bytes(&header, &buf)?;
-
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-        for slice_desc in &slice_descs {
-            let image = decoder.transcode_to_etc2(slice_desc, &buf)?;
-            images.push(image);
-        }
-        Ok(images)
-    } else {
-        unimplemented!();
-    }
+/// Channel of the decoded texture, used by formats which store only some of the channels.
+///
+/// Alpha of ETC1S textures comes from the alpha slices, textures without alpha are opaque.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
 }
 
-pub fn read_to_uastc<P: AsRef<Path>>(path: P) -> Result<Vec<Image<u8>>> {
-    let buf = std::fs::read(path)?;
-
-    let header = basis::read_header(&buf)?;
-
-    if !basis::check_file_checksum(&buf, &header) {
-        return Err("Data CRC16 failed".into());
-    }
-
-    let slice_descs = basis::read_slice_descs(&buf, &header)?;
-
-    if header.texture_format()? == TexFormat::UASTC4x4 {
-
-        let decoder = uastc::Decoder::from_file_bytes(&header, &buf)?;
-
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-        for slice_desc in &slice_descs {
-            let image = decoder.read_to_uastc(slice_desc, &buf)?;
-            images.push(image);
+impl Channel {
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Channel::Red => 0,
+            Channel::Green => 1,
+            Channel::Blue => 2,
+            Channel::Alpha => 3,
         }
-        Ok(images)
-    } else {
-        unimplemented!();
     }
 }
 
-pub fn read_to_astc<P: AsRef<Path>>(path: P) -> Result<Vec<Image<u8>>> {
-    let buf = std::fs::read(path)?;
-
-    let header = basis::read_header(&buf)?;
-
-    if !basis::check_file_checksum(&buf, &header) {
-        return Err("Data CRC16 failed".into());
-    }
-
-    let slice_descs = basis::read_slice_descs(&buf, &header)?;
-
-    if header.texture_format()? == TexFormat::UASTC4x4 {
+pub type Result<T> = std::result::Result<T, Error>;
 
-        let decoder = uastc::Decoder::from_file_bytes(&header, &buf)?;
+/// Transcodes all images and levels of a .basis file to the given format.
+///
+/// Returns the mip chain of each image, largest level first. Returns an
+/// error if the file can't be transcoded to the requested format.
+pub fn transcode(bytes: &[u8], format: TranscoderTextureFormat) -> Result<Vec<Vec<Image<u8>>>> {
+    let file = BasisFile::new(bytes)?;
 
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-        for slice_desc in &slice_descs {
-            let image = decoder.transcode_to_astc(slice_desc, &buf)?;
-            images.push(image);
-        }
-        Ok(images)
-    } else {
-        unimplemented!();
-    }
+    (0..file.image_count())
+        .map(|image| file.transcode_image(image, format))
+        .collect()
 }
 
-pub fn read_to_bc7<P: AsRef<Path>>(path: P) -> Result<Vec<Image<u8>>> {
+/// Reads a .basis file and transcodes all its images and levels to the given format.
+pub fn transcode_file<P: AsRef<Path>>(path: P, format: TranscoderTextureFormat) -> Result<Vec<Vec<Image<u8>>>> {
     let buf = std::fs::read(path)?;
-
-    let header = basis::read_header(&buf)?;
-
-    if !basis::check_file_checksum(&buf, &header) {
-        return Err("Data CRC16 failed".into());
-    }
-
-    let slice_descs = basis::read_slice_descs(&buf, &header)?;
-
-    if header.texture_format()? == TexFormat::UASTC4x4 {
-
-        let decoder = uastc::Decoder::from_file_bytes(&header, &buf)?;
-
-        let mut images = Vec::with_capacity(header.total_slices as usize);
-        for slice_desc in &slice_descs {
-            let image = decoder.transcode_to_bc7(slice_desc, &buf)?;
-            images.push(image);
-        }
-        Ok(images)
-    } else {
-        unimplemented!();
-    }
+    transcode(&buf, format)
 }
 
 #[doc(hidden)]
@@ -240,6 +167,58 @@ impl Image<Color32> {
     }
 }
 
+/// Decodes all blocks of a block compressed image to RGBA bytes, laid out like `Rgba32` output
+fn decode_block_image<P: AsRef<[[u8; 4]]>>(
+    image: &Image<u8>,
+    block_size: usize,
+    block_width: usize,
+    block_height: usize,
+    decode_block: impl Fn(&[u8]) -> P,
+) -> Image<u8> {
+    let num_blocks_x = image.stride as usize / block_size;
+    let num_blocks_y = if num_blocks_x > 0 { image.data.len() / image.stride as usize } else { 0 };
+    let stride = num_blocks_x * block_width * 4;
+    let mut data = vec![0u8; stride * num_blocks_y * block_height];
+    for block_y in 0..num_blocks_y {
+        for block_x in 0..num_blocks_x {
+            let block_start = block_y * image.stride as usize + block_x * block_size;
+            let pixels = decode_block(&image.data[block_start..block_start + block_size]);
+            for (i, pixel) in pixels.as_ref().iter().enumerate() {
+                let x = block_x * block_width + i % block_width;
+                let y = block_y * block_height + i / block_width;
+                data[y * stride + x * 4..y * stride + x * 4 + 4].copy_from_slice(pixel);
+            }
+        }
+    }
+    Image {
+        w: image.w,
+        h: image.h,
+        stride: stride as u32,
+        y_flipped: image.y_flipped,
+        data,
+    }
+}
+
+/// Mip level of all layers of a layered texture, stored one layer after another
+pub struct LayeredImage<T> {
+    pub w: u32,
+    pub h: u32,
+    /// Number of array layers, or depth of a volume texture
+    pub layers: u32,
+    pub stride: u32,
+    /// Distance between the starts of two consecutive layers in `data`
+    pub layer_stride: u32,
+    pub y_flipped: bool,
+    pub data: Vec<T>,
+}
+
+impl<T> LayeredImage<T> {
+    pub fn layer(&self, layer: u32) -> &[T] {
+        let start = (layer * self.layer_stride) as usize;
+        &self.data[start..start + self.layer_stride as usize]
+    }
+}
+
 #[derive(Clone, Copy, Default, PartialEq)]
 struct Color32([u8; 4]);
 
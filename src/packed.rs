@@ -0,0 +1,45 @@
+use crate::{
+    Color32,
+    Image,
+};
+
+/// Layout of a 16-bit pixel as the shift and bit count of red, green, blue and alpha
+pub struct PackedFormat([(u32, u32); 4]);
+
+pub const RGB565: PackedFormat = PackedFormat([(11, 5), (5, 6), (0, 5), (0, 0)]);
+pub const BGR565: PackedFormat = PackedFormat([(0, 5), (5, 6), (11, 5), (0, 0)]);
+pub const RGBA4444: PackedFormat = PackedFormat([(12, 4), (8, 4), (4, 4), (0, 4)]);
+
+/// 4x4 Bayer matrix of thresholds for ordered dithering
+const BAYER_4X4: [[u32; 4]; 4] = [
+    [ 0,  8,  2, 10],
+    [12,  4, 14,  6],
+    [ 3, 11,  1,  9],
+    [15,  7, 13,  5],
+];
+
+/// Packs the pixels to 16 bits each, stored as little endian
+pub fn pack_image(image: Image<Color32>, format: &PackedFormat, dither: bool) -> Image<u8> {
+    let stride = image.stride as usize;
+    let mut data = vec![0u8; image.data.len() * 2];
+    for (i, (color, output)) in image.data.iter().zip(data.chunks_exact_mut(2)).enumerate() {
+        // Threshold in 32nds of a step, without dithering every pixel rounds to nearest,
+        // which is also the mean of the dithering thresholds
+        let threshold = if dither { 2 * BAYER_4X4[i / stride % 4][i % stride % 4] + 1 } else { 16 };
+        let mut packed = 0u16;
+        for (c, &(shift, bits)) in format.0.iter().enumerate() {
+            let max = (1u32 << bits) - 1;
+            let value = (color[c] as u32 * max * 32 + threshold * 255) / (255 * 32);
+            packed |= (value as u16) << shift;
+        }
+        output.copy_from_slice(&packed.to_le_bytes());
+    }
+
+    Image {
+        w: image.w,
+        h: image.h,
+        stride: image.stride * 2,
+        y_flipped: image.y_flipped,
+        data,
+    }
+}
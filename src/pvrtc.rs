@@ -0,0 +1,239 @@
+use crate::{
+    Color32,
+    Error,
+    Result,
+};
+
+pub const PVRTC1_BLOCK_SIZE: usize = 8;
+
+/// Checks that the texture, padded to whole blocks, is a square with power of two dimensions
+/// of at least 8x8, the smallest PVRTC1 4bpp texture.
+///
+/// Non-square textures are valid PVRTC1, but some platforms (e.g. iOS) only accept square ones.
+pub fn check_dimensions(num_blocks_x: u32, num_blocks_y: u32) -> Result<()> {
+    if num_blocks_x == num_blocks_y && num_blocks_x.is_power_of_two() && num_blocks_x >= 2 {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedDimensions { width: 4 * num_blocks_x, height: 4 * num_blocks_y })
+    }
+}
+
+/// Encodes an image covering whole blocks to PVRTC1 4bpp, the blocks are stored in Morton order.
+///
+/// The colors of a block are interpolated from the endpoints of the neighboring blocks
+/// (wrapping at the edges), so the endpoints of all blocks are chosen before the modulation.
+pub fn encode_image(pixels: &[Color32], num_blocks_x: u32, num_blocks_y: u32, alpha: bool) -> Vec<u8> {
+    let (num_blocks_x, num_blocks_y) = (num_blocks_x as usize, num_blocks_y as usize);
+    let stride = 4 * num_blocks_x;
+
+    let block_pixels = |block_x: usize, block_y: usize| {
+        let mut block = [Color32::default(); 16];
+        for (i, pixel) in block.iter_mut().enumerate() {
+            *pixel = pixels[(4 * block_y + i / 4) * stride + 4 * block_x + i % 4];
+            if !alpha {
+                pixel[3] = 0xFF;
+            }
+        }
+        block
+    };
+
+    let mut packed_endpoints = vec![0u32; num_blocks_x * num_blocks_y];
+    for block_y in 0..num_blocks_y {
+        for block_x in 0..num_blocks_x {
+            let endpoints = block_endpoints(&block_pixels(block_x, block_y));
+            packed_endpoints[block_y * num_blocks_x + block_x] = pack_endpoint(endpoints, 0) as u32 | (pack_endpoint(endpoints, 1) as u32) << 16;
+        }
+    }
+
+    let endpoints: Vec<[[u32; 4]; 2]> = packed_endpoints.iter()
+        .map(|&packed| [unpack_endpoint(packed as u16, 0), unpack_endpoint((packed >> 16) as u16, 1)])
+        .collect();
+
+    let mut output = vec![0u8; num_blocks_x * num_blocks_y * PVRTC1_BLOCK_SIZE];
+    for block_y in 0..num_blocks_y {
+        for block_x in 0..num_blocks_x {
+            let block = block_pixels(block_x, block_y);
+            let mut modulation = 0u32;
+            for (i, pixel) in block.iter().enumerate() {
+                let (x, y) = (4 * block_x + i % 4, 4 * block_y + i / 4);
+                let [a, b] = interpolate_endpoints(&endpoints, num_blocks_x, num_blocks_y, x, y);
+                let index = (0..4)
+                    .min_by_key(|&index| {
+                        let color = modulate(a, b, MODULATION_WEIGHTS[index]);
+                        (0..4).map(|c| {
+                            let d = color[c] as i32 - pixel[c] as i32;
+                            (d * d) as u32
+                        }).sum::<u32>()
+                    })
+                    .unwrap();
+                modulation |= (index as u32) << (2 * i);
+            }
+
+            let block_id = morton_index(num_blocks_x, num_blocks_y, block_x, block_y);
+            let block_start = block_id * PVRTC1_BLOCK_SIZE;
+            let block_end = block_start + PVRTC1_BLOCK_SIZE;
+            output[block_start..block_start + 4].copy_from_slice(&modulation.to_le_bytes());
+            output[block_start + 4..block_end].copy_from_slice(&packed_endpoints[block_y * num_blocks_x + block_x].to_le_bytes());
+        }
+    }
+    output
+}
+
+/// Modulation weights of the standard mode, in eighths of endpoint B
+const MODULATION_WEIGHTS: [u32; 4] = [0, 3, 5, 8];
+
+/// Alpha of an endpoint above this is stored in the opaque format
+const OPAQUE_THRESHOLD: u8 = 247;
+
+/// Chooses the low and high endpoint from the bounding box of the block.
+///
+/// Channels which decrease while the others increase get their extremes swapped.
+fn block_endpoints(pixels: &[Color32; 16]) -> [Color32; 2] {
+    let mut mean = [0i32; 4];
+    for pixel in pixels {
+        for c in 0..4 {
+            mean[c] += pixel[c] as i32;
+        }
+    }
+
+    let luma = |p: &Color32| 2 * p[0] as i32 + 5 * p[1] as i32 + p[2] as i32;
+    let mean_luma = pixels.iter().map(luma).sum::<i32>();
+
+    let mut low = Color32::new(0xFF, 0xFF, 0xFF, 0xFF);
+    let mut high = Color32::new(0, 0, 0, 0);
+    for c in 0..4 {
+        let min = pixels.iter().map(|p| p[c]).min().unwrap();
+        let max = pixels.iter().map(|p| p[c]).max().unwrap();
+        let correlation: i32 = pixels.iter()
+            .map(|p| (16 * p[c] as i32 - mean[c]) * (16 * luma(p) - mean_luma) / 256)
+            .sum();
+        if correlation < 0 {
+            low[c] = max;
+            high[c] = min;
+        } else {
+            low[c] = min;
+            high[c] = max;
+        }
+    }
+    [low, high]
+}
+
+/// Packs endpoint A (`index` 0) or B (`index` 1), the lowest bit of A is the modulation mode.
+///
+/// Channels which are flat in the block are rounded down in A and up in B, so that modulation
+/// can reach the values between the quantization levels, the others are rounded to nearest.
+fn pack_endpoint(endpoints: [Color32; 2], index: usize) -> u16 {
+    let color = endpoints[index];
+    let opaque = color[3] > OPAQUE_THRESHOLD;
+    let layout = match (opaque, index) {
+        (true, 0) => [(10, 5), (5, 5), (1, 4), (12, 0)],
+        (true, _) => [(10, 5), (5, 5), (0, 5), (12, 0)],
+        (false, 0) => [(8, 4), (4, 4), (1, 3), (12, 3)],
+        (false, _) => [(8, 4), (4, 4), (0, 4), (12, 3)],
+    };
+
+    let mut packed = if opaque { 0x8000 } else { 0 };
+    for (c, &(shift, bits)) in layout.iter().enumerate() {
+        if bits == 0 {
+            continue;
+        }
+        let value = color[c] as u32;
+        let expand = |q: u16| {
+            let channel = unpack_endpoint(packed | q << shift, index)[c];
+            if c < 3 { expand_5_to_8(channel) } else { expand_4_to_8(channel) }
+        };
+        let max = (1u16 << bits) - 1;
+        let q = if endpoints[0][c] != endpoints[1][c] {
+            (0..=max).min_by_key(|&q| (expand(q) as i32 - value as i32).abs()).unwrap()
+        } else if index == 0 {
+            (0..=max).rev().find(|&q| expand(q) <= value).unwrap_or(0)
+        } else {
+            (0..=max).find(|&q| expand(q) >= value).unwrap_or(max)
+        };
+        packed |= q << shift;
+    }
+    packed
+}
+
+fn expand_5_to_8(value: u32) -> u32 {
+    (value << 3) | (value >> 2)
+}
+
+fn expand_4_to_8(value: u32) -> u32 {
+    (value << 4) | value
+}
+
+/// Expands a packed endpoint to 5 bits of RGB and 4 bits of alpha
+fn unpack_endpoint(packed: u16, index: usize) -> [u32; 4] {
+    let packed = packed as u32;
+    if packed & 0x8000 != 0 {
+        let b = if index == 0 {
+            let b = (packed >> 1) & 0xF;
+            (b << 1) | (b >> 3)
+        } else {
+            packed & 0x1F
+        };
+        [(packed >> 10) & 0x1F, (packed >> 5) & 0x1F, b, 0xF]
+    } else {
+        let expand4 = |v: u32| (v << 1) | (v >> 3);
+        let b = if index == 0 {
+            let b = (packed >> 1) & 0x7;
+            (b << 2) | (b >> 1)
+        } else {
+            expand4(packed & 0xF)
+        };
+        [expand4((packed >> 8) & 0xF), expand4((packed >> 4) & 0xF), b, ((packed >> 12) & 0x7) << 1]
+    }
+}
+
+/// Bilinearly interpolates both endpoints of the 4 blocks around the pixel and expands them to 8 bits
+fn interpolate_endpoints(endpoints: &[[[u32; 4]; 2]], num_blocks_x: usize, num_blocks_y: usize, x: usize, y: usize) -> [Color32; 2] {
+    // Endpoints are positioned at the block centers
+    let (x, y) = (x + 4 * num_blocks_x - 2, y + 4 * num_blocks_y - 2);
+    let (x0, y0) = ((x / 4) % num_blocks_x, (y / 4) % num_blocks_y);
+    let (x1, y1) = ((x0 + 1) % num_blocks_x, (y0 + 1) % num_blocks_y);
+    let (fx, fy) = ((x % 4) as u32, (y % 4) as u32);
+
+    let corners = [
+        (x0, y0, (4 - fx) * (4 - fy)),
+        (x1, y0, fx * (4 - fy)),
+        (x0, y1, (4 - fx) * fy),
+        (x1, y1, fx * fy),
+    ];
+
+    [0, 1].map(|index| {
+        let mut sum = [0u32; 4];
+        for &(bx, by, weight) in &corners {
+            let endpoint = endpoints[by * num_blocks_x + bx][index];
+            for c in 0..4 {
+                sum[c] += endpoint[c] * weight;
+            }
+        }
+        Color32::new(
+            ((sum[0] >> 1) + (sum[0] >> 6)) as u8,
+            ((sum[1] >> 1) + (sum[1] >> 6)) as u8,
+            ((sum[2] >> 1) + (sum[2] >> 6)) as u8,
+            (sum[3] + (sum[3] >> 4)) as u8,
+        )
+    })
+}
+
+fn modulate(a: Color32, b: Color32, weight: u32) -> Color32 {
+    let mix = |c: usize| ((a[c] as u32 * (8 - weight) + b[c] as u32 * weight) / 8) as u8;
+    Color32::new(mix(0), mix(1), mix(2), mix(3))
+}
+
+/// Index of the block in Morton order, the low bits of x and y are interleaved with y first
+fn morton_index(num_blocks_x: usize, num_blocks_y: usize, x: usize, y: usize) -> usize {
+    let min_dimension = num_blocks_x.min(num_blocks_y);
+    let mut index = 0;
+    let mut shift = 0;
+    while (1 << shift) < min_dimension {
+        index |= ((y >> shift) & 1) << (2 * shift);
+        index |= ((x >> shift) & 1) << (2 * shift + 1);
+        shift += 1;
+    }
+    // The remaining bits of the larger dimension follow
+    let rest = if num_blocks_x > num_blocks_y { x } else { y };
+    index | (rest >> shift) << (2 * shift)
+}
@@ -1,5 +1,7 @@
 use crate::{
+    Channel,
     Color32,
+    Error,
     Image,
     Result,
     basis::{
@@ -7,7 +9,7 @@ use crate::{
         SliceDesc,
     },
     bitreader::BitReaderLsb,
-    astc, bc7, etc,
+    astc, bc1, bc4, bc7, etc, pvrtc,
 };
 
 #[cfg(test)]
@@ -25,6 +27,15 @@ mod tests_to_etc1;
 #[cfg(test)]
 mod tests_to_etc2;
 
+#[cfg(test)]
+mod tests_to_eac;
+
+#[cfg(test)]
+mod tests_to_bc1;
+
+#[cfg(test)]
+mod tests_to_bc4;
+
 const MAX_ENDPOINT_COUNT: usize = 18;
 const MAX_WEIGHT_COUNT: usize = 32;
 
@@ -71,9 +82,8 @@ impl Decoder {
         const UASTC_BLOCK_SIZE: usize = 16;
 
         let block_bytes = {
-            let start = slice_desc.file_ofs as usize;
-            let len = slice_desc.file_size as usize;
-            &bytes[start..start+len]
+            let len = UASTC_BLOCK_SIZE * slice_desc.num_blocks_x as usize * slice_desc.num_blocks_y as usize;
+            slice_desc.data(bytes)?.get(..len).ok_or(Error::TruncatedData)?
         };
 
         let image = Image {
@@ -133,6 +143,30 @@ impl Decoder {
         Ok(image)
     }
 
+    pub(crate) fn transcode_to_bc1(&self, slice_desc: &SliceDesc, bytes: &[u8]) -> Result<Image<u8>> {
+
+        const UASTC_BLOCK_SIZE: usize = 16;
+        const BC1_BLOCK_SIZE: usize = bc1::BC1_BLOCK_SIZE;
+
+        let mut image = Image {
+            w: slice_desc.orig_width as u32,
+            h: slice_desc.orig_height as u32,
+            stride: BC1_BLOCK_SIZE as u32 * slice_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: vec![0u8; slice_desc.num_blocks_x as usize * slice_desc.num_blocks_y as usize * BC1_BLOCK_SIZE],
+        };
+
+        let block_to_bc1 = |_block_x: u32, _block_y: u32, block_offset: usize, block_bytes: &[u8]| {
+            let output_offset = block_offset / UASTC_BLOCK_SIZE * BC1_BLOCK_SIZE;
+            let output = &mut image.data[output_offset..output_offset + BC1_BLOCK_SIZE];
+            bc1::convert_block_from_uastc(block_bytes, output);
+        };
+
+        self.iterate_blocks(slice_desc, bytes, block_to_bc1)?;
+
+        Ok(image)
+    }
+
     pub(crate) fn transcode_to_bc7(&self, slice_desc: &SliceDesc, bytes: &[u8]) -> Result<Image<u8>> {
 
         const BC7_BLOCK_SIZE: usize = 16;
@@ -199,24 +233,126 @@ impl Decoder {
         Ok(image)
     }
 
+    pub(crate) fn transcode_to_bc3(&self, slice_desc: &SliceDesc, bytes: &[u8]) -> Result<Image<u8>> {
+
+        const UASTC_BLOCK_SIZE: usize = 16;
+        const BC3_BLOCK_SIZE: usize = bc4::BC4_BLOCK_SIZE + bc1::BC1_BLOCK_SIZE;
+
+        let mut image = Image {
+            w: slice_desc.orig_width as u32,
+            h: slice_desc.orig_height as u32,
+            stride: BC3_BLOCK_SIZE as u32 * slice_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: vec![0u8; slice_desc.num_blocks_x as usize * slice_desc.num_blocks_y as usize * BC3_BLOCK_SIZE],
+        };
+
+        // Alpha block first, color block second
+        let block_to_bc3 = |_block_x: u32, _block_y: u32, block_offset: usize, block_bytes: &[u8]| {
+            let output_offset = block_offset / UASTC_BLOCK_SIZE * BC3_BLOCK_SIZE;
+            let output = &mut image.data[output_offset..output_offset + BC3_BLOCK_SIZE];
+            let rgba = decode_block_to_rgba(block_bytes);
+            bc4::encode_block(&rgba.map(|color| color[3]), &mut output[..bc4::BC4_BLOCK_SIZE]);
+            bc1::convert_block_from_uastc(block_bytes, &mut output[bc4::BC4_BLOCK_SIZE..]);
+        };
+
+        self.iterate_blocks(slice_desc, bytes, block_to_bc3)?;
+
+        Ok(image)
+    }
+
+    pub(crate) fn transcode_to_pvrtc1(&self, slice_desc: &SliceDesc, bytes: &[u8], alpha: bool) -> Result<Image<u8>> {
+        let num_blocks_x = slice_desc.num_blocks_x as u32;
+        let num_blocks_y = slice_desc.num_blocks_y as u32;
+        pvrtc::check_dimensions(num_blocks_x, num_blocks_y)?;
+
+        let rgba = self.decode_to_rgba(slice_desc, bytes)?;
+
+        Ok(Image {
+            w: rgba.w,
+            h: rgba.h,
+            stride: pvrtc::PVRTC1_BLOCK_SIZE as u32 * num_blocks_x,
+            y_flipped: self.y_flipped,
+            data: pvrtc::encode_image(&rgba.data, num_blocks_x, num_blocks_y, alpha),
+        })
+    }
+
+    /// Transcodes the channels to EAC R11 blocks, one block per channel
+    pub(crate) fn transcode_to_eac(&self, slice_desc: &SliceDesc, bytes: &[u8], channels: &[Channel]) -> Result<Image<u8>> {
+        self.transcode_channels(slice_desc, bytes, channels, 8, etc::encode_eac_block)
+    }
+
+    /// Transcodes the channels to BC4 blocks, one block per channel
+    pub(crate) fn transcode_to_bc4(&self, slice_desc: &SliceDesc, bytes: &[u8], channels: &[Channel]) -> Result<Image<u8>> {
+        self.transcode_channels(slice_desc, bytes, channels, bc4::BC4_BLOCK_SIZE, bc4::encode_block)
+    }
+
+    fn transcode_channels<F>(&self, slice_desc: &SliceDesc, bytes: &[u8], channels: &[Channel], channel_block_size: usize, encode_channel: F) -> Result<Image<u8>>
+        where F: Fn(&[u8; 16], &mut [u8])
+    {
+        const UASTC_BLOCK_SIZE: usize = 16;
+        let block_size = channel_block_size * channels.len();
+
+        let mut image = Image {
+            w: slice_desc.orig_width as u32,
+            h: slice_desc.orig_height as u32,
+            stride: block_size as u32 * slice_desc.num_blocks_x as u32,
+            y_flipped: self.y_flipped,
+            data: vec![0u8; slice_desc.num_blocks_x as usize * slice_desc.num_blocks_y as usize * block_size],
+        };
+
+        let block_to_channels = |_block_x: u32, _block_y: u32, block_offset: usize, block_bytes: &[u8]| {
+            let output_offset = block_offset / UASTC_BLOCK_SIZE * block_size;
+            let output = &mut image.data[output_offset..output_offset + block_size];
+            let rgba = decode_block_to_rgba(block_bytes);
+            for (&channel, output) in channels.iter().zip(output.chunks_exact_mut(channel_block_size)) {
+                encode_channel(&rgba.map(|color| color[channel.index()]), output);
+            }
+        };
+
+        self.iterate_blocks(slice_desc, bytes, block_to_channels)?;
+
+        Ok(image)
+    }
+
+    /// Checks that all blocks of the slice use valid modes, pattern indices and component
+    /// selectors, and that solid color blocks have ETC1 base colors in range.
+    ///
+    /// Endpoints and weights can't be out of range. The transcoding hints (BC1 and ETC1
+    /// flags, ETC1 bias, ETC2 alpha table) are not checked against the block contents,
+    /// a bad hint lowers the quality of the transcoded block but isn't illegal.
+    pub(crate) fn validate_slice(&self, slice_desc: &SliceDesc, bytes: &[u8]) -> Result<()> {
+        let mut res = Ok(());
+
+        self.iterate_blocks(slice_desc, bytes, |block_x, block_y, _block_offset, block_bytes| {
+            if res.is_ok() {
+                if let Err(reason) = validate_block(block_bytes) {
+                    res = Err(Error::IllegalEncoding {
+                        slice: slice_desc.index,
+                        block_x,
+                        block_y,
+                        reason,
+                    });
+                }
+            }
+        })?;
+
+        res
+    }
+
     fn iterate_blocks<F>(&self, slice_desc: &SliceDesc, bytes: &[u8], mut f: F) -> Result<()>
         where F: FnMut(u32, u32, usize, &[u8])
     {
         let num_blocks_x = slice_desc.num_blocks_x as u32;
         let num_blocks_y = slice_desc.num_blocks_y as u32;
 
-        let bytes = {
-            let start = slice_desc.file_ofs as usize;
-            let len = slice_desc.file_size as usize;
-            &bytes[start..start+len]
-        };
+        let bytes = slice_desc.data(bytes)?;
 
         let mut block_offset = 0;
 
         const BLOCK_SIZE: usize = 16;
 
         if bytes.len() < BLOCK_SIZE * num_blocks_x as usize * num_blocks_y as usize {
-            return Err("Not enough bytes for all blocks".into());
+            return Err(Error::TruncatedData);
         }
 
         for block_y in 0..num_blocks_y {
@@ -263,7 +399,7 @@ pub(crate) fn assemble_endpoint_pairs(mode: Mode, endpoint_bytes: &[u8]) -> [[Co
     endpoint_pairs
 }
 
-fn astc_interpolate(mut l: u32, mut h: u32, w: u32, srgb: bool) -> u8 {
+pub(crate) fn astc_interpolate(mut l: u32, mut h: u32, w: u32, srgb: bool) -> u8 {
     if srgb {
         l = (l << 8) | 0x80;
         h = (h << 8) | 0x80;
@@ -284,6 +420,24 @@ pub(crate) fn decode_block_to_rgba(bytes: &[u8]) -> [Color32; 16] {
     }
 }
 
+pub(crate) fn validate_block(bytes: &[u8]) -> std::result::Result<(), &'static str> {
+    let reader = &mut BitReaderLsb::new(bytes);
+
+    let mode = decode_mode(reader).map_err(|_| "invalid mode")?;
+
+    if mode.id == 8 {
+        skip_mode8_rgba(reader);
+        decode_mode8_etc1_flags(reader).map_err(|_| "solid color ETC1 base color out of range")?;
+        return Ok(());
+    }
+
+    skip_trans_flags(reader, mode);
+    decode_compsel(reader, mode).map_err(|_| "component selector out of range")?;
+    decode_pattern_index(reader, mode).map_err(|_| "pattern index out of range")?;
+
+    Ok(())
+}
+
 fn decode_block_to_rgba_result(bytes: &[u8]) -> Result<[Color32; 16]> {
 
     let reader = &mut BitReaderLsb::new(bytes);
@@ -297,7 +451,7 @@ fn decode_block_to_rgba_result(bytes: &[u8]) -> Result<[Color32; 16]> {
     skip_trans_flags(reader, mode);
 
     // Component selector for dual-plane modes
-    let compsel = decode_compsel(reader, mode);
+    let compsel = decode_compsel(reader, mode)?;
 
     // Pattern id for modes with multiple subsets
     let pat = decode_pattern_index(reader, mode)?;
@@ -371,7 +525,7 @@ pub fn decode_mode(reader: &mut BitReaderLsb) -> Result<Mode> {
     let mode_index = MODE_LUT[mode_code] as usize;
 
     if mode_index >= 19 {
-        return Err("invalid mode index".into());
+        return Err(Error::InvalidBlock);
     }
 
     let mode = MODES[mode_index];
@@ -381,12 +535,19 @@ pub fn decode_mode(reader: &mut BitReaderLsb) -> Result<Mode> {
     Ok(mode)
 }
 
-pub fn decode_compsel(reader: &mut BitReaderLsb, mode: Mode) -> u8 {
-    match (mode.plane_count, mode.format) {
+pub fn decode_compsel(reader: &mut BitReaderLsb, mode: Mode) -> Result<u8> {
+    let compsel = match (mode.plane_count, mode.format) {
         // LA modes always have component selector 3 for alpha
         (2, Format::La) => 3,
         (2, _) => reader.read_u8(2),
         _ => 0,
+    };
+
+    // RGB modes have no alpha channel to put on the second plane
+    if compsel == 3 && !mode.has_alpha() {
+        Err(Error::InvalidBlock)
+    } else {
+        Ok(compsel)
     }
 }
 
@@ -406,7 +567,7 @@ pub fn decode_pattern_index(reader: &mut BitReaderLsb, mode: Mode) -> Result<u8>
     if (pattern_index as usize) < pattern_count {
         Ok(pattern_index)
     } else {
-        Err("block pattern is not valid".into())
+        Err(Error::InvalidBlock)
     }
 }
 
@@ -442,14 +603,21 @@ pub fn skip_mode8_rgba(reader: &mut BitReaderLsb) {
     reader.remove(32);
 }
 
-pub fn decode_mode8_etc1_flags(reader: &mut BitReaderLsb) -> Mode8Etc1Flags {
-    Mode8Etc1Flags {
+pub fn decode_mode8_etc1_flags(reader: &mut BitReaderLsb) -> Result<Mode8Etc1Flags> {
+    let flags = Mode8Etc1Flags {
         etc1d: reader.read_bool(),
         etc1i: reader.read_u8(3),
         etc1s: reader.read_u8(2),
         etc1r: reader.read_u8(5),
         etc1g: reader.read_u8(5),
         etc1b: reader.read_u8(5),
+    };
+
+    // Without the differential bit the base color is 4 bits per channel
+    if !flags.etc1d && (flags.etc1r | flags.etc1g | flags.etc1b) > 0xF {
+        Err(Error::InvalidBlock)
+    } else {
+        Ok(flags)
     }
 }
 
@@ -699,7 +867,7 @@ pub fn decode_endpoints(reader: &mut BitReaderLsb, range_index: u8, value_count:
     output
 }
 
-fn unquant_weights(weights: &mut [u8], weight_bits: u8) {
+pub(crate) fn unquant_weights(weights: &mut [u8], weight_bits: u8) {
     const LUT1: [u8; 2] = [ 0, 64 ];
     const LUT2: [u8; 4] = [ 0, 21, 43, 64 ];
     const LUT3: [u8; 8] = [ 0, 9, 18, 27, 37, 46, 55, 64 ];
@@ -42,6 +42,20 @@ fn test_blocks_uastc_to_astc() {
     test_uastc_mode(18);
 }
 
+#[test]
+fn test_decode_astc_blocks() {
+    for (mode, test_data) in TEST_DATA_UASTC_ASTC.iter().enumerate() {
+        for (uastc, astc) in test_data.iter() {
+            // UASTC is a subset of ASTC 4x4, decoding is lossless
+            let expected = decode_block_to_rgba(uastc);
+            let actual = astc::decode_block(astc, 4, 4, false).unwrap();
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                assert_eq!([e[0], e[1], e[2], e[3]], *a, "\nUASTC Mode: {}\n{:02X?}\n{:02X?}", mode, uastc, astc);
+            }
+        }
+    }
+}
+
 static TEST_DATA_UASTC_ASTC: [[([u8; 16], [u8; 16]); 32]; 19] = [
     [   // 0
         ([0xB1, 0x1B, 0x7F, 0x16, 0xD0, 0xA9, 0x98, 0xB9, 0x4B, 0x50, 0x9E, 0x57, 0xB8, 0x9C, 0x73, 0xAB], [0x42, 0x02, 0x81, 0x75, 0x28, 0xF1, 0xCC, 0x5B, 0xD5, 0xCE, 0x39, 0x1D, 0xEA, 0x79, 0x0A, 0xA2]),
@@ -0,0 +1,84 @@
+use super::*;
+use super::tests_to_rgba::TEST_DATA_UASTC_RGBA;
+
+fn decode_bc1_block(block: &[u8]) -> [Color32; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let unpack = |c: u16| {
+        let (r, g, b) = ((c >> 11) as i32, (c >> 5 & 63) as i32, (c & 31) as i32);
+        [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+    };
+    let (c0, c1) = (unpack(color0), unpack(color1));
+    // Only the 4 color mode is produced
+    assert!(color0 > color1 || indices == 0, "{:02X?}", block);
+    let palette = [
+        c0,
+        c1,
+        [0, 1, 2].map(|c| (2 * c0[c] + c1[c]) / 3),
+        [0, 1, 2].map(|c| (c0[c] + 2 * c1[c]) / 3),
+    ];
+
+    let mut pixels = [Color32::default(); 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let color = palette[(indices >> (2 * i)) as usize & 3];
+        *pixel = Color32::new(color[0] as u8, color[1] as u8, color[2] as u8, 0xFF);
+    }
+    pixels
+}
+
+/// Quantized UASTC endpoints of a single subset block as packed 5:6:5 colors
+fn uastc_endpoints_565(uastc: &[u8]) -> [u16; 2] {
+    let reader = &mut BitReaderLsb::new(uastc);
+    let mode = decode_mode(reader).unwrap();
+    skip_trans_flags(reader, mode);
+    decode_compsel(reader, mode).unwrap();
+    let pat = decode_pattern_index(reader, mode).unwrap();
+    assert_eq!(pat, 0);
+
+    let quant_endpoints = decode_endpoints(reader, mode.endpoint_range_index, mode.endpoint_count());
+    let mut endpoints = [0u8; MAX_ENDPOINT_COUNT];
+    for (quant, unquant) in quant_endpoints.iter().zip(endpoints.iter_mut()) {
+        *unquant = unquant_endpoint(*quant, mode.endpoint_range_index);
+    }
+    assemble_endpoint_pairs(mode, &endpoints)[0].map(|e| {
+        let quantize = |v: u8, max: u32| (v as u32 * max + 127) / 255;
+        (quantize(e[0], 31) << 11 | quantize(e[1], 63) << 5 | quantize(e[2], 31)) as u16
+    })
+}
+
+#[test]
+fn test_blocks_uastc_to_bc1() {
+    let mut total_error = 0;
+    let mut count = 0;
+    for test_blocks in TEST_DATA_UASTC_RGBA.iter() {
+        for (uastc, _) in test_blocks.iter() {
+            let mut bc1 = [0u8; 8];
+            bc1::convert_block_from_uastc(uastc, &mut bc1);
+
+            // Blocks with the hint and a single set of color weights are converted directly from the UASTC endpoints
+            let reader = &mut BitReaderLsb::new(uastc);
+            let mode = decode_mode(reader).unwrap();
+            let direct = mode.id != 8 && decode_trans_flags(reader, mode).bc1h0
+                && mode.subset_count == 1 && (mode.plane_count == 1 || decode_compsel(reader, mode).unwrap() == 3);
+            if direct {
+                let mut colors = [u16::from_le_bytes([bc1[0], bc1[1]]), u16::from_le_bytes([bc1[2], bc1[3]])];
+                let mut expected = uastc_endpoints_565(uastc);
+                colors.sort_unstable();
+                expected.sort_unstable();
+                assert_eq!(colors, expected, "\n{:02X?}\n{:02X?}", uastc, bc1);
+            }
+
+            let rgba = decode_block_to_rgba(uastc);
+            for (expected, actual) in rgba.iter().zip(decode_bc1_block(&bc1).iter()) {
+                for c in 0..3 {
+                    total_error += (expected[c] as i32 - actual[c] as i32).abs();
+                    count += 1;
+                }
+            }
+        }
+    }
+    let mean = total_error as f64 / count as f64;
+    assert!(mean <= 5.0, "mean error {}", mean);
+}
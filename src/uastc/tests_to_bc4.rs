@@ -0,0 +1,46 @@
+use super::*;
+use super::tests_to_rgba::TEST_DATA_UASTC_RGBA;
+
+fn decode_bc4_block(block: &[u8]) -> [u8; 16] {
+    let (e0, e1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [e0, e1, 0, 0, 0, 0, 0, 0];
+    for (i, entry) in palette.iter_mut().enumerate().skip(2) {
+        let i = i as u32;
+        *entry = if e0 > e1 {
+            ((8 - i) * e0 + (i - 1) * e1) / 7
+        } else if i < 6 {
+            ((6 - i) * e0 + (i - 1) * e1) / 5
+        } else {
+            (i - 6) * 255
+        };
+    }
+    let indices = u64::from_le_bytes([block[2], block[3], block[4], block[5], block[6], block[7], 0, 0]);
+
+    let mut values = [0u8; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[(indices >> (3 * i)) as usize & 7] as u8;
+    }
+    values
+}
+
+#[test]
+fn test_blocks_uastc_to_bc4() {
+    for test_blocks in TEST_DATA_UASTC_RGBA.iter() {
+        for (uastc, _) in test_blocks.iter() {
+            let rgba = decode_block_to_rgba(uastc);
+            for c in 0..4 {
+                let values = rgba.map(|color| color[c]);
+                let mut bc4 = [0u8; 8];
+                bc4::encode_block(&values, &mut bc4);
+
+                let min = *values.iter().min().unwrap();
+                let max = *values.iter().max().unwrap();
+                for (&expected, actual) in values.iter().zip(decode_bc4_block(&bc4)) {
+                    // 8 evenly spaced levels between the extremes
+                    let error = (expected as i32 - actual as i32).abs();
+                    assert!(error <= (max - min) as i32 / 14 + 1, "\n{:02X?}\n{:?}\n{:02X?}", uastc, values, bc4);
+                }
+            }
+        }
+    }
+}
@@ -56,6 +56,23 @@ fn test_blocks_uastc_to_bc7() {
     test_uastc_mode(18);
 }
 
+#[test]
+fn test_decode_bc7_blocks() {
+    for test_data in TEST_DATA_UASTC_BC7.iter() {
+        for (uastc, bc7) in test_data.iter() {
+            let expected = decode_block_to_rgba(uastc);
+            let actual = bc7::decode_block(bc7);
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                for c in 0..4 {
+                    // Transcoding to BC7 requantizes the endpoints and weights
+                    let error = (e[c] as i32 - a[c] as i32).abs();
+                    assert!(error <= 6, "\n{:02X?}\n{:?}\n{:?}", uastc, expected, actual);
+                }
+            }
+        }
+    }
+}
+
 static TEST_DATA_UASTC_BC7: [[([u8; 16], [u8; 16]); 32]; 19] = [
     [   // 0
         ([0xB1, 0x1B, 0x7F, 0x16, 0xD0, 0xA9, 0x98, 0xB9, 0x4B, 0x50, 0x9E, 0x57, 0xB8, 0x9C, 0x73, 0xAB], [0x40, 0x80, 0xAE, 0xF2, 0x6C, 0x24, 0xFF, 0x7F, 0x4A, 0x50, 0x9E, 0x57, 0xB8, 0x9C, 0x73, 0xAB]),
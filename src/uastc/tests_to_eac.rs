@@ -0,0 +1,30 @@
+use super::*;
+use super::tests_to_rgba::TEST_DATA_UASTC_RGBA;
+
+#[test]
+fn test_blocks_uastc_to_eac() {
+    let mut total_error = 0;
+    let mut count = 0;
+    for test_blocks in TEST_DATA_UASTC_RGBA.iter() {
+        for (uastc, _) in test_blocks.iter() {
+            let rgba = decode_block_to_rgba(uastc);
+            for c in 0..4 {
+                let values = rgba.map(|color| color[c]);
+                let mut eac = [0u8; 8];
+                etc::encode_eac_block(&values, &mut eac);
+
+                let min = *values.iter().min().unwrap();
+                let max = *values.iter().max().unwrap();
+                for (&expected, actual) in values.iter().zip(etc::decode_alpha_block(&eac)) {
+                    let error = (expected as i32 - actual as i32).abs();
+                    // 8 levels between the extremes, spaced no further apart than the modifiers allow
+                    assert!(error <= (max - min) as i32 / 8 + 2, "\n{:02X?}\n{:?}\n{:02X?}", uastc, values, eac);
+                    total_error += error;
+                    count += 1;
+                }
+            }
+        }
+    }
+    let mean = total_error as f64 / count as f64;
+    assert!(mean <= 2.0, "mean error {}", mean);
+}
@@ -56,6 +56,27 @@ fn test_blocks_uastc_to_etc2() {
     test_uastc_mode(18);
 }
 
+#[test]
+fn test_decode_etc2_blocks() {
+    let mut total_error = 0;
+    let mut count = 0;
+    for test_data in TEST_DATA_UASTC_ETC2.iter() {
+        for (uastc, etc2) in test_data.iter() {
+            let expected = decode_block_to_rgba(uastc);
+            let actual = etc::decode_rgba_block(etc2);
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                for c in 0..4 {
+                    total_error += (e[c] as i32 - a[c] as i32).abs();
+                    count += 1;
+                }
+            }
+        }
+    }
+    // Each ETC1 subblock has only 4 colors on a gray line, the alpha blocks have 8 values
+    let mean = total_error as f64 / count as f64;
+    assert!(mean <= 3.0, "mean error {}", mean);
+}
+
 static TEST_DATA_UASTC_ETC2: [[([u8; 16], [u8; 16]); 32]; 19] = [
     [   // 0
         ([0xB1, 0x1B, 0x7F, 0x16, 0xD0, 0xA9, 0x98, 0xB9, 0x4B, 0x50, 0x9E, 0x57, 0xB8, 0x9C, 0x73, 0xAB], [0xFF, 0x1D, 0x92, 0x49, 0x24, 0x92, 0x49, 0x24, 0x46, 0x6E, 0x5E, 0x6E, 0x31, 0x9D, 0x8D, 0x1A]),
@@ -45,7 +45,7 @@ fn test_blocks_uastc_to_rgba() {
     test_uastc_mode(18);
 }
 
-static TEST_DATA_UASTC_RGBA: [[([u8; 16], [u32; 16]); 32]; 19] = [
+pub(super) static TEST_DATA_UASTC_RGBA: [[([u8; 16], [u32; 16]); 32]; 19] = [
     [   // 0
         ([0xB1, 0x1B, 0x7F, 0x16, 0xD0, 0xA9, 0x98, 0xB9, 0x4B, 0x50, 0x9E, 0x57, 0xB8, 0x9C, 0x73, 0xAB], [0xFF415026, 0xFF39491E, 0xFF1A2A00, 0xFF415026, 0xFF8A976D, 0xFF627146, 0xFF505E34, 0xFF415026, 0xFF5B693F, 0xFF717F55, 0xFF7B885E, 0xFF627146, 0xFF304015, 0xFF505E34, 0xFF717F55, 0xFF6A784E]),
         ([0xF1, 0x1A, 0xB8, 0xD1, 0x06, 0x6A, 0x6A, 0x67, 0x1E, 0xA1, 0x28, 0x70, 0x45, 0xC8, 0xA5, 0xBF], [0xFF58663B, 0xFF849066, 0xFF849066, 0xFF3F4E23, 0xFF4D5B31, 0xFF7D895F, 0xFF8B976D, 0xFF58663B, 0xFF667349, 0xFF6D7A50, 0xFF4D5B31, 0xFF2F3E14, 0xFF667349, 0xFF3F4E23, 0xFF1A2A00, 0xFF38471D]),
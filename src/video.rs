@@ -0,0 +1,103 @@
+use crate::{
+    BasisFile,
+    Error,
+    Image,
+    Result,
+    TextureType,
+    TranscoderTextureFormat,
+    etc1s::FrameState,
+};
+
+/// Sequential decoder of the frames of a video .basis file.
+///
+/// ETC1S P-frames reuse blocks of the previous frame, so frames have to be
+/// decoded in order. The decoder keeps the block indices of the last frame
+/// between calls, seeking decodes forward from the nearest preceding I-frame.
+pub struct VideoDecoder<'a> {
+    file: BasisFile<'a>,
+    level: u32,
+    next_frame: u32,
+    frame_state: FrameState,
+}
+
+impl<'a> VideoDecoder<'a> {
+    /// Creates a decoder of the given mip level of all frames
+    pub fn new(file: BasisFile<'a>, level: u32) -> Result<Self> {
+        if file.texture_type() != TextureType::VideoFrames {
+            return Err(Error::UnsupportedTextureType(file.texture_type()));
+        }
+        Ok(Self {
+            file,
+            level,
+            next_frame: 0,
+            frame_state: FrameState::default(),
+        })
+    }
+
+    pub fn file(&self) -> &BasisFile<'a> {
+        &self.file
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.file.image_count()
+    }
+
+    /// Duration of a single frame in microseconds
+    pub fn us_per_frame(&self) -> u32 {
+        self.file.us_per_frame()
+    }
+
+    /// Index of the frame returned by the next call to `decode_next_frame`
+    pub fn next_frame(&self) -> u32 {
+        self.next_frame
+    }
+
+    /// Decodes the next frame, returns `None` after the last frame
+    pub fn decode_next_frame(&mut self, format: TranscoderTextureFormat) -> Result<Option<Image<u8>>> {
+        if self.next_frame >= self.frame_count() {
+            return Ok(None);
+        }
+        match self.file.transcode_frame(self.next_frame, self.level, format, &mut self.frame_state) {
+            Ok(image) => {
+                self.next_frame += 1;
+                Ok(Some(image))
+            }
+            Err(e) => {
+                // The state is only partially updated, only an I-frame can be decoded after this
+                self.frame_state = FrameState::default();
+                Err(e)
+            }
+        }
+    }
+
+    /// Moves to the given frame, so it's returned by the next call to `decode_next_frame`.
+    ///
+    /// Frames between the nearest I-frame and the target frame are decoded to
+    /// restore the state, unless the decoder is already in between them.
+    pub fn seek(&mut self, frame: u32) -> Result<()> {
+        let frame_count = self.frame_count();
+        if frame > frame_count {
+            return Err(Error::ImageNotFound { image: frame, level: self.level });
+        }
+        // After a failed decode the state is empty, so the current frame has to be rebuilt as well
+        let state_valid = frame == self.next_frame && !self.frame_state.is_empty();
+        if state_valid || frame == frame_count || !self.file.has_inter_frames() {
+            self.next_frame = frame;
+            return Ok(());
+        }
+
+        let iframe = self.file.find_iframe(frame, self.level)?;
+        if self.frame_state.is_empty() || self.next_frame <= iframe || self.next_frame > frame {
+            self.frame_state = FrameState::default();
+            self.next_frame = iframe;
+        }
+        while self.next_frame < frame {
+            if let Err(e) = self.file.skip_frame(self.next_frame, self.level, &mut self.frame_state) {
+                self.frame_state = FrameState::default();
+                return Err(e);
+            }
+            self.next_frame += 1;
+        }
+        Ok(())
+    }
+}
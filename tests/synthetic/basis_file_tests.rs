@@ -0,0 +1,446 @@
+use crate::builder::*;
+use crate::common::*;
+use crate::decoders::*;
+
+use basisu::{
+    BasisFile,
+    Channel,
+    ChecksumPolicy,
+    CubeFace,
+    Error,
+    TextureType,
+    TranscoderTextureFormat,
+};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 128];
+
+fn mipmapped_uastc_file() -> Vec<u8> {
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_2D, 2);
+    builder.slices = vec![
+        uastc_solid_slice(0, 0, 10, 6, RED),
+        uastc_solid_slice(0, 1, 5, 3, GREEN),
+        uastc_solid_slice(0, 2, 2, 1, BLUE),
+        uastc_solid_slice(1, 0, 4, 4, BLUE),
+    ];
+    builder.build()
+}
+
+#[test]
+fn test_basis_file_levels() {
+    let bytes = mipmapped_uastc_file();
+    let file = BasisFile::new(&bytes).unwrap();
+
+    assert_eq!(file.image_count(), 2);
+    assert_eq!(file.level_count(0), 3);
+    assert_eq!(file.level_count(1), 1);
+    assert_eq!(file.level_count(2), 0);
+
+    let info = file.image_level_info(0, 1).unwrap();
+    assert_eq!((info.orig_width, info.orig_height), (5, 3));
+    assert_eq!((info.width, info.height), (8, 4));
+    assert_eq!((info.num_blocks_x, info.num_blocks_y), (2, 1));
+    assert_eq!(info.first_slice_index, 1);
+    assert!(!info.has_alpha);
+
+    assert!(file.image_level_info(0, 2).unwrap().has_alpha);
+    assert!(file.image_level_info(1, 1).is_none());
+}
+
+#[test]
+fn test_basis_file_transcode_level() {
+    let bytes = mipmapped_uastc_file();
+    let file = BasisFile::new(&bytes).unwrap();
+
+    for &(image, level, color) in &[(0, 0, RED), (0, 1, GREEN), (0, 2, BLUE), (1, 0, BLUE)] {
+        let decoded = file.transcode(image, level, TranscoderTextureFormat::Rgba32).unwrap();
+        let info = file.image_level_info(image, level).unwrap();
+        assert_eq!((decoded.w, decoded.h), (info.orig_width, info.orig_height));
+        for row in rgba_rows(&decoded) {
+            for pixel in row.chunks_exact(4) {
+                assert_eq!(pixel, &color);
+            }
+        }
+    }
+
+    let uastc = file.transcode(0, 1, TranscoderTextureFormat::Uastc4x4).unwrap();
+    assert_eq!(uastc.data.len(), 2 * 16);
+    assert_eq!(&uastc.data[0..16], &uastc_solid_block(GREEN));
+
+    let bc1 = file.transcode(0, 1, TranscoderTextureFormat::Bc1Rgb).unwrap();
+    assert_eq!(bc1.data.len(), 2 * 8);
+    for block in bc1.data.chunks_exact(8) {
+        assert_eq!(decode_bc1_block(block), [GREEN; 16]);
+    }
+
+    let atc = file.transcode(0, 1, TranscoderTextureFormat::AtcRgb).unwrap();
+    assert_eq!(atc.data.len(), 2 * 8);
+    for block in atc.data.chunks_exact(8) {
+        assert_eq!(decode_atc_block(block), [GREEN; 16]);
+    }
+
+    for level in 0..3 {
+        let rgba = file.transcode(0, level, TranscoderTextureFormat::Rgba32).unwrap();
+
+        let bc7 = file.transcode(0, level, TranscoderTextureFormat::Bc7Rgba).unwrap();
+        let decoded = basisu::decode_bc7_image(&bc7);
+        assert_eq!((decoded.w, decoded.h, decoded.stride), (rgba.w, rgba.h, rgba.stride));
+        assert_slices_eq(&decoded.data, &rgba.data);
+
+        let astc = file.transcode(0, level, TranscoderTextureFormat::Astc4x4Rgba).unwrap();
+        let decoded = basisu::decode_astc_image(&astc, 4, 4, false).unwrap();
+        assert_eq!((decoded.w, decoded.h, decoded.stride), (rgba.w, rgba.h, rgba.stride));
+        assert_slices_eq(&decoded.data, &rgba.data);
+    }
+
+    let bc3 = file.transcode(0, 2, TranscoderTextureFormat::Bc3Rgba).unwrap();
+    assert_eq!(bc3.data.len(), 16);
+    assert_eq!(decode_bc3_block(&bc3.data), [BLUE; 16]);
+
+    let bc5 = file.transcode(0, 2, TranscoderTextureFormat::Bc5Rg { channels: [Channel::Blue, Channel::Alpha] }).unwrap();
+    assert_eq!(bc5.data.len(), 16);
+    assert_eq!(decode_bc4_block(&bc5.data[0..8]), [BLUE[2]; 16]);
+    assert_eq!(decode_bc4_block(&bc5.data[8..16]), [BLUE[3]; 16]);
+
+    // PVRTC1 needs square power of two dimensions of at least 2x2 blocks
+    for &(image, level, width, height) in &[(0, 0, 12, 8), (0, 1, 8, 4), (1, 0, 4, 4)] {
+        assert!(matches!(
+            file.transcode(image, level, TranscoderTextureFormat::Pvrtc1Rgb),
+            Err(Error::UnsupportedDimensions { width: w, height: h }) if (w, h) == (width, height)
+        ));
+    }
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_2D, 1);
+    builder.slices = vec![uastc_solid_slice(0, 0, 7, 8, GREEN)];
+    let bytes = builder.build();
+    let pvrtc = BasisFile::new(&bytes).unwrap().transcode(0, 0, TranscoderTextureFormat::Pvrtc1Rgba).unwrap();
+    assert_eq!(pvrtc.data.len(), 4 * 8);
+    let decoded = decode_pvrtc1_image(&pvrtc);
+    assert!(decoded.data.chunks_exact(4).all(|pixel| pixel == GREEN));
+
+    let packed_pixels = |format| {
+        let image = file.transcode(0, 2, format).unwrap();
+        assert_eq!(image.data.len(), 16 * 2);
+        image.data.chunks_exact(2).map(|p| u16::from_le_bytes([p[0], p[1]])).collect::<Vec<_>>()
+    };
+    assert_eq!(packed_pixels(TranscoderTextureFormat::Rgb565 { dither: false }), [0x001F; 16]);
+    assert_eq!(packed_pixels(TranscoderTextureFormat::Bgr565 { dither: false }), [0xF800; 16]);
+    assert_eq!(packed_pixels(TranscoderTextureFormat::Rgba4444 { dither: false }), [0x00F8; 16]);
+    assert_eq!(packed_pixels(TranscoderTextureFormat::Rgb565 { dither: true }), [0x001F; 16]);
+
+    // Alpha of 128 is between 4-bit levels 7 and 8, dithering alternates them to keep the average
+    let alphas: Vec<u16> = packed_pixels(TranscoderTextureFormat::Rgba4444 { dither: true }).iter()
+        .map(|&pixel| {
+            assert_eq!(pixel & 0xFFF0, 0x00F0);
+            pixel & 0xF
+        })
+        .collect();
+    assert!(alphas.iter().all(|&alpha| alpha == 7 || alpha == 8), "{:?}", alphas);
+    let mean = alphas.iter().sum::<u16>() as f64 * 17.0 / 16.0;
+    assert!((mean - 128.0).abs() <= 2.0, "mean alpha {}", mean);
+
+    assert!(matches!(
+        file.transcode(0, 3, TranscoderTextureFormat::Rgba32),
+        Err(Error::ImageNotFound { image: 0, level: 3 })
+    ));
+}
+
+#[test]
+fn test_basis_file_rejects_corrupt_data() {
+    let mut bytes = mipmapped_uastc_file();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    assert!(matches!(BasisFile::new(&bytes), Err(Error::DataCrcMismatch)));
+
+    let mut bytes = mipmapped_uastc_file();
+    bytes[20] ^= 1;
+    assert!(matches!(BasisFile::new(&bytes), Err(Error::HeaderCrcMismatch)));
+
+    assert!(matches!(BasisFile::new(b"not a basis file"), Err(Error::BadSignature)));
+}
+
+#[test]
+fn test_checksum_policy() {
+    // Damage the last slice, image 1 level 0
+    let mut bytes = mipmapped_uastc_file();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+
+    let file = BasisFile::with_checksum_policy(&bytes, ChecksumPolicy::VerifySlices).unwrap();
+    file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+    file.transcode(0, 2, TranscoderTextureFormat::Rgba32).unwrap();
+    assert!(matches!(
+        file.transcode(1, 0, TranscoderTextureFormat::Rgba32),
+        Err(Error::SliceCrcMismatch { slice: 3 })
+    ));
+    assert!(matches!(file.validate(), Err(Error::SliceCrcMismatch { slice: 3 })));
+
+    let file = BasisFile::with_checksum_policy(&bytes, ChecksumPolicy::Skip).unwrap();
+    file.transcode(1, 0, TranscoderTextureFormat::Rgba32).unwrap();
+
+    // Header checksums are always verified
+    let mut bytes = mipmapped_uastc_file();
+    bytes[20] ^= 1;
+    assert!(matches!(
+        BasisFile::with_checksum_policy(&bytes, ChecksumPolicy::Skip),
+        Err(Error::HeaderCrcMismatch)
+    ));
+}
+
+#[test]
+fn test_transcode_all_levels() {
+    let bytes = mipmapped_uastc_file();
+    let images = basisu::transcode(&bytes, TranscoderTextureFormat::Uastc4x4).unwrap();
+
+    let sizes: Vec<Vec<_>> = images.iter()
+        .map(|levels| levels.iter().map(|level| (level.w, level.h)).collect())
+        .collect();
+    assert_eq!(sizes, vec![vec![(10, 6), (5, 3), (2, 1)], vec![(4, 4)]]);
+}
+
+fn etc1s_codebooks() -> Etc1sCodebooks {
+    Etc1sCodebooks {
+        endpoints: vec![([31, 31, 31], 0), ([0, 0, 0], 0), ([0, 31, 0], 0)],
+        selectors: vec![[0x00; 4], [0xE4; 4]],
+    }
+}
+
+const ENDPOINT_WHITE: u16 = 0;
+const ENDPOINT_BLACK: u16 = 1;
+const ENDPOINT_GREEN: u16 = 2;
+
+fn mipmapped_etc1s_alpha_file(edit_slices: impl FnOnce(&mut Vec<SliceData>)) -> Vec<u8> {
+    let codebooks = etc1s_codebooks();
+    let alpha_slice = |level, width, height, endpoint| SliceData {
+        flags: SLICE_FLAG_HAS_ALPHA,
+        ..etc1s_solid_slice(&codebooks, 0, level, width, height, endpoint, 1)
+    };
+    let mut slices = vec![
+        etc1s_solid_slice(&codebooks, 0, 0, 8, 6, ENDPOINT_GREEN, 1),
+        alpha_slice(0, 8, 6, ENDPOINT_WHITE),
+        etc1s_solid_slice(&codebooks, 0, 1, 4, 3, ENDPOINT_GREEN, 0),
+        alpha_slice(1, 4, 3, ENDPOINT_BLACK),
+    ];
+    edit_slices(&mut slices);
+
+    let mut builder = BasisBuilder::new(TEX_FORMAT_ETC1S, TEX_TYPE_2D, 1);
+    builder.flags = HEADER_FLAG_ETC1S | HEADER_FLAG_HAS_ALPHA_SLICES;
+    builder.etc1s_codebooks = Some(codebooks);
+    builder.slices = slices;
+    builder.build()
+}
+
+#[test]
+fn test_etc1s_alpha_mip_chain() {
+    let bytes = mipmapped_etc1s_alpha_file(|_| {});
+    let file = BasisFile::new(&bytes).unwrap();
+
+    assert!(file.has_alpha());
+    assert_eq!(file.image_count(), 1);
+    assert_eq!(file.level_count(0), 2);
+
+    let levels = file.image_levels(0);
+    let sizes: Vec<_> = levels.iter()
+        .map(|info| (info.level_index, info.orig_width, info.orig_height, info.first_slice_index))
+        .collect();
+    assert_eq!(sizes, vec![(0, 8, 6, 0), (1, 4, 3, 2)]);
+
+    let decoded = file.transcode_image(0, TranscoderTextureFormat::Rgba32).unwrap();
+    assert_eq!(decoded.len(), 2);
+    for (level, expect_opaque) in decoded.iter().zip(&[true, false]) {
+        for row in rgba_rows(level) {
+            for pixel in row.chunks_exact(4) {
+                assert!(pixel[0] <= 8 && pixel[1] >= 247 && pixel[2] <= 8, "{:?}", pixel);
+                if *expect_opaque {
+                    assert!(pixel[3] >= 247, "{:?}", pixel);
+                } else {
+                    assert!(pixel[3] <= 8, "{:?}", pixel);
+                }
+            }
+        }
+    }
+    assert_eq!((decoded[1].w, decoded[1].h), (4, 3));
+
+    // Level 0 has 2x2 blocks, the smallest PVRTC1 texture, level 1 a single block
+    let pvrtc = file.transcode(0, 0, TranscoderTextureFormat::Pvrtc1Rgba).unwrap();
+    assert_eq!(pvrtc.data.len(), 4 * 8);
+    assert!(matches!(
+        file.transcode(0, 1, TranscoderTextureFormat::Pvrtc1Rgba),
+        Err(Error::UnsupportedDimensions { width: 4, height: 4 })
+    ));
+
+    assert!(matches!(
+        file.transcode_image(1, TranscoderTextureFormat::Rgba32),
+        Err(Error::ImageNotFound { image: 1, .. })
+    ));
+}
+
+#[test]
+fn test_max_block_count() {
+    // ETC1S data isn't sized by the block count, so huge dimensions fit in a tiny file
+    let bytes = mipmapped_etc1s_alpha_file(|slices| {
+        slices[0].orig_width = u16::MAX;
+        slices[0].orig_height = u16::MAX;
+    });
+    assert!(matches!(
+        BasisFile::new(&bytes),
+        Err(Error::UnsupportedDimensions { width: 65536, height: 65536 })
+    ));
+
+    // Level 0 is 2x2 blocks
+    let bytes = mipmapped_etc1s_alpha_file(|_| {});
+    assert!(BasisFile::with_max_block_count(&bytes, 4).is_ok());
+    assert!(matches!(
+        BasisFile::with_max_block_count(&bytes, 3),
+        Err(Error::UnsupportedDimensions { width: 8, height: 8 })
+    ));
+}
+
+#[test]
+fn test_invalid_level_slices() {
+    let is_invalid_slice = |bytes: &[u8]| matches!(BasisFile::new(bytes), Err(Error::InvalidSlice(_)));
+
+    // Alpha slice paired with the wrong level
+    assert!(is_invalid_slice(&mipmapped_etc1s_alpha_file(|slices| slices[1].level_index = 1)));
+    // Alpha slice without the alpha flag
+    assert!(is_invalid_slice(&mipmapped_etc1s_alpha_file(|slices| slices[3].flags = 0)));
+    // Alpha slice with different dimensions
+    assert!(is_invalid_slice(&mipmapped_etc1s_alpha_file(|slices| slices[3].orig_width = 2)));
+
+    let uastc_file = |slices| {
+        let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_2D, 1);
+        builder.slices = slices;
+        builder.build()
+    };
+    // Level 1 is missing
+    assert!(is_invalid_slice(&uastc_file(vec![
+        uastc_solid_slice(0, 0, 8, 8, RED),
+        uastc_solid_slice(0, 2, 2, 2, RED),
+    ])));
+    // Level 0 is there twice
+    assert!(is_invalid_slice(&uastc_file(vec![
+        uastc_solid_slice(0, 0, 8, 8, RED),
+        uastc_solid_slice(0, 0, 8, 8, RED),
+    ])));
+    // Image index is out of range
+    assert!(is_invalid_slice(&uastc_file(vec![
+        uastc_solid_slice(1, 0, 8, 8, RED),
+    ])));
+}
+
+/// Cubemap array where face `i` is filled with gray level `i * 10`, with 2 levels per face
+fn cubemap_array_file(cubemap_count: u32) -> Vec<u8> {
+    let image_count = cubemap_count * 6;
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_CUBEMAP_ARRAY, image_count);
+    for image in 0..image_count {
+        let gray = image as u8 * 10;
+        builder.slices.push(uastc_solid_slice(image, 0, 8, 8, [gray, gray, gray, 255]));
+        builder.slices.push(uastc_solid_slice(image, 1, 4, 4, [gray, gray, gray, 255]));
+    }
+    builder.build()
+}
+
+#[test]
+fn test_cubemap_faces() {
+    let bytes = cubemap_array_file(2);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    assert_eq!(file.texture_type(), TextureType::CubemapArray);
+    assert_eq!(file.face_count(), 6);
+    assert_eq!(file.cubemap_count(), 2);
+    assert_eq!(file.image_face(8), Some(CubeFace::PositiveY));
+    assert_eq!(file.image_face(12), None);
+    assert_eq!(file.image_level_info(11, 1).unwrap().face_index, 5);
+
+    let faces = file.transcode_cubemap(1, 1, TranscoderTextureFormat::Rgba32).unwrap();
+    assert_eq!(faces.len(), 6);
+    for (face, decoded) in CubeFace::ALL.iter().zip(&faces) {
+        let gray = (6 + *face as u8) * 10;
+        assert_eq!((decoded.w, decoded.h), (4, 4));
+        assert_eq!(&decoded.data[0..4], &[gray, gray, gray, 255]);
+    }
+
+    assert!(matches!(
+        file.transcode_cubemap(2, 0, TranscoderTextureFormat::Rgba32),
+        Err(Error::ImageNotFound { image: 12, level: 0 })
+    ));
+    assert!(matches!(
+        file.transcode_cubemap(u32::MAX, 0, TranscoderTextureFormat::Rgba32),
+        Err(Error::ImageNotFound { image: u32::MAX, level: 0 })
+    ));
+
+    let bytes = mipmapped_uastc_file();
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_eq!(file.face_count(), 1);
+    assert_eq!(file.cubemap_count(), 0);
+    assert!(matches!(
+        file.transcode_cubemap(0, 0, TranscoderTextureFormat::Rgba32),
+        Err(Error::UnsupportedTextureType(TextureType::Type2D))
+    ));
+}
+
+#[test]
+fn test_invalid_cubemap() {
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_CUBEMAP_ARRAY, 7);
+    builder.slices = (0..7).map(|image| uastc_solid_slice(image, 0, 4, 4, RED)).collect();
+    assert!(matches!(BasisFile::new(&builder.build()), Err(Error::InvalidHeader(_))));
+
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_CUBEMAP_ARRAY, 6);
+    builder.slices = (0..6).map(|image| uastc_solid_slice(image, 0, 4, 4, RED)).collect();
+    builder.slices[3] = uastc_solid_slice(3, 0, 8, 8, RED);
+    assert!(matches!(BasisFile::new(&builder.build()), Err(Error::InvalidSlice(_))));
+}
+
+fn layered_file(tex_type: u8, layer_colors: &[[u8; 4]]) -> BasisBuilder {
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, tex_type, layer_colors.len() as u32);
+    for (layer, &color) in layer_colors.iter().enumerate() {
+        builder.slices.push(uastc_solid_slice(layer as u32, 0, 8, 6, color));
+        builder.slices.push(uastc_solid_slice(layer as u32, 1, 4, 3, color));
+    }
+    builder
+}
+
+#[test]
+fn test_texture_array_layers() {
+    for &(tex_type, texture_type) in &[(TEX_TYPE_2D_ARRAY, TextureType::Type2DArray), (TEX_TYPE_VOLUME, TextureType::Volume)] {
+        let bytes = layered_file(tex_type, &[RED, GREEN, BLUE]).build();
+        let file = BasisFile::new(&bytes).unwrap();
+        assert_eq!(file.texture_type(), texture_type);
+        assert_eq!(file.layer_count(), 3);
+
+        let layers = file.transcode_layers(1, TranscoderTextureFormat::Rgba32).unwrap();
+        assert_eq!((layers.w, layers.h, layers.layers), (4, 3, 3));
+        assert_eq!(layers.layer_stride, 4 * 4 * 4);
+        assert_eq!(layers.data.len(), 3 * layers.layer_stride as usize);
+        for (layer, color) in [RED, GREEN, BLUE].iter().enumerate() {
+            for pixel in layers.layer(layer as u32).chunks_exact(4) {
+                assert_eq!(pixel, color);
+            }
+        }
+
+        let uastc = file.transcode_layers(0, TranscoderTextureFormat::Uastc4x4).unwrap();
+        assert_eq!(uastc.layer_stride, 2 * 2 * 16);
+        assert_eq!(&uastc.layer(2)[0..16], &uastc_solid_block(BLUE));
+    }
+
+    let bytes = mipmapped_uastc_file();
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_eq!(file.layer_count(), 1);
+    assert!(matches!(
+        file.transcode_layers(0, TranscoderTextureFormat::Rgba32),
+        Err(Error::UnsupportedTextureType(TextureType::Type2D))
+    ));
+}
+
+#[test]
+fn test_invalid_texture_array() {
+    // Layer with different dimensions
+    let mut builder = layered_file(TEX_TYPE_2D_ARRAY, &[RED, GREEN]);
+    builder.slices[2] = uastc_solid_slice(1, 0, 8, 8, GREEN);
+    assert!(matches!(BasisFile::new(&builder.build()), Err(Error::InvalidSlice(_))));
+
+    // Layer with fewer levels
+    let mut builder = layered_file(TEX_TYPE_VOLUME, &[RED, GREEN]);
+    builder.slices.pop();
+    assert!(matches!(BasisFile::new(&builder.build()), Err(Error::InvalidSlice(_))));
+}
@@ -0,0 +1,350 @@
+pub const TEX_FORMAT_ETC1S: u8 = 0;
+pub const TEX_FORMAT_UASTC4X4: u8 = 1;
+
+pub const TEX_TYPE_2D: u8 = 0;
+pub const TEX_TYPE_2D_ARRAY: u8 = 1;
+pub const TEX_TYPE_CUBEMAP_ARRAY: u8 = 2;
+pub const TEX_TYPE_VIDEO_FRAMES: u8 = 3;
+pub const TEX_TYPE_VOLUME: u8 = 4;
+
+pub const HEADER_FLAG_ETC1S: u16 = 1;
+pub const HEADER_FLAG_HAS_ALPHA_SLICES: u16 = 4;
+
+pub const SLICE_FLAG_HAS_ALPHA: u8 = 1;
+pub const SLICE_FLAG_FRAME_IS_I_FRAME: u8 = 2;
+
+/// Slice of a synthetic .basis file
+#[derive(Clone)]
+pub struct SliceData {
+    pub image_index: u32,
+    pub level_index: u8,
+    pub flags: u8,
+    pub orig_width: u16,
+    pub orig_height: u16,
+    pub data: Vec<u8>,
+}
+
+/// Writer of synthetic .basis files, used when the texture corpus is not needed
+pub struct BasisBuilder {
+    pub tex_format: u8,
+    pub tex_type: u8,
+    pub flags: u16,
+    pub total_images: u32,
+    pub us_per_frame: u32,
+    pub etc1s_codebooks: Option<Etc1sCodebooks>,
+    pub slices: Vec<SliceData>,
+}
+
+impl BasisBuilder {
+    pub fn new(tex_format: u8, tex_type: u8, total_images: u32) -> Self {
+        Self {
+            tex_format,
+            tex_type,
+            flags: 0,
+            total_images,
+            us_per_frame: 0,
+            etc1s_codebooks: None,
+            slices: Vec::new(),
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        const HEADER_SIZE: usize = 77;
+        const SLICE_DESC_SIZE: usize = 23;
+
+        fn put(out: &mut Vec<u8>, v: u32, bytes: usize) {
+            out.extend_from_slice(&v.to_le_bytes()[..bytes]);
+        }
+        fn zeros(out: &mut Vec<u8>, bytes: usize) {
+            out.resize(out.len() + bytes, 0);
+        }
+
+        let slice_desc_ofs = HEADER_SIZE;
+        let codebooks_ofs = slice_desc_ofs + self.slices.len() * SLICE_DESC_SIZE;
+
+        let (endpoint_cb, selector_cb, tables) = match &self.etc1s_codebooks {
+            Some(codebooks) => codebooks.write(),
+            None => (Vec::new(), Vec::new(), Vec::new()),
+        };
+        let (num_endpoints, num_selectors) = match &self.etc1s_codebooks {
+            Some(codebooks) => (codebooks.endpoints.len() as u32, codebooks.selectors.len() as u32),
+            None => (0, 0),
+        };
+        let endpoint_cb_ofs = codebooks_ofs;
+        let selector_cb_ofs = endpoint_cb_ofs + endpoint_cb.len();
+        let tables_ofs = selector_cb_ofs + selector_cb.len();
+
+        let mut data_ofs = tables_ofs + tables.len();
+
+        let mut slice_descs = Vec::new();
+        for slice in &self.slices {
+            put(&mut slice_descs, slice.image_index, 3);
+            put(&mut slice_descs, slice.level_index as u32, 1);
+            put(&mut slice_descs, slice.flags as u32, 1);
+            put(&mut slice_descs, slice.orig_width as u32, 2);
+            put(&mut slice_descs, slice.orig_height as u32, 2);
+            put(&mut slice_descs, (slice.orig_width as u32).div_ceil(4), 2);
+            put(&mut slice_descs, (slice.orig_height as u32).div_ceil(4), 2);
+            put(&mut slice_descs, data_ofs as u32, 4);
+            put(&mut slice_descs, slice.data.len() as u32, 4);
+            put(&mut slice_descs, crc16(&slice.data) as u32, 2);
+            data_ofs += slice.data.len();
+        }
+
+        let mut data = slice_descs;
+        data.extend_from_slice(&endpoint_cb);
+        data.extend_from_slice(&selector_cb);
+        data.extend_from_slice(&tables);
+        for slice in &self.slices {
+            data.extend_from_slice(&slice.data);
+        }
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        put(&mut header, 0x4273, 2);                    // sig
+        put(&mut header, 0x13, 2);                      // ver
+        put(&mut header, HEADER_SIZE as u32, 2);        // header_size
+        put(&mut header, 0, 2);                         // header_crc16, filled in below
+        put(&mut header, data.len() as u32, 4);         // data_size
+        put(&mut header, crc16(&data) as u32, 2);       // data_crc16
+        put(&mut header, self.slices.len() as u32, 3);  // total_slices
+        put(&mut header, self.total_images, 3);         // total_images
+        put(&mut header, self.tex_format as u32, 1);    // tex_format
+        put(&mut header, self.flags as u32, 2);         // flags
+        put(&mut header, self.tex_type as u32, 1);      // tex_type
+        put(&mut header, self.us_per_frame, 3);         // us_per_frame
+        zeros(&mut header, 4 + 4 + 4);                 // reserved, userdata0, userdata1
+        put(&mut header, num_endpoints, 2);             // total_endpoints
+        put(&mut header, endpoint_cb_ofs as u32, 4);    // endpoint_cb_file_ofs
+        put(&mut header, endpoint_cb.len() as u32, 3);  // endpoint_cb_file_size
+        put(&mut header, num_selectors, 2);             // total_selectors
+        put(&mut header, selector_cb_ofs as u32, 4);    // selector_cb_file_ofs
+        put(&mut header, selector_cb.len() as u32, 3);  // selector_cb_file_size
+        put(&mut header, tables_ofs as u32, 4);         // tables_file_ofs
+        put(&mut header, tables.len() as u32, 4);       // tables_file_size
+        put(&mut header, slice_desc_ofs as u32, 4);     // slice_desc_file_ofs
+        zeros(&mut header, 4 + 4);                     // extended_file_ofs, extended_file_size
+        assert_eq!(header.len(), HEADER_SIZE);
+
+        let header_crc16 = crc16(&header[8..]);
+        header[6..8].copy_from_slice(&header_crc16.to_le_bytes());
+
+        header.extend_from_slice(&data);
+        header
+    }
+}
+
+/// UASTC mode 8 (void-extent) block filled with a single color
+pub fn uastc_solid_block(rgba: [u8; 4]) -> [u8; 16] {
+    const MODE_8_CODE: u128 = 0b10111;
+    let color = u32::from_le_bytes(rgba) as u128;
+    (MODE_8_CODE | color << 5).to_le_bytes()
+}
+
+/// UASTC slice filled with a single color
+pub fn uastc_solid_slice(image_index: u32, level_index: u8, width: u16, height: u16, rgba: [u8; 4]) -> SliceData {
+    let block_count = (width as usize).div_ceil(4) * (height as usize).div_ceil(4);
+    let block = uastc_solid_block(rgba);
+    SliceData {
+        image_index,
+        level_index,
+        flags: if rgba[3] != 255 { SLICE_FLAG_HAS_ALPHA } else { 0 },
+        orig_width: width,
+        orig_height: height,
+        data: block.iter().copied().cycle().take(16 * block_count).collect(),
+    }
+}
+
+/// ETC1S codebooks of a synthetic .basis file.
+///
+/// Everything is written with fixed length Huffman codes and a raw selector
+/// codebook, slices mostly use the delta endpoint prediction and always use
+/// direct selector indices. This is far from what the encoder produces, but it is valid and
+/// simple enough to write by hand.
+pub struct Etc1sCodebooks {
+    /// Endpoints as 5-bit RGB colors and a 3-bit intensity table index
+    pub endpoints: Vec<([u8; 3], u8)>,
+    /// Selectors as 4 rows of 2-bit selectors, lowest bits are the leftmost pixel
+    pub selectors: Vec<[u8; 4]>,
+}
+
+impl Etc1sCodebooks {
+    fn write(&self) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut endpoint_cb = BitWriter::default();
+        for _ in 0..3 {
+            write_fixed_huffman_table(&mut endpoint_cb, 32);    // color5 deltas
+        }
+        write_fixed_huffman_table(&mut endpoint_cb, 8);         // inten deltas
+        endpoint_cb.write(0, 1);                                // grayscale
+        let mut prev_color5 = [16, 16, 16];
+        let mut prev_inten = 0;
+        for &(color5, inten) in &self.endpoints {
+            endpoint_cb.write_code(8, (inten.wrapping_sub(prev_inten) & 7) as u32);
+            prev_inten = inten;
+            for c in 0..3 {
+                endpoint_cb.write_code(32, (color5[c].wrapping_sub(prev_color5[c]) & 31) as u32);
+                prev_color5[c] = color5[c];
+            }
+        }
+
+        let mut selector_cb = BitWriter::default();
+        selector_cb.write(0b100, 3);                            // global: 0, hybrid: 0, raw: 1
+        for selector in &self.selectors {
+            for &row in selector {
+                selector_cb.write(row as u32, 8);
+            }
+        }
+
+        let mut tables = BitWriter::default();
+        write_fixed_huffman_table(&mut tables, 257);            // endpoint_pred_model
+        write_fixed_huffman_table(&mut tables, self.endpoints.len());
+        write_fixed_huffman_table(&mut tables, self.selectors.len());
+        write_fixed_huffman_table(&mut tables, 1);              // selector_history_buf_rle_model
+        tables.write(0, 13);                                    // selector_history_buffer_size
+
+        (endpoint_cb.bytes, selector_cb.bytes, tables.bytes)
+    }
+
+    /// Encodes a slice, `blocks` holds `(endpoint_index, selector_index)` in raster order
+    pub fn slice_data(&self, num_blocks_x: usize, num_blocks_y: usize, blocks: &[(u16, u16)]) -> Vec<u8> {
+        self.video_slice_data(num_blocks_x, num_blocks_y, blocks, None)
+    }
+
+    /// Encodes a video frame, blocks matching `prev_frame` reuse the previous frame's indices
+    pub fn video_slice_data(&self, num_blocks_x: usize, num_blocks_y: usize, blocks: &[(u16, u16)], prev_frame: Option<&[(u16, u16)]>) -> Vec<u8> {
+        let preds: Vec<u32> = match prev_frame {
+            Some(prev_frame) => blocks.iter().zip(prev_frame)
+                .map(|(block, prev)| if block == prev { PRED_PREV_FRAME } else { PRED_DELTA })
+                .collect(),
+            None => vec![PRED_DELTA; blocks.len()],
+        };
+        self.encode_blocks(num_blocks_x, num_blocks_y, blocks, &preds, true)
+    }
+
+    /// Encodes a non-video slice with the given endpoint prediction of each block.
+    ///
+    /// Predicted blocks only store their selector, `blocks` should contain the
+    /// endpoint index the decoder is expected to predict.
+    pub fn slice_data_with_preds(&self, num_blocks_x: usize, num_blocks_y: usize, blocks: &[(u16, u16)], preds: &[u32]) -> Vec<u8> {
+        self.encode_blocks(num_blocks_x, num_blocks_y, blocks, preds, false)
+    }
+
+    fn encode_blocks(&self, num_blocks_x: usize, num_blocks_y: usize, blocks: &[(u16, u16)], preds: &[u32], is_video: bool) -> Vec<u8> {
+        assert_eq!(blocks.len(), num_blocks_x * num_blocks_y);
+        assert_eq!(preds.len(), num_blocks_x * num_blocks_y);
+
+        let pred = |x: usize, y: usize| {
+            if x >= num_blocks_x || y >= num_blocks_y {
+                return PRED_DELTA;
+            }
+            preds[y * num_blocks_x + x]
+        };
+
+        let mut writer = BitWriter::default();
+        let mut prev_endpoint_index = 0;
+        for block_y in 0..num_blocks_y {
+            for block_x in 0..num_blocks_x {
+                if block_x % 2 == 0 && block_y % 2 == 0 {
+                    // Prediction of a 2x2 group of blocks, 2 bits per block
+                    let preds = pred(block_x, block_y)
+                        | pred(block_x + 1, block_y) << 2
+                        | pred(block_x, block_y + 1) << 4
+                        | pred(block_x + 1, block_y + 1) << 6;
+                    writer.write_code(257, preds);
+                }
+                let (endpoint_index, selector_index) = blocks[block_y * num_blocks_x + block_x];
+                let pred = pred(block_x, block_y);
+                if pred == PRED_DELTA {
+                    let num_endpoints = self.endpoints.len() as u32;
+                    let delta = (endpoint_index as u32 + num_endpoints - prev_endpoint_index as u32) % num_endpoints;
+                    writer.write_code(self.endpoints.len(), delta);
+                }
+                // Video blocks predicted from the previous frame also reuse its selector
+                if !is_video || pred != PRED_PREV_FRAME {
+                    writer.write_code(self.selectors.len(), selector_index as u32);
+                }
+                prev_endpoint_index = endpoint_index;
+            }
+        }
+        writer.bytes
+    }
+}
+
+pub const PRED_LEFT: u32 = 0;
+pub const PRED_UP: u32 = 1;
+pub const PRED_PREV_FRAME: u32 = 2;
+pub const PRED_DELTA: u32 = 3;
+
+/// ETC1S slice where every block uses the same endpoint and selector
+pub fn etc1s_solid_slice(codebooks: &Etc1sCodebooks, image_index: u32, level_index: u8, width: u16, height: u16, endpoint_index: u16, selector_index: u16) -> SliceData {
+    let num_blocks_x = (width as usize).div_ceil(4);
+    let num_blocks_y = (height as usize).div_ceil(4);
+    let blocks = vec![(endpoint_index, selector_index); num_blocks_x * num_blocks_y];
+    SliceData {
+        image_index,
+        level_index,
+        flags: 0,
+        orig_width: width,
+        orig_height: height,
+        data: codebooks.slice_data(num_blocks_x, num_blocks_y, &blocks),
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_pos: usize,
+}
+
+impl BitWriter {
+    /// Writes `count` bits of `v`, lowest bit first
+    fn write(&mut self, v: u32, count: usize) {
+        for i in 0..count {
+            let byte = self.bit_pos / 8;
+            if byte == self.bytes.len() {
+                self.bytes.push(0);
+            }
+            let bit = ((v >> i) & 1) as u8;
+            self.bytes[byte] |= bit << (self.bit_pos % 8);
+            self.bit_pos += 1;
+        }
+    }
+
+    /// Writes `symbol` using the table written by `write_fixed_huffman_table(total_syms)`
+    fn write_code(&mut self, total_syms: usize, symbol: u32) {
+        // Huffman codes are read starting with their highest bit
+        let size = fixed_code_size(total_syms);
+        self.write(symbol.reverse_bits() >> (32 - size), size);
+    }
+}
+
+fn fixed_code_size(total_syms: usize) -> usize {
+    (total_syms.next_power_of_two().trailing_zeros() as usize).max(1)
+}
+
+/// Writes a Huffman table where all symbols have the same code size
+fn write_fixed_huffman_table(writer: &mut BitWriter, total_syms: usize) {
+    const CODELENGTH_INDICES: [usize; 21] = [17, 18, 19, 20, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15, 16];
+
+    let size = fixed_code_size(total_syms);
+    let position = CODELENGTH_INDICES.iter().position(|&i| i == size).unwrap();
+
+    writer.write(total_syms as u32, 14);
+    writer.write(position as u32 + 1, 5);
+    // The code length table only holds `size`, using a single bit code
+    for i in 0..=position {
+        writer.write((i == position) as u32, 3);
+    }
+    for _ in 0..total_syms {
+        writer.write(0, 1);
+    }
+}
+
+pub fn crc16(r: &[u8]) -> u16 {
+    let mut crc: u16 = !0;
+    for &b in r {
+        let q: u16 = (b as u16) ^ (crc >> 8);
+        let k: u16 = (q >> 4) ^ q;
+        crc = (((crc << 8) ^ k) ^ (k << 5)) ^ (k << 12);
+    }
+    !crc
+}
@@ -0,0 +1,474 @@
+/// Decodes a BC1 block to RGBA pixels in raster order
+pub fn decode_bc1_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let unpack = |c: u16| {
+        let r = (c >> 11) as u32 & 31;
+        let g = (c >> 5) as u32 & 63;
+        let b = c as u32 & 31;
+        [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+    };
+    let (c0, c1) = (unpack(color0), unpack(color1));
+
+    let mut palette = [[0u8, 0, 0, 255]; 4];
+    for ch in 0..3 {
+        palette[0][ch] = c0[ch] as u8;
+        palette[1][ch] = c1[ch] as u8;
+        if color0 > color1 {
+            palette[2][ch] = ((2 * c0[ch] + c1[ch]) / 3) as u8;
+            palette[3][ch] = ((c0[ch] + 2 * c1[ch]) / 3) as u8;
+        } else {
+            palette[2][ch] = ((c0[ch] + c1[ch]) / 2) as u8;
+        }
+    }
+    if color0 <= color1 {
+        palette[3] = [0, 0, 0, 0];
+    }
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = palette[(indices >> (2 * i)) as usize & 3];
+    }
+    pixels
+}
+
+/// Decodes an ATC RGB block to RGBA pixels in raster order
+pub fn decode_atc_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]) as u32;
+    let color1 = u16::from_le_bytes([block[2], block[3]]) as u32;
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let expand5 = |c: u32| (c << 3) | (c >> 2);
+    let expand6 = |c: u32| (c << 2) | (c >> 4);
+    let c0 = [expand5((color0 >> 10) & 31), expand5((color0 >> 5) & 31), expand5(color0 & 31)];
+    let c1 = [expand5(color1 >> 11), expand6((color1 >> 5) & 63), expand5(color1 & 31)];
+
+    let mut palette = [[0u8, 0, 0, 255]; 4];
+    for ch in 0..3 {
+        if color0 & 0x8000 == 0 {
+            palette[0][ch] = c0[ch] as u8;
+            palette[1][ch] = ((2 * c0[ch] + c1[ch]) / 3) as u8;
+            palette[2][ch] = ((c0[ch] + 2 * c1[ch]) / 3) as u8;
+        } else {
+            palette[1][ch] = c0[ch].saturating_sub(c1[ch] / 4) as u8;
+            palette[2][ch] = c0[ch] as u8;
+        }
+        palette[3][ch] = c1[ch] as u8;
+    }
+
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = palette[(indices >> (2 * i)) as usize & 3];
+    }
+    pixels
+}
+
+/// Decodes all blocks of a transcoded image to an RGBA image with the same layout as `Rgba32` output
+pub fn decode_block_image(image: &basisu::Image<u8>, block_size: usize, decode_block: impl Fn(&[u8]) -> [[u8; 4]; 16]) -> basisu::Image<u8> {
+    let num_blocks_x = image.stride as usize / block_size;
+    let num_blocks_y = image.data.len() / image.stride as usize;
+    let stride = num_blocks_x * 4 * 4;
+    let mut data = vec![0u8; stride * num_blocks_y * 4];
+    for (block_id, block) in image.data.chunks_exact(block_size).enumerate() {
+        let (block_x, block_y) = (block_id % num_blocks_x, block_id / num_blocks_x);
+        for (i, pixel) in decode_block(block).iter().enumerate() {
+            let (x, y) = (block_x * 4 + i % 4, block_y * 4 + i / 4);
+            data[y * stride + x * 4..y * stride + x * 4 + 4].copy_from_slice(pixel);
+        }
+    }
+    basisu::Image {
+        w: image.w,
+        h: image.h,
+        stride: stride as u32,
+        y_flipped: image.y_flipped,
+        data,
+    }
+}
+
+/// Decodes a BC4 block to single channel values in raster order
+pub fn decode_bc4_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+    if a0 > a1 {
+        for (i, entry) in (2..).zip(&mut palette[2..8]) {
+            *entry = ((8 - i) * a0 + (i - 1) * a1) / 7;
+        }
+    } else {
+        for (i, entry) in (2..).zip(&mut palette[2..6]) {
+            *entry = ((6 - i) * a0 + (i - 1) * a1) / 5;
+        }
+        palette[7] = 255;
+    }
+
+    let mut indices = [0u8; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+
+    let mut values = [0u8; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[(indices >> (3 * i)) as usize & 7] as u8;
+    }
+    values
+}
+
+/// Decodes a BC3 block to RGBA pixels in raster order
+pub fn decode_bc3_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut pixels = decode_bc1_block(&block[8..16]);
+    // The color block of BC3 always uses 4 colors
+    let color0 = u16::from_le_bytes([block[8], block[9]]);
+    let color1 = u16::from_le_bytes([block[10], block[11]]);
+    assert!(color0 > color1 || block[12..16] == [0; 4], "BC3 color block with 3 colors");
+    for (pixel, alpha) in pixels.iter_mut().zip(decode_bc4_block(&block[0..8]).iter()) {
+        pixel[3] = *alpha;
+    }
+    pixels
+}
+
+/// Decodes a BC7 block to RGBA pixels in raster order, only modes 5 and 6 are supported
+pub fn decode_bc7_block(block: &[u8]) -> [[u8; 4]; 16] {
+    const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+    const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(block);
+    let bits = u128::from_le_bytes(bytes);
+    let mut pos = 0;
+    let mut read = |count: u32| {
+        let v = (bits >> pos) as u32 & ((1u32 << count) - 1);
+        pos += count;
+        v
+    };
+    let interpolate = |e0: u32, e1: u32, w: u32| ((e0 * (64 - w) + e1 * w + 32) >> 6) as u8;
+
+    let mut pixels = [[0u8; 4]; 16];
+    let mode = bits.trailing_zeros();
+    read(mode + 1);
+    match mode {
+        5 => {
+            let rotation = read(2);
+            let mut endpoints = [[0u32; 4]; 2];
+            for c in 0..3 {
+                for e in &mut endpoints {
+                    let q = read(7);
+                    e[c] = (q << 1) | (q >> 6);
+                }
+            }
+            for e in &mut endpoints {
+                e[3] = read(8);
+            }
+            let color_weights: Vec<u32> = (0..16).map(|i| read(if i == 0 { 1 } else { 2 })).collect();
+            let alpha_weights: Vec<u32> = (0..16).map(|i| read(if i == 0 { 1 } else { 2 })).collect();
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                for c in 0..4 {
+                    let w = if c == 3 { alpha_weights[i] } else { color_weights[i] };
+                    pixel[c] = interpolate(endpoints[0][c], endpoints[1][c], WEIGHTS_2[w as usize]);
+                }
+                if rotation != 0 {
+                    pixel.swap(rotation as usize - 1, 3);
+                }
+            }
+        }
+        6 => {
+            let mut endpoints = [[0u32; 4]; 2];
+            for c in 0..4 {
+                for e in &mut endpoints {
+                    e[c] = read(7) << 1;
+                }
+            }
+            for e in &mut endpoints {
+                let p = read(1);
+                for c in e.iter_mut() {
+                    *c |= p;
+                }
+            }
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let w = read(if i == 0 { 3 } else { 4 });
+                for c in 0..4 {
+                    pixel[c] = interpolate(endpoints[0][c], endpoints[1][c], WEIGHTS_4[w as usize]);
+                }
+            }
+        }
+        _ => panic!("BC7 mode {} not supported", mode),
+    }
+    pixels
+}
+
+/// Decodes an ASTC 4x4 block to RGBA pixels in raster order.
+///
+/// Only void-extent blocks and single partition blocks of RGB or RGBA direct endpoints with
+/// 2-bit weights are supported, that is 8-bit endpoints with one plane and 48 endpoint levels with two planes.
+pub fn decode_astc_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(block);
+    let bits = u128::from_le_bytes(bytes);
+    let read = |pos: u32, count: u32| (bits >> pos) as u32 & ((1u32 << count) - 1);
+
+    if read(0, 12) == 0xDFC {
+        let rgba = [0, 1, 2, 3].map(|c| (read(64 + 16 * c, 16) >> 8) as u8);
+        return [rgba; 16];
+    }
+
+    let block_mode = read(0, 11);
+    assert_eq!(block_mode & !0x400, 0x042, "unsupported block mode");
+    assert_eq!(read(11, 2), 0, "unsupported partition count");
+    let dual_plane = block_mode & 0x400 != 0;
+    let cem = read(13, 4);
+    let channel_count = match cem {
+        8 => 3,
+        12 => 4,
+        _ => panic!("unsupported CEM {}", cem),
+    };
+
+    // The endpoint range is the largest one which fits in the remaining bits
+    let mut endpoints = [[0, 0, 0, 255]; 2];
+    if dual_plane {
+        assert_eq!(channel_count, 4);
+        let mut values = [0u32; 8];
+        let mut pos = 17;
+        for group in values.chunks_mut(5) {
+            let mut m = [0u32; 5];
+            let mut t = 0;
+            for (i, &t_bits) in [2, 2, 1, 2, 1].iter().enumerate() {
+                m[i] = read(pos, 4);
+                pos += 4;
+                t |= read(pos, t_bits) << [0, 2, 4, 5, 7][i];
+                pos += t_bits;
+            }
+            let trits = decode_trits(t);
+            for (i, value) in group.iter_mut().enumerate() {
+                *value = unquant_trit_4_bits(trits[i], m[i]);
+            }
+        }
+        for (c, pair) in values.chunks_exact(2).enumerate() {
+            endpoints[0][c] = pair[0];
+            endpoints[1][c] = pair[1];
+        }
+    } else {
+        for (c, pos) in (17..).step_by(16).take(channel_count).enumerate() {
+            endpoints[0][c] = read(pos, 8);
+            endpoints[1][c] = read(pos + 8, 8);
+        }
+    }
+
+    let s0: u32 = endpoints[0][..3].iter().sum();
+    let s1: u32 = endpoints[1][..3].iter().sum();
+    assert!(s0 <= s1, "blue contraction is not supported");
+
+    let rev = bits.reverse_bits();
+    let weight_count = if dual_plane { 32 } else { 16 };
+    let weight = |i: u32| [0, 21, 43, 64][((rev >> (2 * i)) & 0b11) as usize];
+    let ccs = if dual_plane { read(128 - 2 * weight_count - 2, 2) as usize } else { 4 };
+
+    let interpolate = |e0: u32, e1: u32, w: u32| {
+        let (e0, e1) = (e0 << 8 | e0, e1 << 8 | e1);
+        ((e0 * (64 - w) + e1 * w + 32) >> 6 >> 8) as u8
+    };
+    let mut pixels = [[0u8; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        for (c, value) in pixel.iter_mut().enumerate() {
+            let w = if !dual_plane {
+                weight(i as u32)
+            } else if c == ccs {
+                weight(2 * i as u32 + 1)
+            } else {
+                weight(2 * i as u32)
+            };
+            *value = interpolate(endpoints[0][c], endpoints[1][c], w);
+        }
+    }
+    pixels
+}
+
+/// Decodes 5 trits packed in 8 bits
+fn decode_trits(t: u32) -> [u32; 5] {
+    let (t4, t3);
+    let c = if (t >> 2) & 0b111 == 0b111 {
+        t4 = 2;
+        t3 = 2;
+        ((t >> 5) & 0b111) << 2 | (t & 0b11)
+    } else {
+        if (t >> 5) & 0b11 == 0b11 {
+            t4 = 2;
+            t3 = (t >> 7) & 1;
+        } else {
+            t4 = (t >> 7) & 1;
+            t3 = (t >> 5) & 0b11;
+        }
+        t & 0b11111
+    };
+    let (t2, t1, t0);
+    if c & 0b11 == 0b11 {
+        t2 = 2;
+        t1 = (c >> 4) & 1;
+        t0 = ((c >> 2) & 0b10) | ((c >> 2) & (!c >> 3) & 1);
+    } else if (c >> 2) & 0b11 == 0b11 {
+        t2 = 2;
+        t1 = 2;
+        t0 = c & 0b11;
+    } else {
+        t2 = (c >> 4) & 1;
+        t1 = (c >> 2) & 0b11;
+        t0 = (c & 0b10) | (c & (!c >> 1) & 1);
+    }
+    [t0, t1, t2, t3, t4]
+}
+
+/// Unquantizes an endpoint of the range with a trit and 4 bits
+fn unquant_trit_4_bits(trit: u32, m: u32) -> u32 {
+    let a = if m & 1 != 0 { 0x1FF } else { 0 };
+    let dcb = (m >> 1) & 0b111;
+    let b = dcb << 6 | dcb;
+    let t = (trit * 22 + b) ^ a;
+    (a & 0x80) | (t >> 2)
+}
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6,  -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5,  -8, -13, 1, 4, 7, 12],
+    [-2, -4,  -6, -13, 1, 3, 5, 12],
+    [-3, -6,  -8, -12, 2, 5, 7, 11],
+    [-3, -7,  -9, -11, 2, 6, 8, 10],
+    [-4, -7,  -8, -11, 3, 6, 7, 10],
+    [-3, -5,  -8, -11, 2, 4, 7, 10],
+    [-2, -6,  -8, -10, 1, 5, 7,  9],
+    [-2, -5,  -8, -10, 1, 4, 7,  9],
+    [-2, -4,  -8, -10, 1, 3, 7,  9],
+    [-2, -5,  -7, -10, 1, 4, 6,  9],
+    [-3, -4,  -7, -10, 2, 3, 6,  9],
+    [-1, -2,  -3, -10, 0, 1, 2,  9],
+    [-4, -6,  -8,  -9, 3, 5, 7,  8],
+    [-3, -5,  -7,  -9, 2, 4, 6,  8],
+];
+
+/// Returns the modifier table indices of an EAC block in raster order
+fn eac_indices(block: &[u8]) -> [usize; 16] {
+    let mut index_bytes = [0u8; 8];
+    index_bytes[2..].copy_from_slice(&block[2..8]);
+    let bits = u64::from_be_bytes(index_bytes);
+
+    let mut indices = [0; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        // Pixels are stored in columns
+        let id = (i % 4) * 4 + i / 4;
+        *index = ((bits >> (45 - 3 * id)) & 0b111) as usize;
+    }
+    indices
+}
+
+/// Decodes an EAC alpha block, the alpha part of an ETC2 RGBA block, to values in raster order
+pub fn decode_eac_alpha_block(block: &[u8]) -> [u8; 16] {
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = EAC_MODIFIERS[(block[1] & 0xF) as usize];
+    eac_indices(block).map(|index| (base + modifiers[index] * multiplier).clamp(0, 255) as u8)
+}
+
+/// Decodes an unsigned EAC R11 block to 11-bit values in raster order
+pub fn decode_eac_r11_block(block: &[u8]) -> [u16; 16] {
+    let base = block[0] as i32 * 8 + 4;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = EAC_MODIFIERS[(block[1] & 0xF) as usize];
+    eac_indices(block).map(|index| {
+        let modifier = if multiplier == 0 { modifiers[index] } else { modifiers[index] * multiplier * 8 };
+        (base + modifier).clamp(0, 2047) as u16
+    })
+}
+
+/// Decodes an EAC R11 block to 8-bit values in raster order
+pub fn decode_eac_r11_block_u8(block: &[u8]) -> [u8; 16] {
+    decode_eac_r11_block(block).map(|value| ((value as u32 * 255 + 1023) / 2047) as u8)
+}
+
+/// Morton order index of a PVRTC1 block, like the `twiddleUV` function of the PowerVR SDK
+fn pvrtc_twiddle(num_blocks_x: usize, num_blocks_y: usize, x: usize, y: usize) -> usize {
+    let (min_dimension, mut max_value) = if num_blocks_y < num_blocks_x { (num_blocks_y, x) } else { (num_blocks_x, y) };
+    let mut twiddled = 0;
+    let mut src_bit = 1;
+    let mut dst_bit = 1;
+    let mut shift_count = 0;
+    while src_bit < min_dimension {
+        if y & src_bit != 0 {
+            twiddled |= dst_bit;
+        }
+        if x & src_bit != 0 {
+            twiddled |= dst_bit << 1;
+        }
+        src_bit <<= 1;
+        dst_bit <<= 2;
+        shift_count += 1;
+    }
+    max_value >>= shift_count;
+    twiddled | (max_value << (2 * shift_count))
+}
+
+/// Expands color A (`b` false) or color B of a PVRTC1 block to 5-bit RGB and 4-bit alpha
+fn pvrtc_color(color_data: u32, b: bool) -> [i32; 4] {
+    let data = if b { color_data >> 16 } else { color_data & 0xFFFE } as i32;
+    if data & 0x8000 != 0 {
+        let blue = if b { data & 0x1F } else { (data & 0x1E) | ((data & 0x1E) >> 4) };
+        [(data & 0x7C00) >> 10, (data & 0x3E0) >> 5, blue, 0xF]
+    } else {
+        let blue = if b { ((data & 0xF) << 1) | ((data & 0xF) >> 3) } else { ((data & 0xE) << 1) | ((data & 0xE) >> 2) };
+        [((data & 0xF00) >> 7) | ((data & 0xF00) >> 11), ((data & 0xF0) >> 3) | ((data & 0xF0) >> 7), blue, (data & 0x7000) >> 11]
+    }
+}
+
+/// Decodes a PVRTC1 4bpp image to an RGBA image with the same layout as `decode_block_image`
+pub fn decode_pvrtc1_image(image: &basisu::Image<u8>) -> basisu::Image<u8> {
+    let num_blocks_x = image.stride as usize / 8;
+    let num_blocks_y = image.data.len() / image.stride as usize;
+    let block = |x: usize, y: usize| {
+        let offset = 8 * pvrtc_twiddle(num_blocks_x, num_blocks_y, x % num_blocks_x, y % num_blocks_y);
+        let word = |i: usize| u32::from_le_bytes([image.data[i], image.data[i + 1], image.data[i + 2], image.data[i + 3]]);
+        (word(offset), word(offset + 4))
+    };
+
+    let (width, height) = (4 * num_blocks_x, 4 * num_blocks_y);
+    let stride = 4 * width;
+    let mut data = vec![0u8; stride * height];
+    for y in 0..height {
+        for x in 0..width {
+            // The 4 blocks whose centers surround the pixel, and the position relative to the top left one
+            let (px, py) = (x + width - 2, y + height - 2);
+            let (bx, by) = (px / 4, py / 4);
+            let (u, v) = ((px % 4) as i32, (py % 4) as i32);
+            let colors = [block(bx, by).1, block(bx + 1, by).1, block(bx, by + 1).1, block(bx + 1, by + 1).1];
+
+            let upscale = |b: bool| {
+                let [p, q, r, s] = colors.map(|c| pvrtc_color(c, b));
+                let mut result = [0u8; 4];
+                for c in 0..4 {
+                    let top = p[c] * 4 + u * (q[c] - p[c]);
+                    let bottom = r[c] * 4 + u * (s[c] - r[c]);
+                    let value = top * 4 + v * (bottom - top);
+                    result[c] = if c < 3 { (value >> 1) + (value >> 6) } else { value + (value >> 4) } as u8;
+                }
+                result
+            };
+            let (color_a, color_b) = (upscale(false), upscale(true));
+
+            let (modulation_data, color_data) = block(x / 4, y / 4);
+            let bits = (modulation_data >> (2 * ((y % 4) * 4 + x % 4))) & 3;
+            let punch_through = color_data & 1 != 0;
+            let weight = if punch_through { [0, 4, 4, 8][bits as usize] } else { [0, 3, 5, 8][bits as usize] };
+
+            let pixel = &mut data[y * stride + 4 * x..y * stride + 4 * x + 4];
+            for c in 0..4 {
+                pixel[c] = ((color_a[c] as u32 * (8 - weight) + color_b[c] as u32 * weight) / 8) as u8;
+            }
+            if punch_through && bits == 2 {
+                pixel[3] = 0;
+            }
+        }
+    }
+    basisu::Image {
+        w: image.w,
+        h: image.h,
+        stride: stride as u32,
+        y_flipped: image.y_flipped,
+        data,
+    }
+}
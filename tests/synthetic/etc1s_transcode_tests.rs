@@ -0,0 +1,463 @@
+use crate::builder::*;
+use crate::common::*;
+use crate::decoders::*;
+
+use basisu::{
+    BasisFile,
+    Channel,
+    TranscoderTextureFormat,
+};
+use std::convert::TryInto;
+
+fn codebooks() -> Etc1sCodebooks {
+    Etc1sCodebooks {
+        endpoints: vec![
+            ([31, 31, 31], 0),
+            ([0, 0, 0], 1),
+            ([4, 20, 9], 2),
+            ([16, 16, 16], 3),
+            ([28, 3, 12], 4),
+            ([10, 25, 30], 5),
+            ([7, 7, 24], 6),
+            ([19, 11, 2], 7),
+        ],
+        selectors: vec![
+            // Single selector
+            [0x00; 4],
+            [0xAA; 4],
+            // All selectors
+            [0xE4, 0x1B, 0xE4, 0x1B],
+            // Selector ranges 1..=2, 0..=1, 2..=3 and 0..=2
+            [0x99, 0x66, 0x96, 0x69],
+            [0x11, 0x44, 0x14, 0x41],
+            [0xBB, 0xEE, 0xBE, 0xEB],
+            [0x24, 0x18, 0x21, 0x06],
+        ],
+    }
+}
+
+/// ETC1S file where the blocks go through all endpoint and selector combinations.
+///
+/// The alpha slice uses the same blocks in reverse order.
+fn etc1s_file(alpha: bool) -> Vec<u8> {
+    let codebooks = codebooks();
+    etc1s_file_with_size(alpha, codebooks.endpoints.len(), codebooks.selectors.len())
+}
+
+/// ETC1S file where the columns go through the endpoints and the rows through the selectors,
+/// repeating them as needed
+fn etc1s_file_with_size(alpha: bool, num_blocks_x: usize, num_blocks_y: usize) -> Vec<u8> {
+    let codebooks = codebooks();
+    let (num_endpoints, num_selectors) = (codebooks.endpoints.len(), codebooks.selectors.len());
+    let mut blocks: Vec<(u16, u16)> = (0..num_blocks_x * num_blocks_y)
+        .map(|i| ((i % num_blocks_x % num_endpoints) as u16, (i / num_blocks_x % num_selectors) as u16))
+        .collect();
+
+    let mut slice = SliceData {
+        image_index: 0,
+        level_index: 0,
+        flags: 0,
+        orig_width: 4 * num_blocks_x as u16,
+        orig_height: 4 * num_blocks_y as u16,
+        data: codebooks.slice_data(num_blocks_x, num_blocks_y, &blocks),
+    };
+
+    let mut builder = BasisBuilder::new(TEX_FORMAT_ETC1S, TEX_TYPE_2D, 1);
+    builder.flags = HEADER_FLAG_ETC1S;
+    if alpha {
+        builder.flags |= HEADER_FLAG_HAS_ALPHA_SLICES;
+        blocks.reverse();
+        let alpha_slice = SliceData {
+            flags: SLICE_FLAG_HAS_ALPHA,
+            data: codebooks.slice_data(num_blocks_x, num_blocks_y, &blocks),
+            ..slice.clone()
+        };
+        builder.slices = vec![slice, alpha_slice];
+    } else {
+        slice.flags = 0;
+        builder.slices = vec![slice];
+    }
+    builder.etc1s_codebooks = Some(codebooks);
+    builder.build()
+}
+
+/// Returns the largest absolute difference of the channels in each block, and the mean difference of all pixels
+fn rgb_error(a: &basisu::Image<u8>, b: &basisu::Image<u8>) -> (Vec<Vec<u8>>, f64) {
+    channel_error(a, b, 0..3)
+}
+
+fn channel_error(a: &basisu::Image<u8>, b: &basisu::Image<u8>, channels: std::ops::Range<usize>) -> (Vec<Vec<u8>>, f64) {
+    let mut block_errors = vec![vec![0u8; (a.w as usize).div_ceil(4)]; (a.h as usize).div_ceil(4)];
+    let mut sum = 0u64;
+    let mut count = 0;
+    for (y, (row_a, row_b)) in rgba_rows(a).zip(rgba_rows(b)).enumerate() {
+        for (x, (pa, pb)) in row_a.chunks_exact(4).zip(row_b.chunks_exact(4)).enumerate() {
+            for c in channels.clone() {
+                let diff = (pa[c] as i32 - pb[c] as i32).unsigned_abs() as u8;
+                let block_error = &mut block_errors[y / 4][x / 4];
+                *block_error = (*block_error).max(diff);
+                sum += diff as u64;
+                count += 1;
+            }
+        }
+    }
+    (block_errors, sum as f64 / count as f64)
+}
+
+#[test]
+fn test_etc1s_to_bc1() {
+    let bytes = etc1s_file(false);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+    let bc1 = file.transcode(0, 0, TranscoderTextureFormat::Bc1Rgb).unwrap();
+    assert_eq!(bc1.data.len(), 8 * 8 * 7);
+
+    let decoded = decode_block_image(&bc1, 8, decode_bc1_block);
+    let (block_errors, mean) = rgb_error(&rgba, &decoded);
+    assert!(mean <= 2.5, "mean error {}", mean);
+
+    // BC1 colors lie on a line, ETC1S colors of large intensity tables are clamped and don't,
+    // so only smaller intensities can be close everywhere
+    let endpoints = codebooks().endpoints;
+    for row in &block_errors {
+        for (&error, &(_, inten)) in row.iter().zip(&endpoints) {
+            if inten <= 3 {
+                assert!(error <= 6, "error {} with intensity {}", error, inten);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_etc1s_to_bc3() {
+    let bytes = etc1s_file(true);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+    let bc3 = file.transcode(0, 0, TranscoderTextureFormat::Bc3Rgba).unwrap();
+    assert_eq!(bc3.data.len(), 16 * 8 * 7);
+
+    // Same color blocks as BC1
+    let bc1 = file.transcode(0, 0, TranscoderTextureFormat::Bc1Rgb).unwrap();
+    for (bc3_block, bc1_block) in bc3.data.chunks_exact(16).zip(bc1.data.chunks_exact(8)) {
+        assert_eq!(&bc3_block[8..], bc1_block);
+    }
+
+    let decoded = decode_block_image(&bc3, 16, decode_bc3_block);
+    let (block_errors, mean) = channel_error(&rgba, &decoded, 3..4);
+    // Alpha blocks have up to 4 values, BC4 interpolates 6 values between the lowest and highest
+    assert!(mean <= 2.0, "mean error {}", mean);
+    for row in &block_errors {
+        for &error in row {
+            assert!(error <= 10, "alpha error {}", error);
+        }
+    }
+}
+
+#[test]
+fn test_etc1s_to_bc3_opaque() {
+    let bytes = etc1s_file(false);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let bc3 = file.transcode(0, 0, TranscoderTextureFormat::Bc3Rgba).unwrap();
+    for pixel in decode_block_image(&bc3, 16, decode_bc3_block).data.chunks_exact(4) {
+        assert_eq!(pixel[3], 255);
+    }
+}
+
+#[test]
+fn test_etc1s_to_bc7() {
+    let endpoints = codebooks().endpoints;
+    for &alpha in &[false, true] {
+        let bytes = etc1s_file(alpha);
+        let file = BasisFile::new(&bytes).unwrap();
+
+        let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+        let bc7 = file.transcode(0, 0, TranscoderTextureFormat::Bc7Rgba).unwrap();
+        assert_eq!(bc7.data.len(), 16 * 8 * 7);
+
+        let decoded = decode_block_image(&bc7, 16, decode_bc7_block);
+        assert_eq!(basisu::decode_bc7_image(&bc7).data, decoded.data);
+        let (block_errors, mean) = rgb_error(&rgba, &decoded);
+        assert!(mean <= 2.0, "mean error {}", mean);
+        for row in &block_errors {
+            for (&error, &(_, inten)) in row.iter().zip(&endpoints) {
+                if inten <= 3 {
+                    assert!(error <= 6, "error {} with intensity {}", error, inten);
+                }
+            }
+        }
+
+        let (_, alpha_mean) = channel_error(&rgba, &decoded, 3..4);
+        if alpha {
+            // Mode 5 has only 4 alpha weights for up to 4 alpha values
+            assert!(alpha_mean <= 1.5, "mean alpha error {}", alpha_mean);
+        } else {
+            assert_eq!(alpha_mean, 0.0);
+        }
+    }
+}
+
+#[test]
+fn test_etc1s_to_astc() {
+    let endpoints = codebooks().endpoints;
+    for &alpha in &[false, true] {
+        let bytes = etc1s_file(alpha);
+        let file = BasisFile::new(&bytes).unwrap();
+
+        let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+        let astc = file.transcode(0, 0, TranscoderTextureFormat::Astc4x4Rgba).unwrap();
+        assert_eq!(astc.data.len(), 16 * 8 * 7);
+
+        let decoded = decode_block_image(&astc, 16, decode_astc_block);
+        assert_eq!(basisu::decode_astc_image(&astc, 4, 4, false).unwrap().data, decoded.data);
+        let (block_errors, mean) = rgb_error(&rgba, &decoded);
+        assert!(mean <= 2.5, "mean error {}", mean);
+
+        // Blocks with alpha have only 48 endpoint levels
+        let max_error = if alpha { 10 } else { 6 };
+        for row in &block_errors {
+            for (&error, &(_, inten)) in row.iter().zip(&endpoints) {
+                if inten <= 3 {
+                    assert!(error <= max_error, "error {} with intensity {}", error, inten);
+                }
+            }
+        }
+
+        let (_, alpha_mean) = channel_error(&rgba, &decoded, 3..4);
+        if alpha {
+            assert!(alpha_mean <= 2.0, "mean alpha error {}", alpha_mean);
+        } else {
+            assert_eq!(alpha_mean, 0.0);
+        }
+    }
+}
+
+#[test]
+fn test_etc1s_to_etc2() {
+    let bytes = etc1s_file(true);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+    let etc2 = file.transcode(0, 0, TranscoderTextureFormat::Etc2Rgba).unwrap();
+    assert_eq!(etc2.data.len(), 16 * 8 * 7);
+
+    // Color blocks are the ETC1 blocks
+    let etc1 = file.transcode(0, 0, TranscoderTextureFormat::Etc1Rgb).unwrap();
+    for (etc2_block, etc1_block) in etc2.data.chunks_exact(16).zip(etc1.data.chunks_exact(8)) {
+        assert_eq!(&etc2_block[8..], etc1_block);
+    }
+
+    let decoded = decode_block_image(&etc2, 16, |block| {
+        decode_eac_alpha_block(block).map(|alpha| [0, 0, 0, alpha])
+    });
+    let (block_errors, mean) = channel_error(&rgba, &decoded, 3..4);
+    // EAC has 8 values per block for up to 4 alpha values
+    assert!(mean <= 0.5, "mean alpha error {}", mean);
+    for row in &block_errors {
+        for &error in row {
+            assert!(error <= 6, "alpha error {}", error);
+        }
+    }
+
+    let public_decoded = basisu::decode_etc2_rgba_image(&etc2);
+    assert_eq!((public_decoded.w, public_decoded.h, public_decoded.stride), (rgba.w, rgba.h, rgba.stride));
+    // ETC1S is a subset of ETC1, so the colors are exact
+    let (_, mean) = channel_error(&rgba, &public_decoded, 0..3);
+    assert_eq!(mean, 0.0);
+    for (pixel, expected) in public_decoded.data.chunks_exact(4).zip(decoded.data.chunks_exact(4)) {
+        assert_eq!(pixel[3], expected[3]);
+    }
+}
+
+#[test]
+fn test_etc1s_to_etc2_opaque() {
+    let bytes = etc1s_file(false);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let etc2 = file.transcode(0, 0, TranscoderTextureFormat::Etc2Rgba).unwrap();
+    for block in etc2.data.chunks_exact(16) {
+        assert_eq!(decode_eac_alpha_block(block), [255; 16]);
+        assert_eq!(basisu::decode_eac_alpha_block(block[..8].try_into().unwrap()), [255; 16]);
+    }
+}
+
+#[test]
+fn test_etc1s_to_eac_r11() {
+    let channels = [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+    for &alpha in &[false, true] {
+        let bytes = etc1s_file(alpha);
+        let file = BasisFile::new(&bytes).unwrap();
+        let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+
+        for (c, &channel) in channels.iter().enumerate() {
+            let r11 = file.transcode(0, 0, TranscoderTextureFormat::EacR11 { channel }).unwrap();
+            assert_eq!(r11.data.len(), 8 * 8 * 7);
+
+            let decoded = decode_block_image(&r11, 8, |block| decode_eac_r11_block_u8(block).map(|value| [value; 4]));
+            // The public decoder puts the values in the red channel
+            let public_decoded = basisu::decode_eac_r11_image(&r11);
+            for (pixel, expected) in public_decoded.data.chunks_exact(4).zip(decoded.data.chunks_exact(4)) {
+                assert_eq!(pixel[0], expected[0]);
+            }
+            let (block_errors, mean) = channel_error(&rgba, &decoded, c..c + 1);
+            assert!(mean <= 0.5, "mean error {} of {:?}", mean, channel);
+            for row in &block_errors {
+                for &error in row {
+                    assert!(error <= 6, "error {} of {:?}", error, channel);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_etc1s_to_eac_rg11() {
+    let bytes = etc1s_file(true);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let channels = [Channel::Alpha, Channel::Red];
+    let rg11 = file.transcode(0, 0, TranscoderTextureFormat::EacRg11 { channels }).unwrap();
+    assert_eq!(rg11.data.len(), 16 * 8 * 7);
+
+    // Each half is the R11 block of its channel
+    let r11_alpha = file.transcode(0, 0, TranscoderTextureFormat::EacR11 { channel: Channel::Alpha }).unwrap();
+    let r11_red = file.transcode(0, 0, TranscoderTextureFormat::EacR11 { channel: Channel::Red }).unwrap();
+    for ((rg11_block, alpha_block), red_block) in rg11.data.chunks_exact(16).zip(r11_alpha.data.chunks_exact(8)).zip(r11_red.data.chunks_exact(8)) {
+        assert_eq!(&rg11_block[..8], alpha_block);
+        assert_eq!(&rg11_block[8..], red_block);
+    }
+
+    let decoded = basisu::decode_eac_rg11_image(&rg11);
+    let decoded_alpha = basisu::decode_eac_r11_image(&r11_alpha);
+    let decoded_red = basisu::decode_eac_r11_image(&r11_red);
+    for ((pixel, alpha), red) in decoded.data.chunks_exact(4).zip(decoded_alpha.data.chunks_exact(4)).zip(decoded_red.data.chunks_exact(4)) {
+        assert_eq!(pixel, [alpha[0], red[0], 0, 255]);
+    }
+}
+
+#[test]
+fn test_etc1s_to_pvrtc1() {
+    // Colors are interpolated between neighboring blocks, so repeating blocks are reproduced well,
+    // as long as the colors lie on a line like with the smaller intensity tables. The modulation
+    // weights of 3/8 and 5/8 still miss the unevenly spaced middle colors a bit.
+    for &(endpoint, selector) in &[(2, 0), (3, 0), (2, 2), (3, 2), (2, 6), (3, 6)] {
+        let mut builder = BasisBuilder::new(TEX_FORMAT_ETC1S, TEX_TYPE_2D, 1);
+        builder.flags = HEADER_FLAG_ETC1S;
+        builder.slices = vec![etc1s_solid_slice(&codebooks(), 0, 0, 32, 32, endpoint, selector)];
+        builder.etc1s_codebooks = Some(codebooks());
+        let bytes = builder.build();
+        let file = BasisFile::new(&bytes).unwrap();
+
+        let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+        let pvrtc = file.transcode(0, 0, TranscoderTextureFormat::Pvrtc1Rgb).unwrap();
+        assert_eq!(pvrtc.data.len(), 8 * 8 * 8);
+
+        let (block_errors, mean) = rgb_error(&rgba, &decode_pvrtc1_image(&pvrtc));
+        assert!(mean <= 4.0, "mean error {} of endpoint {} and selector {}", mean, endpoint, selector);
+        for row in &block_errors {
+            for &error in row {
+                assert!(error <= 8, "error {} of endpoint {} and selector {}", error, endpoint, selector);
+            }
+        }
+    }
+
+    // Unrelated neighboring blocks only get close, but alpha stays exact without alpha slices
+    for &alpha in &[false, true] {
+        let bytes = etc1s_file_with_size(alpha, 16, 16);
+        let file = BasisFile::new(&bytes).unwrap();
+
+        let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+        for &format in &[TranscoderTextureFormat::Pvrtc1Rgb, TranscoderTextureFormat::Pvrtc1Rgba] {
+            let pvrtc = file.transcode(0, 0, format).unwrap();
+            assert_eq!(pvrtc.data.len(), 16 * 16 * 8);
+
+            let decoded = decode_pvrtc1_image(&pvrtc);
+            let (_, mean) = rgb_error(&rgba, &decoded);
+            assert!(mean <= 40.0, "mean error {} of {:?}", mean, format);
+
+            let (_, alpha_mean) = channel_error(&rgba, &decoded, 3..4);
+            if !alpha {
+                assert_eq!(alpha_mean, 0.0);
+            } else if format == TranscoderTextureFormat::Pvrtc1Rgba {
+                assert!(alpha_mean <= 45.0, "mean alpha error {}", alpha_mean);
+            } else {
+                assert!(rgba_rows(&decoded).all(|row| row.chunks_exact(4).all(|pixel| pixel[3] == 255)));
+            }
+        }
+    }
+
+    // Only square power of two dimensions of at least 2x2 blocks are supported, the original
+    // file has 8x7 blocks
+    let bytes = etc1s_file(false);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert!(matches!(
+        file.transcode(0, 0, TranscoderTextureFormat::Pvrtc1Rgb),
+        Err(basisu::Error::UnsupportedDimensions { width: 32, height: 28 })
+    ));
+    for &(num_blocks_x, num_blocks_y) in &[(8, 4), (4, 8), (1, 1)] {
+        let bytes = etc1s_file_with_size(false, num_blocks_x, num_blocks_y);
+        let file = BasisFile::new(&bytes).unwrap();
+        assert!(matches!(
+            file.transcode(0, 0, TranscoderTextureFormat::Pvrtc1Rgba),
+            Err(basisu::Error::UnsupportedDimensions { width, height })
+                if (width, height) == (4 * num_blocks_x as u32, 4 * num_blocks_y as u32)
+        ));
+    }
+}
+
+#[test]
+fn test_etc1s_to_packed_16() {
+    let bytes = etc1s_file(true);
+    let file = BasisFile::new(&bytes).unwrap();
+    let rgba = file.transcode(0, 0, TranscoderTextureFormat::Rgba32).unwrap();
+
+    // Shift and bit count of red, green, blue and alpha
+    let formats = [
+        (TranscoderTextureFormat::Rgb565 { dither: false }, TranscoderTextureFormat::Rgb565 { dither: true }, [(11, 5), (5, 6), (0, 5), (0, 0)]),
+        (TranscoderTextureFormat::Bgr565 { dither: false }, TranscoderTextureFormat::Bgr565 { dither: true }, [(0, 5), (5, 6), (11, 5), (0, 0)]),
+        (TranscoderTextureFormat::Rgba4444 { dither: false }, TranscoderTextureFormat::Rgba4444 { dither: true }, [(12, 4), (8, 4), (4, 4), (0, 4)]),
+    ];
+    for &(format, dithered_format, layout) in &formats {
+        let image = file.transcode(0, 0, format).unwrap();
+        let dithered = file.transcode(0, 0, dithered_format).unwrap();
+        assert_eq!(image.data.len(), 2 * 32 * 28);
+        assert_eq!((image.w, image.h, image.stride), (rgba.w, rgba.h, rgba.stride / 2));
+
+        for ((expected, pixel), dithered_pixel) in rgba.data.chunks_exact(4).zip(image.data.chunks_exact(2)).zip(dithered.data.chunks_exact(2)) {
+            let pixel = u16::from_le_bytes([pixel[0], pixel[1]]) as u32;
+            let dithered_pixel = u16::from_le_bytes([dithered_pixel[0], dithered_pixel[1]]) as u32;
+            for (c, &(shift, bits)) in layout.iter().enumerate().filter(|(_, &(_, bits))| bits > 0) {
+                let max = (1 << bits) - 1;
+                // Distance to the expected value in 1/255 of a quantization step
+                let distance = |value: u32| (((value >> shift) & max) as i32 * 255 - expected[c] as i32 * max as i32).abs();
+                assert!(distance(pixel) <= 255 / 2 + 1, "{:?} {:?} {:04X}", format, expected, pixel);
+                assert!(distance(dithered_pixel) < 255, "{:?} {:?} {:04X}", dithered_format, expected, dithered_pixel);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_etc1s_to_atc() {
+    let bytes = etc1s_file(true);
+    let file = BasisFile::new(&bytes).unwrap();
+
+    let bc3 = file.transcode(0, 0, TranscoderTextureFormat::Bc3Rgba).unwrap();
+    let atc_rgba = file.transcode(0, 0, TranscoderTextureFormat::AtcRgba).unwrap();
+    let atc_rgb = file.transcode(0, 0, TranscoderTextureFormat::AtcRgb).unwrap();
+    assert_eq!(atc_rgba.data.len(), 16 * 8 * 7);
+    assert_eq!(atc_rgb.data.len(), 8 * 8 * 7);
+
+    // ATC shares the encoding with BC1, only color 0 has one bit less of green
+    for ((bc3_block, atc_rgba_block), atc_rgb_block) in bc3.data.chunks_exact(16).zip(atc_rgba.data.chunks_exact(16)).zip(atc_rgb.data.chunks_exact(8)) {
+        assert_eq!(&bc3_block[0..8], &atc_rgba_block[0..8]);
+        assert_eq!(&atc_rgba_block[8..16], atc_rgb_block);
+
+        for (bc1_pixel, atc_pixel) in decode_bc1_block(&bc3_block[8..16]).iter().zip(decode_atc_block(atc_rgb_block).iter()) {
+            assert_eq!((bc1_pixel[0], bc1_pixel[2]), (atc_pixel[0], atc_pixel[2]), "{:02X?} {:02X?}", bc3_block, atc_rgb_block);
+            assert!((bc1_pixel[1] as i32 - atc_pixel[1] as i32).abs() <= 4, "{:02X?} {:02X?}", bc3_block, atc_rgb_block);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::builder::*;
+use crate::common::*;
+
+use std::path::Path;
+
+use basisu::{
+    BasisFile,
+    Channel,
+    TranscoderTextureFormat,
+    VideoDecoder,
+};
+
+const MUTATIONS_PER_FILE: usize = 2000;
+
+/// Mutated dimensions can describe huge but valid images, files with larger slices are rejected
+const MAX_BLOCK_COUNT: u32 = 1 << 12;
+
+const FORMATS: [TranscoderTextureFormat; 19] = [
+    TranscoderTextureFormat::Etc1Rgb,
+    TranscoderTextureFormat::Bc1Rgb,
+    TranscoderTextureFormat::Bc3Rgba,
+    TranscoderTextureFormat::Etc2Rgba,
+    TranscoderTextureFormat::Bc7Rgba,
+    TranscoderTextureFormat::Astc4x4Rgba,
+    TranscoderTextureFormat::EacR11 { channel: Channel::Green },
+    TranscoderTextureFormat::EacRg11 { channels: [Channel::Red, Channel::Alpha] },
+    TranscoderTextureFormat::Bc4R { channel: Channel::Red },
+    TranscoderTextureFormat::Bc5Rg { channels: [Channel::Red, Channel::Green] },
+    TranscoderTextureFormat::Pvrtc1Rgb,
+    TranscoderTextureFormat::Pvrtc1Rgba,
+    TranscoderTextureFormat::Rgb565 { dither: false },
+    TranscoderTextureFormat::Bgr565 { dither: true },
+    TranscoderTextureFormat::Rgba4444 { dither: true },
+    TranscoderTextureFormat::AtcRgb,
+    TranscoderTextureFormat::AtcRgba,
+    TranscoderTextureFormat::Rgba32,
+    TranscoderTextureFormat::Uastc4x4,
+];
+
+/// Xorshift generator, so failures are reproducible without extra dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+fn uastc_file(rng: &mut Rng) -> Vec<u8> {
+    // Random blocks cover all modes, including invalid ones
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_2D, 1);
+    for (level, &(w, h)) in [(16u16, 12u16), (8, 6), (4, 3)].iter().enumerate() {
+        let block_count = (w as usize).div_ceil(4) * (h as usize).div_ceil(4);
+        builder.slices.push(SliceData {
+            image_index: 0,
+            level_index: level as u8,
+            flags: SLICE_FLAG_HAS_ALPHA,
+            orig_width: w,
+            orig_height: h,
+            data: rng.bytes(16 * block_count),
+        });
+    }
+    builder.build()
+}
+
+fn uastc_cubemap_file() -> Vec<u8> {
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_CUBEMAP_ARRAY, 6);
+    builder.slices = (0..6).map(|face| uastc_solid_slice(face, 0, 4, 4, [face as u8 * 40, 0, 0, 255])).collect();
+    builder.build()
+}
+
+fn etc1s_file(rng: &mut Rng, tex_type: u8) -> Vec<u8> {
+    let codebooks = Etc1sCodebooks {
+        endpoints: (0..5).map(|_| ([rng.below(32) as u8, rng.below(32) as u8, rng.below(32) as u8], rng.below(8) as u8)).collect(),
+        selectors: (0..3).map(|_| [rng.next() as u8, rng.next() as u8, rng.next() as u8, rng.next() as u8]).collect(),
+    };
+    let (image_count, level_sizes): (u32, &[(u16, u16)]) = match tex_type {
+        TEX_TYPE_VIDEO_FRAMES => (4, &[(12, 8)]),
+        _ => (1, &[(12, 8), (6, 4), (3, 2)]),
+    };
+
+    let mut slices = Vec::new();
+    let mut prev_frame = vec![None; 2];
+    for image in 0..image_count {
+        for (level, &(w, h)) in level_sizes.iter().enumerate() {
+            let (num_blocks_x, num_blocks_y) = ((w as usize).div_ceil(4), (h as usize).div_ceil(4));
+            for (alpha, prev_frame) in prev_frame.iter_mut().enumerate() {
+                let blocks: Vec<_> = (0..num_blocks_x * num_blocks_y)
+                    .map(|i| match prev_frame {
+                        // Keep some blocks so P-frames reuse them
+                        Some(prev) if rng.below(2) == 0 => (prev as &Vec<(u16, u16)>)[i],
+                        _ => (rng.below(5) as u16, rng.below(3) as u16),
+                    })
+                    .collect();
+                let iframe = image == 0;
+                let data = codebooks.video_slice_data(num_blocks_x, num_blocks_y, &blocks, prev_frame.as_deref().filter(|_| !iframe));
+                slices.push(SliceData {
+                    image_index: image,
+                    level_index: level as u8,
+                    flags: if alpha == 1 { SLICE_FLAG_HAS_ALPHA } else { 0 } | if iframe { SLICE_FLAG_FRAME_IS_I_FRAME } else { 0 },
+                    orig_width: w,
+                    orig_height: h,
+                    data,
+                });
+                if tex_type == TEX_TYPE_VIDEO_FRAMES {
+                    *prev_frame = Some(blocks);
+                }
+            }
+        }
+    }
+
+    let mut builder = BasisBuilder::new(TEX_FORMAT_ETC1S, tex_type, image_count);
+    builder.flags = HEADER_FLAG_ETC1S | HEADER_FLAG_HAS_ALPHA_SLICES;
+    builder.us_per_frame = 1000;
+    builder.etc1s_codebooks = Some(codebooks);
+    builder.slices = slices;
+    builder.build()
+}
+
+fn mutate(rng: &mut Rng, bytes: &mut Vec<u8>) {
+    for _ in 0..1 + rng.below(4) {
+        if bytes.is_empty() {
+            return;
+        }
+        let i = rng.below(bytes.len());
+        match rng.below(10) {
+            0..=5 => bytes[i] ^= 1 << rng.below(8),
+            6 | 7 => bytes[i] = rng.next() as u8,
+            8 => bytes[i] = if rng.below(2) == 0 { 0 } else { 0xFF },
+            _ => bytes.truncate(i),
+        }
+    }
+    // Most of the time make the checksums match, so the mutation reaches the decoders
+    if rng.below(8) != 0 {
+        fix_checksums(bytes);
+    }
+}
+
+fn fix_checksums(bytes: &mut [u8]) {
+    const HEADER_SIZE: usize = 77;
+    const SLICE_DESC_SIZE: usize = 23;
+    if bytes.len() < HEADER_SIZE {
+        return;
+    }
+    let read_uint = |bytes: &[u8], ofs: usize, len: usize| {
+        bytes[ofs..ofs + len].iter().rev().fold(0, |v, &b| v << 8 | b as usize)
+    };
+    let total_slices = read_uint(bytes, 14, 3);
+    let slice_desc_file_ofs = read_uint(bytes, 65, 4);
+    for slice in 0..total_slices {
+        let desc = slice_desc_file_ofs + slice * SLICE_DESC_SIZE;
+        if desc + SLICE_DESC_SIZE > bytes.len() {
+            break;
+        }
+        let file_ofs = read_uint(bytes, desc + 13, 4);
+        let file_size = read_uint(bytes, desc + 17, 4);
+        if let Some(data) = bytes.get(file_ofs..file_ofs.saturating_add(file_size)) {
+            let slice_crc16 = crc16(data);
+            bytes[desc + 21..desc + 23].copy_from_slice(&slice_crc16.to_le_bytes());
+        }
+    }
+    let data_crc16 = crc16(&bytes[HEADER_SIZE..]);
+    bytes[12..14].copy_from_slice(&data_crc16.to_le_bytes());
+    let header_crc16 = crc16(&bytes[8..HEADER_SIZE]);
+    bytes[6..8].copy_from_slice(&header_crc16.to_le_bytes());
+}
+
+/// Runs the file through everything which can fail on malformed input, errors are expected
+fn exercise(bytes: &[u8]) {
+    let file = match BasisFile::with_max_block_count(bytes, MAX_BLOCK_COUNT) {
+        Ok(file) => file,
+        Err(_) => return,
+    };
+
+    for image in 0..file.image_count() {
+        for info in file.image_levels(image) {
+            for &format in &FORMATS {
+                let _ = file.transcode(image, info.level_index, format);
+            }
+        }
+    }
+
+    let _ = file.validate();
+    let _ = file.transcode_layers(0, TranscoderTextureFormat::Rgba32);
+    let _ = file.transcode_cubemap(0, 0, TranscoderTextureFormat::Rgba32);
+    if let Ok(mut decoder) = VideoDecoder::new(file, 0) {
+        while let Ok(Some(_)) = decoder.decode_next_frame(TranscoderTextureFormat::Etc1Rgb) {}
+        let _ = decoder.seek(decoder.frame_count() / 2);
+        let _ = decoder.decode_next_frame(TranscoderTextureFormat::Rgba32);
+    }
+}
+
+fn fuzz(name: &str, original: &[u8], seed: u64) {
+    // The unmodified file has to decode, otherwise the mutations don't test much
+    let file = BasisFile::new(original).unwrap();
+    assert!(file.transcode(0, 0, TranscoderTextureFormat::Rgba32).is_ok(), "{}", name);
+
+    let mut rng = Rng(seed);
+    for mutation in 0..MUTATIONS_PER_FILE {
+        let mut bytes = original.to_vec();
+        mutate(&mut rng, &mut bytes);
+        let result = std::panic::catch_unwind(|| exercise(&bytes));
+        assert!(result.is_ok(), "{}: mutation {} panicked", name, mutation);
+    }
+}
+
+#[test]
+fn fuzz_uastc() {
+    let mut rng = Rng(0x1234_5678);
+    fuzz("uastc", &uastc_file(&mut rng), 1);
+    fuzz("uastc cubemap", &uastc_cubemap_file(), 2);
+}
+
+#[test]
+fn fuzz_etc1s() {
+    let mut rng = Rng(0x8765_4321);
+    fuzz("etc1s", &etc1s_file(&mut rng, TEX_TYPE_2D), 3);
+    fuzz("etc1s video", &etc1s_file(&mut rng, TEX_TYPE_VIDEO_FRAMES), 4);
+}
+
+#[test]
+fn fuzz_corpus() {
+    // Uses a few files of the texture corpus, when it's available
+    for (corpus, seed) in [(UASTC_CORPUS, 5), (ETC1S_CORPUS, 6)].iter() {
+        let dir = Path::new(corpus).join(DIR_RGBA);
+        let textures = match list_textures(&dir) {
+            Ok(textures) => textures,
+            Err(_) => continue,
+        };
+        for texture in textures.iter().take(2) {
+            let case = TestCase::new(&dir, texture);
+            let bytes = std::fs::read(&case.basis).unwrap();
+            fuzz(texture, &bytes, *seed);
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Tests on synthetic .basis files, which don't need the texture corpus
+
+#[path = "../common.rs"]
+mod common;
+mod builder;
+mod decoders;
+
+mod basis_file_tests;
+mod etc1s_transcode_tests;
+mod fuzz_tests;
+mod validation_tests;
+mod video_tests;
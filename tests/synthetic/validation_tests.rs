@@ -0,0 +1,139 @@
+use crate::builder::*;
+
+use basisu::{
+    BasisFile,
+    Error,
+    TranscoderTextureFormat,
+};
+
+fn codebooks() -> Etc1sCodebooks {
+    Etc1sCodebooks {
+        endpoints: vec![([31, 31, 31], 0), ([0, 0, 0], 1)],
+        selectors: vec![[0x00; 4], [0xE4; 4]],
+    }
+}
+
+/// 8x8 ETC1S file with two images, the second one uses the given predictions
+fn etc1s_file(preds: &[u32]) -> Vec<u8> {
+    let codebooks = codebooks();
+    let blocks = [(0, 0), (0, 1), (0, 1), (0, 0)];
+    let slices = vec![
+        etc1s_solid_slice(&codebooks, 0, 0, 8, 8, 1, 0),
+        SliceData {
+            image_index: 1,
+            level_index: 0,
+            flags: 0,
+            orig_width: 8,
+            orig_height: 8,
+            data: codebooks.slice_data_with_preds(2, 2, &blocks, preds),
+        },
+    ];
+
+    let mut builder = BasisBuilder::new(TEX_FORMAT_ETC1S, TEX_TYPE_2D, 2);
+    builder.flags = HEADER_FLAG_ETC1S;
+    builder.etc1s_codebooks = Some(codebooks);
+    builder.slices = slices;
+    builder.build()
+}
+
+/// 8x8 UASTC file with two images, block (1, 0) of the second one is replaced
+fn uastc_file(block: [u8; 16]) -> Vec<u8> {
+    let mut slice = uastc_solid_slice(1, 0, 8, 8, [10, 20, 30, 255]);
+    slice.data[16..32].copy_from_slice(&block);
+
+    let mut builder = BasisBuilder::new(TEX_FORMAT_UASTC4X4, TEX_TYPE_2D, 2);
+    builder.slices = vec![
+        uastc_solid_slice(0, 0, 8, 8, [255, 0, 0, 255]),
+        slice,
+    ];
+    builder.build()
+}
+
+fn assert_illegal_encoding(result: basisu::Result<()>, expected: (u32, u32, u32, &str)) {
+    match result {
+        Err(Error::IllegalEncoding { slice, block_x, block_y, reason }) => {
+            assert_eq!((slice, block_x, block_y, reason), expected);
+        }
+        other => panic!("Expected an illegal encoding, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_valid_files() {
+    let bytes = etc1s_file(&[PRED_DELTA, PRED_LEFT, PRED_UP, PRED_DELTA]);
+    BasisFile::new(&bytes).unwrap().validate().unwrap();
+
+    let bytes = uastc_file(uastc_solid_block([0, 0, 0, 0]));
+    BasisFile::new(&bytes).unwrap().validate().unwrap();
+}
+
+#[test]
+fn test_validate_etc1s_edge_predictions() {
+    let bytes = etc1s_file(&[PRED_DELTA, PRED_UP, PRED_DELTA, PRED_DELTA]);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_illegal_encoding(file.validate(), (1, 1, 0, "block on the top edge predicts endpoint from above"));
+    assert!(file.transcode(1, 0, TranscoderTextureFormat::Rgba32).is_err());
+
+    let bytes = etc1s_file(&[PRED_DELTA, PRED_DELTA, PRED_LEFT, PRED_DELTA]);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_illegal_encoding(file.validate(), (1, 0, 1, "block on the left edge predicts endpoint from the left"));
+}
+
+#[test]
+fn test_validate_uastc_invalid_mode() {
+    // Mode code 69 doesn't belong to any mode
+    let mut block = [0u8; 16];
+    block[0] = 0x45;
+
+    let bytes = uastc_file(block);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_illegal_encoding(file.validate(), (1, 1, 0, "invalid mode"));
+
+    // Transcoding is lenient and emits an error block instead
+    file.transcode(1, 0, TranscoderTextureFormat::Rgba32).unwrap();
+}
+
+#[test]
+fn test_validate_uastc_invalid_pattern() {
+    // Mode 2 followed by 15 bits of transcoding flags and pattern 31, only 30 patterns exist
+    let block = (0b11101u128 | 31 << 20).to_le_bytes();
+
+    let bytes = uastc_file(block);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_illegal_encoding(file.validate(), (1, 1, 0, "pattern index out of range"));
+}
+
+#[test]
+fn test_validate_uastc_invalid_compsel() {
+    // Mode 6 is RGB with two planes, component selector 3 would put the missing alpha on the second plane
+    let block = (0b11011u128 | 3 << 20).to_le_bytes();
+
+    let bytes = uastc_file(block);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_illegal_encoding(file.validate(), (1, 1, 0, "component selector out of range"));
+
+    // Transcoding agrees and emits a magenta error block
+    let decoded = file.transcode(1, 0, TranscoderTextureFormat::Rgba32).unwrap();
+    assert_eq!(&decoded.data[16..20], &[255, 0, 255, 255]);
+
+    let bytes = uastc_file((0b11011u128 | 2 << 20).to_le_bytes());
+    BasisFile::new(&bytes).unwrap().validate().unwrap();
+}
+
+#[test]
+fn test_validate_uastc_solid_etc1_color() {
+    // Red of the ETC1 base color at bit 43 is 16, only 4 bits are allowed without the differential bit
+    let block = u128::from_le_bytes(uastc_solid_block([10, 20, 30, 255])) | 16 << 43;
+
+    let bytes = uastc_file(block.to_le_bytes());
+    let file = BasisFile::new(&bytes).unwrap();
+    assert_illegal_encoding(file.validate(), (1, 1, 0, "solid color ETC1 base color out of range"));
+
+    // The ETC1 block can't hold the color and is zeroed
+    let decoded = file.transcode(1, 0, TranscoderTextureFormat::Etc1Rgb).unwrap();
+    assert_eq!(&decoded.data[8..16], &[0; 8]);
+
+    // With the differential bit set the color has 5 bits
+    let bytes = uastc_file((block | 1 << 37).to_le_bytes());
+    BasisFile::new(&bytes).unwrap().validate().unwrap();
+}
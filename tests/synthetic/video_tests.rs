@@ -0,0 +1,174 @@
+use crate::builder::*;
+
+use basisu::{
+    BasisFile,
+    Error,
+    TextureType,
+    TranscoderTextureFormat,
+    VideoDecoder,
+};
+
+const US_PER_FRAME: u32 = 33_333;
+
+type Blocks = [(u16, u16); 4];
+
+/// Blocks of a 2x2 block video as `(endpoint_index, selector_index)`, paired with the I-frame flag
+const FRAMES: [(Blocks, bool); 6] = [
+    ([(0, 0), (1, 0), (2, 1), (3, 0)], true),
+    ([(1, 0), (1, 0), (2, 1), (3, 0)], false),
+    ([(1, 0), (1, 0), (2, 1), (0, 1)], false),
+    ([(2, 1), (2, 1), (2, 1), (2, 1)], true),
+    ([(2, 1), (3, 1), (2, 1), (2, 1)], false),
+    ([(2, 1), (3, 1), (0, 0), (2, 1)], false),
+];
+
+fn alpha_blocks(blocks: &Blocks) -> Blocks {
+    let mut alpha = *blocks;
+    alpha.rotate_left(1);
+    alpha
+}
+
+fn codebooks() -> Etc1sCodebooks {
+    Etc1sCodebooks {
+        endpoints: vec![([31, 31, 31], 0), ([0, 0, 0], 1), ([0, 31, 0], 2), ([20, 5, 10], 7)],
+        selectors: vec![[0x00; 4], [0xE4; 4]],
+    }
+}
+
+/// Builds the video, or with `as_video` set to false the same frames as independent 2D images
+fn video_file(as_video: bool, first_iframe: bool) -> Vec<u8> {
+    let codebooks = codebooks();
+    let mut slices = Vec::new();
+    let mut prev_frame: Option<(Blocks, Blocks)> = None;
+    for (frame, (blocks, iframe)) in FRAMES.iter().enumerate() {
+        let iframe = *iframe && (frame != 0 || first_iframe);
+        let alpha = alpha_blocks(blocks);
+        let (prev_rgb, prev_alpha) = match prev_frame {
+            Some((rgb, alpha)) if as_video && !iframe => (Some(rgb), Some(alpha)),
+            _ => (None, None),
+        };
+        let flags = if iframe { SLICE_FLAG_FRAME_IS_I_FRAME } else { 0 };
+        slices.push(SliceData {
+            image_index: frame as u32,
+            level_index: 0,
+            flags,
+            orig_width: 8,
+            orig_height: 8,
+            data: codebooks.video_slice_data(2, 2, blocks, prev_rgb.as_ref().map(|b| &b[..])),
+        });
+        slices.push(SliceData {
+            image_index: frame as u32,
+            level_index: 0,
+            flags: flags | SLICE_FLAG_HAS_ALPHA,
+            orig_width: 8,
+            orig_height: 8,
+            data: codebooks.video_slice_data(2, 2, &alpha, prev_alpha.as_ref().map(|b| &b[..])),
+        });
+        prev_frame = Some((*blocks, alpha));
+    }
+
+    let tex_type = if as_video { TEX_TYPE_VIDEO_FRAMES } else { TEX_TYPE_2D };
+    let mut builder = BasisBuilder::new(TEX_FORMAT_ETC1S, tex_type, FRAMES.len() as u32);
+    builder.flags = HEADER_FLAG_ETC1S | HEADER_FLAG_HAS_ALPHA_SLICES;
+    builder.us_per_frame = if as_video { US_PER_FRAME } else { 0 };
+    builder.etc1s_codebooks = Some(codebooks);
+    builder.slices = slices;
+    builder.build()
+}
+
+fn expected_frames(format: TranscoderTextureFormat) -> Vec<Vec<u8>> {
+    basisu::transcode(&video_file(false, true), format).unwrap()
+        .into_iter()
+        .map(|mut levels| levels.remove(0).data)
+        .collect()
+}
+
+#[test]
+fn test_video_sequential_decoding() {
+    let bytes = video_file(true, true);
+
+    for &format in &[TranscoderTextureFormat::Rgba32, TranscoderTextureFormat::Etc1Rgb] {
+        let expected = expected_frames(format);
+        let mut decoder = VideoDecoder::new(BasisFile::new(&bytes).unwrap(), 0).unwrap();
+        assert_eq!(decoder.frame_count(), FRAMES.len() as u32);
+        assert_eq!(decoder.us_per_frame(), US_PER_FRAME);
+
+        for expected_frame in &expected {
+            let frame = decoder.decode_next_frame(format).unwrap().unwrap();
+            assert_eq!(&frame.data, expected_frame);
+        }
+        assert!(decoder.decode_next_frame(format).unwrap().is_none());
+    }
+}
+
+#[test]
+fn test_video_random_access() {
+    let bytes = video_file(true, true);
+    let file = BasisFile::new(&bytes).unwrap();
+    let expected = expected_frames(TranscoderTextureFormat::Rgba32);
+
+    for frame in (0..FRAMES.len()).rev() {
+        let decoded = file.transcode(frame as u32, 0, TranscoderTextureFormat::Rgba32).unwrap();
+        assert_eq!(decoded.data, expected[frame], "frame: {}", frame);
+    }
+}
+
+#[test]
+fn test_video_seek() {
+    let bytes = video_file(true, true);
+    let mut decoder = VideoDecoder::new(BasisFile::new(&bytes).unwrap(), 0).unwrap();
+    let expected = expected_frames(TranscoderTextureFormat::Rgba32);
+
+    for &frame in &[2, 5, 4, 1, 3, 5, 0] {
+        decoder.seek(frame).unwrap();
+        assert_eq!(decoder.next_frame(), frame);
+        let decoded = decoder.decode_next_frame(TranscoderTextureFormat::Rgba32).unwrap().unwrap();
+        assert_eq!(decoded.data, expected[frame as usize], "frame: {}", frame);
+    }
+
+    decoder.seek(FRAMES.len() as u32).unwrap();
+    assert!(decoder.decode_next_frame(TranscoderTextureFormat::Rgba32).unwrap().is_none());
+    assert!(matches!(decoder.seek(FRAMES.len() as u32 + 1), Err(Error::ImageNotFound { .. })));
+}
+
+#[test]
+fn test_video_seek_after_error() {
+    let bytes = video_file(true, true);
+    let mut decoder = VideoDecoder::new(BasisFile::new(&bytes).unwrap(), 0).unwrap();
+    let expected = expected_frames(TranscoderTextureFormat::Rgba32);
+
+    decoder.decode_next_frame(TranscoderTextureFormat::Rgba32).unwrap().unwrap();
+    assert!(matches!(
+        decoder.decode_next_frame(TranscoderTextureFormat::Uastc4x4),
+        Err(Error::UnsupportedTextureFormat(TranscoderTextureFormat::Uastc4x4))
+    ));
+    assert_eq!(decoder.next_frame(), 1);
+
+    // The state of the failed P-frame is rebuilt from the I-frame before it
+    decoder.seek(1).unwrap();
+    let decoded = decoder.decode_next_frame(TranscoderTextureFormat::Rgba32).unwrap().unwrap();
+    assert_eq!(decoded.data, expected[1]);
+}
+
+#[test]
+fn test_video_without_iframe() {
+    let bytes = video_file(true, false);
+    let file = BasisFile::new(&bytes).unwrap();
+    assert!(matches!(file.transcode(1, 0, TranscoderTextureFormat::Rgba32), Err(Error::InvalidSlice(_))));
+    // Frames after the next I-frame can still be decoded
+    assert!(file.transcode(4, 0, TranscoderTextureFormat::Rgba32).is_ok());
+
+    let mut decoder = VideoDecoder::new(BasisFile::new(&bytes).unwrap(), 0).unwrap();
+    assert!(matches!(decoder.decode_next_frame(TranscoderTextureFormat::Rgba32), Err(Error::InvalidSlice(_))));
+    decoder.seek(3).unwrap();
+    assert!(decoder.decode_next_frame(TranscoderTextureFormat::Rgba32).is_ok());
+}
+
+#[test]
+fn test_video_decoder_requires_video() {
+    let bytes = video_file(false, true);
+    assert!(matches!(
+        VideoDecoder::new(BasisFile::new(&bytes).unwrap(), 0),
+        Err(Error::UnsupportedTextureType(TextureType::Type2D))
+    ));
+}